@@ -6,6 +6,7 @@ mod utils;
 
 use cast::{Cast, SimpleCast, TxBuilder};
 use foundry_config::Config;
+use foundry_utils::SignaturesCache;
 mod opts;
 use cast::InterfacePath;
 use ethers::{
@@ -21,7 +22,7 @@ use ethers::{
     utils::get_contract_address,
 };
 use opts::{
-    cast::{Opts, Subcommands, WalletSubcommands},
+    cast::{Opts, SignaturesSubcommands, Subcommands, WalletSubcommands},
     EthereumOpts, WalletType,
 };
 use rayon::prelude::*;
@@ -773,6 +774,20 @@ async fn main() -> eyre::Result<()> {
             generate(shell, &mut Opts::command(), "cast", &mut std::io::stdout())
         }
         Subcommands::Run(cmd) => cmd.run()?,
+        Subcommands::Signatures { command } => match command {
+            SignaturesSubcommands::Import { path } => {
+                let signatures = SignaturesCache::read(&path)?;
+                let cache_path = SignaturesCache::default_path()
+                    .ok_or_else(|| eyre::eyre!("could not find the home directory"))?;
+                let imported = SignaturesCache::merge_into(cache_path, &signatures)?;
+                println!("Imported {imported} new signatures");
+            }
+            SignaturesSubcommands::Export { path } => {
+                let cache = SignaturesCache::load();
+                cache.write(&path)?;
+                println!("Exported {} signatures to {}", cache.len(), path.display());
+            }
+        },
     };
     Ok(())
 }
@@ -10,12 +10,20 @@ use ethers::{
 use forge::{
     debug::DebugArena,
     executor::{builder::Backend, opts::EvmOpts, DeployResult, ExecutorBuilder, RawCallResult},
-    trace::{identifier::EtherscanIdentifier, CallTraceArena, CallTraceDecoderBuilder, TraceKind},
+    gas_profile::GasProfile,
+    trace::{
+        export::{TraceExport, TraceFormat},
+        identifier::{
+            CompositeIdentifier, EtherscanIdentifier, SourcifyIdentifier, SourcifyRepository,
+        },
+        CallTraceArena, CallTraceDecoderBuilder, TraceKind,
+    },
 };
 use foundry_config::Config;
-use foundry_utils::RuntimeOrHandle;
+use foundry_utils::{RuntimeOrHandle, SignaturesCache};
 use std::{
     collections::{BTreeMap, HashMap},
+    path::PathBuf,
     str::FromStr,
     time::Duration,
 };
@@ -40,6 +48,35 @@ pub struct RunArgs {
         help = "Labels address in the trace. 0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045:vitalik.eth"
     )]
     label: Vec<String>,
+    #[clap(long, value_name = "FILE", help = "Exports the trace of the transaction to a file.")]
+    export_traces: Option<PathBuf>,
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        default_value = "json",
+        requires = "export-traces",
+        help = "The format of the exported trace, either json or chrome."
+    )]
+    trace_format: TraceFormat,
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Writes a gas profile of the transaction to a file, in the folded stack format of flamegraph tools or as a flamegraph if the file has the .svg extension."
+    )]
+    gas_profile: Option<PathBuf>,
+    #[clap(
+        long,
+        value_name = "REPOSITORY",
+        help = "Identifies contracts verified on Sourcify, using a local mirror of the Sourcify repository or its url, e.g. https://repo.sourcify.dev"
+    )]
+    sourcify: Option<SourcifyRepository>,
+    #[clap(
+        long,
+        short,
+        parse(from_occurrences),
+        help = "Verbosity of the trace. -v also shows the storage slots written in each call."
+    )]
+    verbosity: u8,
 }
 
 impl Cmd for RunArgs {
@@ -54,6 +91,7 @@ impl RunArgs {
         let figment = Config::figment();
         let mut evm_opts = figment.extract::<EvmOpts>()?;
         let config = Config::from_provider(figment).sanitized();
+        let precompiles = utils::load_precompiles(&config)?;
 
         let provider =
             Provider::try_from(self.rpc_url.as_str()).expect("could not instantiate provider");
@@ -68,6 +106,7 @@ impl RunArgs {
 
             // Set up the execution environment
             let env = evm_opts.evm_env().await;
+            let chain_id = env.cfg.chain_id.as_u64();
             let db =
                 Backend::new(utils::get_fork(&evm_opts, &config.rpc_storage_caching), &env).await;
 
@@ -102,7 +141,10 @@ impl RunArgs {
 
             // Execute our transaction
             let mut result = {
-                executor.set_tracing(true).set_gas_limit(tx.gas);
+                executor
+                    .set_tracing(true)
+                    .set_storage_tracing(self.verbosity >= 1)
+                    .set_gas_limit(tx.gas);
 
                 if self.debug {
                     executor.set_debugger(true);
@@ -137,6 +179,13 @@ impl RunArgs {
                 Config::foundry_etherscan_cache_dir(evm_opts.get_chain_id()),
                 Duration::from_secs(24 * 60 * 60),
             );
+            let sourcify_identifier = SourcifyIdentifier::new(
+                evm_opts.get_remote_chain_id(),
+                self.sourcify.clone().or_else(|| config.sourcify.and_then(|s| s.parse().ok())),
+            );
+            let identifier = CompositeIdentifier::default()
+                .with(&etherscan_identifier)
+                .with(&sourcify_identifier);
 
             let labeled_addresses: BTreeMap<Address, String> = self
                 .label
@@ -153,16 +202,33 @@ impl RunArgs {
                 })
                 .collect();
 
-            let mut decoder = CallTraceDecoderBuilder::new().with_labels(labeled_addresses).build();
+            let mut decoder = CallTraceDecoderBuilder::new()
+                .with_chain_id(chain_id)
+                .with_precompiles(precompiles)
+                .with_labels(labeled_addresses)
+                .with_signatures(SignaturesCache::load())
+                .build();
 
             for (_, trace) in &mut result.traces {
-                decoder.identify(trace, &etherscan_identifier);
+                decoder.identify(trace, &identifier);
             }
 
             if self.debug {
                 run_debugger(result, decoder)?;
             } else {
                 print_traces(&mut result, decoder)?;
+
+                if let Some(path) = &self.export_traces {
+                    let mut export = TraceExport::default();
+                    export.add(&format!("{tx_hash:?}"), "transaction", &result.traces);
+                    export.write(path, self.trace_format)?;
+                }
+
+                if let Some(path) = &self.gas_profile {
+                    let mut profile = GasProfile::default();
+                    profile.analyze(&result.traces);
+                    profile.write(path, &format!("{tx_hash:?}"))?;
+                }
             }
         }
         Ok(())
@@ -173,8 +239,9 @@ fn run_debugger(result: RunResult, decoder: CallTraceDecoder) -> eyre::Result<()
     // TODO Get source from etherscan
     let source_code: BTreeMap<u32, String> = BTreeMap::new();
     let calls: Vec<DebugArena> = vec![result.debug];
-    let flattened = calls.last().expect("we should have collected debug info").flatten(0);
-    let tui = Tui::new(flattened, 0, decoder.contracts, HashMap::new(), source_code)?;
+    let arena = calls.last().expect("we should have collected debug info");
+    let tui = Tui::new(arena.flatten(0), 0, decoder.contracts, HashMap::new(), source_code)?
+        .with_breakpoints(arena.breakpoints(0), Vec::new(), &BTreeMap::new());
     match tui.start().expect("Failed to start tui") {
         TUIExitReason::CharExit => Ok(()),
     }
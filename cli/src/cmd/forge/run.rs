@@ -25,9 +25,9 @@ use forge::{
 };
 use foundry_common::evm::EvmArgs;
 use foundry_config::{figment::Figment, Config};
-use foundry_utils::{encode_args, IntoFunction, PostLinkInput, RuntimeOrHandle};
+use foundry_utils::{encode_args, IntoFunction, PostLinkInput, RuntimeOrHandle, SignaturesCache};
 use std::{collections::BTreeMap, path::PathBuf};
-use ui::{TUIExitReason, Tui, Ui};
+use ui::{LineBreakpoint, TUIExitReason, Tui, Ui};
 
 // Loads project's figment and merges the build cli arguments into it
 foundry_config::impl_figment_convert!(RunArgs, opts, evm_opts);
@@ -56,6 +56,12 @@ pub struct RunArgs {
     #[clap(long)]
     pub debug: bool,
 
+    /// Set a breakpoint in the debugger at the given source line.
+    ///
+    /// The file may be given as any suffix of its path, e.g. `Contract.s.sol:42`.
+    #[clap(long, requires = "debug", value_name = "FILE:LINE")]
+    pub breakpoint: Vec<LineBreakpoint>,
+
     #[clap(flatten, next_help_heading = "BUILD OPTIONS")]
     pub opts: CoreBuildArgs,
 
@@ -91,6 +97,10 @@ impl Cmd for RunArgs {
                 )
             })
             .collect::<BTreeMap<ArtifactId, (Abi, Vec<u8>)>>();
+        let immutable_references = highlevel_known_contracts
+            .iter()
+            .map(|(id, c)| (id.clone(), c.deployed_bytecode.immutable_references.clone()))
+            .collect();
 
         let CompactContractBytecode { abi, bytecode, .. } = contract;
         let abi = abi.expect("no ABI for contract");
@@ -112,6 +122,7 @@ impl Cmd for RunArgs {
 
         let runtime = RuntimeOrHandle::new();
         let env = runtime.block_on(evm_opts.evm_env());
+        let chain_id = env.cfg.chain_id.as_u64();
         // the db backend that serves all the data
         let db = runtime
             .block_on(Backend::new(utils::get_fork(&evm_opts, &config.rpc_storage_caching), &env));
@@ -161,10 +172,14 @@ impl Cmd for RunArgs {
         // TODO: Could we use the Etherscan identifier here? Main issue: Pulling source code and
         // bytecode. Might be better to wait for an interactive debugger where we can do this on
         // the fly while retaining access to the database?
-        let local_identifier = LocalTraceIdentifier::new(&known_contracts);
+        let local_identifier = LocalTraceIdentifier::new(&known_contracts)
+            .with_immutable_references(&immutable_references);
         let mut decoder = CallTraceDecoderBuilder::new()
+            .with_chain_id(chain_id)
+            .with_precompiles(utils::load_precompiles(&config)?)
             .with_labels(result.labeled_addresses.clone())
             .with_events(local_identifier.events())
+            .with_signatures(SignaturesCache::load())
             .build();
         for (_, trace) in &mut result.traces {
             decoder.identify(trace, &local_identifier);
@@ -189,9 +204,9 @@ impl Cmd for RunArgs {
                 .collect();
 
             let calls: Vec<DebugArena> = result.debug.expect("we should have collected debug info");
-            let flattened = calls.last().expect("we should have collected debug info").flatten(0);
+            let arena = calls.last().expect("we should have collected debug info");
             let tui = Tui::new(
-                flattened,
+                arena.flatten(0),
                 0,
                 decoder.contracts,
                 highlevel_known_contracts
@@ -199,7 +214,8 @@ impl Cmd for RunArgs {
                     .map(|(id, artifact)| (id.name, artifact))
                     .collect(),
                 source_code,
-            )?;
+            )?
+            .with_breakpoints(arena.breakpoints(0), self.breakpoint, &sources);
             match tui.start().expect("Failed to start tui") {
                 TUIExitReason::CharExit => return Ok(()),
             }
@@ -9,25 +9,28 @@ use crate::cmd::{
 };
 use ansi_term::Colour;
 use clap::{Parser, ValueHint};
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
+use ethers::types::U256;
 use eyre::Context;
 use forge::TestKindGas;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
-    collections::HashMap,
+    collections::BTreeMap,
     fmt::Write,
     fs,
-    io::{self, BufRead},
     path::{Path, PathBuf},
     str::FromStr,
 };
 use watchexec::config::{InitConfig, RuntimeConfig};
 
 /// A regex that matches a basic snapshot entry like
-/// `Test:testDeposit() (gas: 58804)`
+/// `Test:testDeposit() (gas: 58804)`, where the signature may be followed by the case of a
+/// table-driven test and the `setUp` variant it ran with, like `testDeposit(uint256) #0 [large]`
 pub static RE_BASIC_SNAPSHOT_ENTRY: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?P<file>(.*?)):(?P<sig>(\w+)\s*\((.*?)\))\s*\(((gas:)?\s*(?P<gas>\d+)|(runs:\s*(?P<runs>\d+),\s*μ:\s*(?P<avg>\d+),\s*~:\s*(?P<med>\d+)))\)").unwrap()
+    Regex::new(r"(?P<file>(.*?)):(?P<sig>(\w+)\s*\((.*?)\)( #\d+)?( \[\w+\])?)\s*\(((gas:)?\s*(?P<gas>\d+)|(runs:\s*(?P<runs>\d+),\s*μ:\s*(?P<avg>\d+),\s*~:\s*(?P<med>\d+)))\)").unwrap()
 });
 
 #[derive(Debug, Clone, Parser)]
@@ -43,6 +46,10 @@ pub struct SnapshotArgs {
     /// Output a diff against a pre-existing snapshot.
     ///
     /// By default the comparison is done with .gas-snapshot.
+    ///
+    /// Snapshot files with a `.json` or `.toml` extension are structured snapshots, which also
+    /// record the fuzz seed and allow a tolerance per entry. Their fuzz tests are run with the
+    /// recorded seed. Other files use the line based format of .gas-snapshot.
     #[clap(
         conflicts_with = "snap",
         long,
@@ -64,12 +71,14 @@ pub struct SnapshotArgs {
     )]
     check: Option<Option<PathBuf>>,
 
-    // Hidden because there is only one option
-    #[clap(help = "How to format the output.", long, hide(true))]
+    /// How to format the output: `table` or `md` for Markdown.
+    ///
+    /// Applies to the diff of --diff, and prints the snapshot when writing it.
+    #[clap(long, value_name = "FORMAT")]
     format: Option<Format>,
 
     #[clap(
-        help = "Output file for the snapshot.",
+        help = "Output file for the snapshot, a structured snapshot if the file has a .json or .toml extension.",
         default_value = ".gas-snapshot",
         long,
         value_name = "SNAPSHOT_FILE"
@@ -102,34 +111,72 @@ impl SnapshotArgs {
 impl Cmd for SnapshotArgs {
     type Output = ();
 
-    fn run(self) -> eyre::Result<()> {
+    fn run(mut self) -> eyre::Result<()> {
+        // listing the tests must not overwrite the snapshot
+        if self.test.is_list() {
+            custom_run(self.test, self.include_fuzz_tests)?;
+            return Ok(())
+        }
+
+        // the snapshot to compare against, or the structured snapshot that is overwritten, whose
+        // tolerances are kept
+        let compare_to = self.diff.clone().or_else(|| self.check.clone());
+        let existing = match &compare_to {
+            Some(path) => Some(read_snapshot(path.as_ref().unwrap_or(&self.snap))?),
+            None if SnapshotFormat::of(&self.snap) != SnapshotFormat::Legacy &&
+                self.snap.exists() =>
+            {
+                Some(read_snapshot(&self.snap)?)
+            }
+            None => None,
+        };
+
+        // the fuzz tests of structured snapshots are run with the seed the snapshot records, or
+        // the configured seed or 0 for new snapshots, so that their gas usage is stable across
+        // runs. Legacy snapshots do not record a seed and use the configured seed, if any
+        let (config, _) = self.test.config_and_evm_opts()?;
+        let snapshot_path = compare_to.as_ref().and_then(Option::as_ref).unwrap_or(&self.snap);
+        if SnapshotFormat::of(snapshot_path) != SnapshotFormat::Legacy {
+            let fuzz_seed = self
+                .test
+                .fuzz_seed
+                .or_else(|| existing.as_ref().and_then(|snapshot| snapshot.fuzz_seed))
+                .or(config.fuzz_seed)
+                .unwrap_or_default();
+            self.test.fuzz_seed = Some(fuzz_seed);
+        }
+        let fuzz_seed = self.test.fuzz_seed;
+
         let outcome = custom_run(self.test, self.include_fuzz_tests)?;
         outcome.ensure_ok()?;
         let tests = self.config.apply(outcome);
 
-        if let Some(path) = self.diff {
-            let snap = path.as_ref().unwrap_or(&self.snap);
-            let snaps = read_snapshot(snap)?;
-            diff(tests, snaps)?;
-        } else if let Some(path) = self.check {
-            let snap = path.as_ref().unwrap_or(&self.snap);
-            let snaps = read_snapshot(snap)?;
-            if check(tests, snaps) {
-                std::process::exit(0)
-            } else {
-                std::process::exit(1)
+        match (self.diff.is_some(), existing) {
+            (true, Some(snapshot)) => diff(tests, snapshot.entries, self.format)?,
+            (false, Some(snapshot)) if compare_to.is_some() => {
+                if check(tests, snapshot.entries) {
+                    std::process::exit(0)
+                } else {
+                    std::process::exit(1)
+                }
             }
-        } else {
-            write_to_snapshot_file(&tests, self.snap, self.format)?;
+            (_, existing) => write_to_snapshot_file(
+                &tests,
+                self.snap,
+                self.format,
+                &config.__root.0,
+                fuzz_seed,
+                existing,
+            )?,
         }
         Ok(())
     }
 }
 
-// TODO implement pretty tables
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Format {
     Table,
+    Markdown,
 }
 
 impl FromStr for Format {
@@ -138,6 +185,7 @@ impl FromStr for Format {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "t" | "table" => Ok(Format::Table),
+            "md" | "markdown" => Ok(Format::Markdown),
             _ => Err(format!("Unrecognized format `{s}`")),
         }
     }
@@ -187,14 +235,168 @@ impl SnapshotConfig {
     }
 }
 
+/// The format of a snapshot file, determined by its extension
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SnapshotFormat {
+    /// One `<contract>:<signature> (gas: <gas>)` line per test, as in `.gas-snapshot`
+    Legacy,
+    Json,
+    Toml,
+}
+
+impl SnapshotFormat {
+    pub fn of(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("json") => SnapshotFormat::Json,
+            Some("toml") => SnapshotFormat::Toml,
+            _ => SnapshotFormat::Legacy,
+        }
+    }
+}
+
+/// The version of structured snapshot files
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// The entries of a snapshot file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    /// The seed the fuzz tests were run with, only recorded in structured snapshots
+    pub fuzz_seed: Option<U256>,
+    pub entries: Vec<SnapshotEntry>,
+}
+
+impl Snapshot {
+    /// Returns the entry of the test, if any
+    pub fn find(&self, test: &Test) -> Option<&SnapshotEntry> {
+        find_entry(&self.entries, test)
+    }
+}
+
+/// A structured snapshot file, keyed by source path, contract and signature
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SnapshotFile {
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fuzz_seed: Option<U256>,
+    #[serde(default)]
+    tests: BTreeMap<String, BTreeMap<String, BTreeMap<String, SnapshotValue>>>,
+}
+
+/// The gas of a test in a structured snapshot file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SnapshotValue {
+    /// The gas used by a unit test, or the median gas of a fuzz test
+    gas: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mean: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    runs: Option<usize>,
+    /// The change in gas, in percent, that is still considered a match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tolerance: Option<f64>,
+}
+
+impl Snapshot {
+    fn parse(content: &str, format: SnapshotFormat) -> eyre::Result<Self> {
+        let file: SnapshotFile = match format {
+            SnapshotFormat::Legacy => {
+                let entries = content
+                    .lines()
+                    .map(|line| SnapshotEntry::from_str(line).map_err(|err| eyre::eyre!("{err}")))
+                    .collect::<eyre::Result<_>>()?;
+                return Ok(Snapshot { fuzz_seed: None, entries })
+            }
+            SnapshotFormat::Json => serde_json::from_str(content)?,
+            SnapshotFormat::Toml => toml::from_str(content)?,
+        };
+        if file.version > SNAPSHOT_VERSION {
+            eyre::bail!(
+                "Unsupported snapshot version {}, expected at most {SNAPSHOT_VERSION}",
+                file.version
+            )
+        }
+
+        let mut entries = Vec::new();
+        for (path, contracts) in file.tests {
+            for (contract_name, signatures) in contracts {
+                for (signature, value) in signatures {
+                    let gas_used = match value.runs {
+                        Some(runs) => TestKindGas::Fuzz {
+                            runs,
+                            mean: value.mean.unwrap_or(value.gas),
+                            median: value.gas,
+                        },
+                        None => TestKindGas::Standard(value.gas),
+                    };
+                    entries.push(SnapshotEntry {
+                        path: Some(path.clone()),
+                        contract_name: contract_name.clone(),
+                        signature,
+                        gas_used,
+                        tolerance: value.tolerance,
+                    });
+                }
+            }
+        }
+        Ok(Snapshot { fuzz_seed: file.fuzz_seed, entries })
+    }
+
+    fn serialize(&self, format: SnapshotFormat) -> eyre::Result<String> {
+        let mut file = SnapshotFile {
+            version: SNAPSHOT_VERSION,
+            fuzz_seed: self.fuzz_seed,
+            tests: Default::default(),
+        };
+        for entry in &self.entries {
+            let (gas, mean, runs) = match entry.gas_used {
+                TestKindGas::Standard(gas) => (gas, None, None),
+                TestKindGas::Fuzz { runs, mean, median } => (median, Some(mean), Some(runs)),
+            };
+            file.tests
+                .entry(entry.path.clone().unwrap_or_default())
+                .or_default()
+                .entry(entry.contract_name.clone())
+                .or_default()
+                .insert(
+                    entry.signature.clone(),
+                    SnapshotValue { gas, mean, runs, tolerance: entry.tolerance },
+                );
+        }
+        Ok(match format {
+            SnapshotFormat::Json => serde_json::to_string_pretty(&file)?,
+            SnapshotFormat::Toml => toml::to_string_pretty(&file)?,
+            SnapshotFormat::Legacy => unreachable!("legacy snapshots are written line by line"),
+        })
+    }
+}
+
 /// A general entry in a snapshot file
 ///
-/// Has the form `<signature>(gas:? 40181)`
-#[derive(Debug, Clone, Eq, PartialEq)]
+/// Has the form `<signature>(gas:? 40181)` in legacy snapshot files.
+#[derive(Debug, Clone, PartialEq)]
 pub struct SnapshotEntry {
+    /// The source path of the contract relative to the project root, only recorded in structured
+    /// snapshots
+    pub path: Option<String>,
     pub contract_name: String,
     pub signature: String,
     pub gas_used: TestKindGas,
+    /// The change in gas, in percent, that is still considered a match
+    pub tolerance: Option<f64>,
+}
+
+impl SnapshotEntry {
+    /// Returns whether this is the entry of the test
+    pub fn matches(&self, test: &Test) -> bool {
+        self.contract_name == test.contract_name() &&
+            self.signature == test.signature &&
+            self.path.as_ref().map_or(true, |path| Path::new(test.file_name()).ends_with(path))
+    }
+}
+
+/// Returns the entry of the test, if any
+fn find_entry<'a>(entries: &'a [SnapshotEntry], test: &Test) -> Option<&'a SnapshotEntry> {
+    entries.iter().find(|entry| entry.matches(test))
 }
 
 impl FromStr for SnapshotEntry {
@@ -208,9 +410,11 @@ impl FromStr for SnapshotEntry {
                     cap.name("sig").and_then(|sig| {
                         if let Some(gas) = cap.name("gas") {
                             Some(SnapshotEntry {
+                                path: None,
                                 contract_name: file.as_str().to_string(),
                                 signature: sig.as_str().to_string(),
                                 gas_used: TestKindGas::Standard(gas.as_str().parse().unwrap()),
+                                tolerance: None,
                             })
                         } else {
                             cap.name("runs")
@@ -219,6 +423,7 @@ impl FromStr for SnapshotEntry {
                                         .and_then(|avg| cap.name("med").map(|med| (runs, avg, med)))
                                 })
                                 .map(|(runs, avg, med)| SnapshotEntry {
+                                    path: None,
                                     contract_name: file.as_str().to_string(),
                                     signature: sig.as_str().to_string(),
                                     gas_used: TestKindGas::Fuzz {
@@ -226,6 +431,7 @@ impl FromStr for SnapshotEntry {
                                         median: med.as_str().parse().unwrap(),
                                         mean: avg.as_str().parse().unwrap(),
                                     },
+                                    tolerance: None,
                                 })
                         }
                     })
@@ -235,27 +441,54 @@ impl FromStr for SnapshotEntry {
     }
 }
 
-/// Reads a list of snapshot entries from a snapshot file
-fn read_snapshot(path: impl AsRef<Path>) -> eyre::Result<Vec<SnapshotEntry>> {
+/// Reads the snapshot entries from a snapshot file in the format of its extension
+fn read_snapshot(path: impl AsRef<Path>) -> eyre::Result<Snapshot> {
     let path = path.as_ref();
-    let mut entries = Vec::new();
-    for line in io::BufReader::new(
-        fs::File::open(path)
-            .wrap_err(format!("failed to read snapshot file \"{}\"", path.display()))?,
-    )
-    .lines()
-    {
-        entries.push(SnapshotEntry::from_str(line?.as_str()).map_err(|err| eyre::eyre!("{err}"))?);
-    }
-    Ok(entries)
+    let content = fs::read_to_string(path)
+        .wrap_err(format!("failed to read snapshot file \"{}\"", path.display()))?;
+    Snapshot::parse(&content, SnapshotFormat::of(path))
+        .wrap_err(format!("failed to parse snapshot file \"{}\"", path.display()))
 }
 
-/// Writes a series of tests to a snapshot file
+/// Writes a series of tests to a snapshot file in the format of its extension
+///
+/// Structured snapshots record the fuzz seed and keep the tolerances of the existing snapshot.
+/// Their source paths are relative to the project root.
 fn write_to_snapshot_file(
     tests: &[Test],
     path: impl AsRef<Path>,
-    _format: Option<Format>,
+    format: Option<Format>,
+    root: &Path,
+    fuzz_seed: Option<U256>,
+    existing: Option<Snapshot>,
 ) -> eyre::Result<()> {
+    let snapshot_format = SnapshotFormat::of(&path);
+    if snapshot_format != SnapshotFormat::Legacy {
+        let entries = tests
+            .iter()
+            .map(|test| SnapshotEntry {
+                path: Some(
+                    Path::new(test.file_name())
+                        .strip_prefix(root)
+                        .unwrap_or_else(|_| Path::new(test.file_name()))
+                        .to_string_lossy()
+                        .to_string(),
+                ),
+                contract_name: test.contract_name().to_string(),
+                signature: test.signature.clone(),
+                gas_used: test.result.kind.gas_used(),
+                tolerance: existing
+                    .as_ref()
+                    .and_then(|snapshot| snapshot.find(test))
+                    .and_then(|entry| entry.tolerance),
+            })
+            .collect();
+        let snapshot = Snapshot { fuzz_seed, entries };
+        fs::write(path, snapshot.serialize(snapshot_format)?)?;
+        print_tests(tests, format);
+        return Ok(())
+    }
+
     let mut out = String::new();
     for test in tests {
         writeln!(
@@ -266,12 +499,39 @@ fn write_to_snapshot_file(
             test.result.kind.gas_used()
         )?;
     }
-    Ok(fs::write(path, out)?)
+    fs::write(path, out)?;
+    print_tests(tests, format);
+    Ok(())
+}
+
+/// Prints the gas used by the tests as a table or in Markdown
+fn print_tests(tests: &[Test], format: Option<Format>) {
+    let rows = tests.iter().map(|test| {
+        let name = format!("{}::{}", test.contract_name(), test.signature);
+        (name, test.result.kind.gas_used().to_string())
+    });
+    match format {
+        Some(Format::Table) => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
+            table.set_header(vec!["Test", "Gas"]);
+            rows.for_each(|(name, gas)| {
+                table.add_row(vec![name, gas]);
+            });
+            println!("{table}");
+        }
+        Some(Format::Markdown) => {
+            println!("| Test | Gas |\n|---|---|");
+            rows.for_each(|(name, gas)| println!("| {name} | {gas} |"));
+        }
+        None => {}
+    }
 }
 
 /// A Snapshot entry diff
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SnapshotDiff {
+    pub contract_name: String,
     pub signature: String,
     pub source_gas_used: TestKindGas,
     pub target_gas_used: TestKindGas,
@@ -288,31 +548,33 @@ impl SnapshotDiff {
 
     /// Determines the percentage change
     fn gas_diff(&self) -> f64 {
-        self.gas_change() as f64 / self.target_gas_used.gas() as f64
+        self.gas_change() as f64 / self.target_gas_used.gas() as f64 * 100.0
     }
 }
 
 /// Compares the set of tests with an existing snapshot
 ///
-/// Returns true all tests match
+/// Returns true all tests match, i.e. their gas changed by no more than the tolerance of their
+/// entry
 fn check(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> bool {
-    let snaps = snaps
-        .into_iter()
-        .map(|s| ((s.contract_name, s.signature), s.gas_used))
-        .collect::<HashMap<_, _>>();
     let mut has_diff = false;
     for test in tests {
-        if let Some(target_gas) =
-            snaps.get(&(test.contract_name().to_string(), test.signature.clone())).cloned()
-        {
-            let source_gas = test.result.kind.gas_used();
-            if source_gas.gas() != target_gas.gas() {
+        if let Some(entry) = find_entry(&snaps, &test) {
+            let diff = SnapshotDiff {
+                contract_name: test.contract_name().to_string(),
+                signature: test.signature.clone(),
+                source_gas_used: test.result.kind.gas_used(),
+                target_gas_used: entry.gas_used.clone(),
+            };
+            let tolerance = entry.tolerance.unwrap_or_default();
+            if diff.gas_change() != 0 && diff.gas_diff().abs() > tolerance {
                 eprintln!(
-                    "Diff in \"{}::{}\": consumed \"{}\" gas, expected \"{}\" gas ",
+                    "Diff in \"{}::{}\": consumed \"{}\" gas, expected \"{}\" gas ({})",
                     test.contract_name(),
                     test.signature,
-                    source_gas,
-                    target_gas
+                    diff.source_gas_used,
+                    diff.target_gas_used,
+                    fmt_pct_change(diff.gas_diff())
                 );
                 has_diff = true;
             }
@@ -328,55 +590,192 @@ fn check(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> bool {
     !has_diff
 }
 
-/// Compare the set of tests with an existing snapshot
-fn diff(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> eyre::Result<()> {
-    let snaps = snaps
-        .into_iter()
-        .map(|s| ((s.contract_name, s.signature), s.gas_used))
-        .collect::<HashMap<_, _>>();
-    let mut diffs = Vec::with_capacity(tests.len());
-    for test in tests.into_iter() {
-        let target_gas_used = snaps
-            .get(&(test.contract_name().to_string(), test.signature.clone()))
-            .cloned()
-            .ok_or_else(|| {
-                eyre::eyre!(
-                    "No matching snapshot entry found for \"{}\" in snapshot file",
-                    test.signature
-                )
-            })?;
-
-        diffs.push(SnapshotDiff {
-            source_gas_used: test.result.kind.gas_used(),
-            signature: test.signature,
-            target_gas_used,
+impl SnapshotDiff {
+    fn name(&self) -> String {
+        format!("{}::{}", self.contract_name, self.signature)
+    }
+}
+
+/// The number of biggest regressions and improvements that are summarized
+const SUMMARY_LEN: usize = 5;
+
+/// The changes of a set of tests compared to an existing snapshot
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotDiffReport {
+    /// The tests that are part of the snapshot, by ascending absolute percentage change
+    pub diffs: Vec<SnapshotDiff>,
+    /// The tests that are not part of the snapshot
+    pub added: Vec<String>,
+    /// The snapshot entries without a test
+    pub removed: Vec<String>,
+}
+
+impl SnapshotDiffReport {
+    pub fn new(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> Self {
+        let mut report = SnapshotDiffReport::default();
+        let mut matched = vec![false; snaps.len()];
+        for test in tests.into_iter() {
+            match snaps.iter().position(|entry| entry.matches(&test)) {
+                Some(idx) => {
+                    matched[idx] = true;
+                    report.diffs.push(SnapshotDiff {
+                        contract_name: test.contract_name().to_string(),
+                        source_gas_used: test.result.kind.gas_used(),
+                        signature: test.signature,
+                        target_gas_used: snaps[idx].gas_used.clone(),
+                    });
+                }
+                None => report.added.push(format!("{}::{}", test.contract_name(), test.signature)),
+            }
+        }
+        report.removed = snaps
+            .iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(entry, _)| format!("{}::{}", entry.contract_name, entry.signature))
+            .collect();
+
+        report.diffs.sort_by(|a, b| {
+            a.gas_diff().abs().partial_cmp(&b.gas_diff().abs()).unwrap_or(Ordering::Equal)
         });
+        report
     }
-    let mut overall_gas_change = 0i128;
-    let mut overall_gas_diff = 0f64;
 
-    diffs.sort_by(|a, b| {
-        a.gas_diff().abs().partial_cmp(&b.gas_diff().abs()).unwrap_or(Ordering::Equal)
-    });
+    /// The total change in gas of the tests that are part of the snapshot
+    pub fn gas_change(&self) -> i128 {
+        self.diffs.iter().map(SnapshotDiff::gas_change).sum()
+    }
+
+    /// The total change in gas in percent of the total gas of the snapshot
+    pub fn gas_diff(&self) -> f64 {
+        let target = self.diffs.iter().map(|diff| diff.target_gas_used.gas() as f64).sum::<f64>();
+        if target == 0.0 {
+            return 0.0
+        }
+        self.gas_change() as f64 / target * 100.0
+    }
+
+    /// The tests whose gas increased the most, by percentage
+    pub fn regressions(&self) -> impl Iterator<Item = &SnapshotDiff> {
+        self.diffs.iter().rev().filter(|diff| diff.gas_change() > 0).take(SUMMARY_LEN)
+    }
 
-    for diff in diffs {
-        let gas_change = diff.gas_change();
-        overall_gas_change += gas_change;
-        let gas_diff = diff.gas_diff();
-        overall_gas_diff += gas_diff;
+    /// The tests whose gas decreased the most, by percentage
+    pub fn improvements(&self) -> impl Iterator<Item = &SnapshotDiff> {
+        self.diffs.iter().rev().filter(|diff| diff.gas_change() < 0).take(SUMMARY_LEN)
+    }
+
+    /// Prints every change, followed by a summary
+    fn print(&self) {
+        for diff in self.diffs.iter() {
+            println!(
+                "{} (gas: {} ({})) ",
+                diff.signature,
+                fmt_change(diff.gas_change()),
+                fmt_pct_change(diff.gas_diff())
+            );
+        }
+
+        println!();
+        let summary = |title: &str, diffs: Vec<&SnapshotDiff>| {
+            if !diffs.is_empty() {
+                println!("{title}:");
+                for diff in diffs {
+                    println!("  {} ({})", diff.name(), fmt_pct_change(diff.gas_diff()));
+                }
+            }
+        };
+        summary("Biggest regressions", self.regressions().collect());
+        summary("Biggest improvements", self.improvements().collect());
+        if !self.added.is_empty() {
+            println!("Added tests: {}", self.added.join(", "));
+        }
+        if !self.removed.is_empty() {
+            println!("Removed tests: {}", self.removed.join(", "));
+        }
         println!(
-            "{} (gas: {} ({})) ",
-            diff.signature,
-            fmt_change(gas_change),
-            fmt_pct_change(gas_diff)
+            "Overall gas change: {} ({})",
+            fmt_change(self.gas_change()),
+            fmt_pct_change(self.gas_diff())
+        );
+    }
+
+    /// Returns the summary and the changed tests as Markdown
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::from("### Gas snapshot\n\n");
+        let _ = writeln!(
+            md,
+            "Overall gas change: **{:+}** ({:+.3}%)\n",
+            self.gas_change(),
+            self.gas_diff()
+        );
+
+        let list = |md: &mut String, title: &str, items: Vec<String>| {
+            if !items.is_empty() {
+                let _ = writeln!(md, "**{title}**\n");
+                items.iter().for_each(|item| {
+                    let _ = writeln!(md, "- {item}");
+                });
+                md.push('\n');
+            }
+        };
+        let fmt_diff =
+            |diff: &SnapshotDiff| format!("`{}` ({:+.3}%)", diff.name(), diff.gas_diff());
+        list(&mut md, "Biggest regressions", self.regressions().map(fmt_diff).collect());
+        list(&mut md, "Biggest improvements", self.improvements().map(fmt_diff).collect());
+        list(&mut md, "Added tests", self.added.iter().map(|name| format!("`{name}`")).collect());
+        list(
+            &mut md,
+            "Removed tests",
+            self.removed.iter().map(|name| format!("`{name}`")).collect(),
         );
+
+        let changed = self.diffs.iter().rev().filter(|diff| diff.gas_change() != 0);
+        let mut changed = changed.peekable();
+        if changed.peek().is_some() {
+            md.push_str("| Test | Before | After | Change |\n|---|---|---|---|\n");
+            for diff in changed {
+                let _ = writeln!(
+                    md,
+                    "| `{}` | {} | {} | {:+} ({:+.3}%) |",
+                    diff.name(),
+                    diff.target_gas_used.gas(),
+                    diff.source_gas_used.gas(),
+                    diff.gas_change(),
+                    diff.gas_diff()
+                );
+            }
+        }
+        md
     }
+}
 
-    println!(
-        "Overall gas change: {} ({})",
-        fmt_change(overall_gas_change),
-        fmt_pct_change(overall_gas_diff)
-    );
+/// Compare the set of tests with an existing snapshot
+fn diff(tests: Vec<Test>, snaps: Vec<SnapshotEntry>, format: Option<Format>) -> eyre::Result<()> {
+    let report = SnapshotDiffReport::new(tests, snaps);
+    match format {
+        Some(Format::Markdown) => print!("{}", report.to_markdown()),
+        Some(Format::Table) => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
+            table.set_header(vec!["Test", "Before", "After", "Change"]);
+            for diff in report.diffs.iter() {
+                table.add_row(vec![
+                    diff.name(),
+                    diff.target_gas_used.gas().to_string(),
+                    diff.source_gas_used.gas().to_string(),
+                    format!("{:+} ({:+.3}%)", diff.gas_change(), diff.gas_diff()),
+                ]);
+            }
+            println!("{table}");
+            println!(
+                "Overall gas change: {} ({})",
+                fmt_change(report.gas_change()),
+                fmt_pct_change(report.gas_diff())
+            );
+        }
+        None => report.print(),
+    }
     Ok(())
 }
 
@@ -411,13 +810,24 @@ mod tests {
         assert_eq!(
             entry,
             SnapshotEntry {
+                path: None,
                 contract_name: "Test".to_string(),
                 signature: "deposit()".to_string(),
-                gas_used: TestKindGas::Standard(7222)
+                gas_used: TestKindGas::Standard(7222),
+                tolerance: None,
             }
         );
     }
 
+    #[test]
+    fn can_parse_setup_variant_snapshot_entry() {
+        let s = "Test:deposit(uint256) #1 [large] (gas: 7222)";
+        let entry = SnapshotEntry::from_str(s).unwrap();
+        assert_eq!(entry.contract_name, "Test");
+        assert_eq!(entry.signature, "deposit(uint256) #1 [large]");
+        assert_eq!(entry.gas_used, TestKindGas::Standard(7222));
+    }
+
     #[test]
     fn can_parse_fuzz_snapshot_entry() {
         let s = "Test:deposit() (runs: 256, μ: 100, ~:200)";
@@ -425,10 +835,98 @@ mod tests {
         assert_eq!(
             entry,
             SnapshotEntry {
+                path: None,
                 contract_name: "Test".to_string(),
                 signature: "deposit()".to_string(),
-                gas_used: TestKindGas::Fuzz { runs: 256, median: 200, mean: 100 }
+                gas_used: TestKindGas::Fuzz { runs: 256, median: 200, mean: 100 },
+                tolerance: None,
             }
         );
     }
+
+    #[test]
+    fn can_roundtrip_structured_snapshot() {
+        let snapshot = Snapshot {
+            fuzz_seed: Some(1.into()),
+            entries: vec![
+                SnapshotEntry {
+                    path: Some("src/Test.t.sol".to_string()),
+                    contract_name: "Test".to_string(),
+                    signature: "deposit()".to_string(),
+                    gas_used: TestKindGas::Standard(7222),
+                    tolerance: Some(1.5),
+                },
+                SnapshotEntry {
+                    path: Some("src/Test.t.sol".to_string()),
+                    contract_name: "Test".to_string(),
+                    signature: "testFuzz(uint256)".to_string(),
+                    gas_used: TestKindGas::Fuzz { runs: 256, median: 200, mean: 100 },
+                    tolerance: None,
+                },
+            ],
+        };
+
+        for format in [SnapshotFormat::Json, SnapshotFormat::Toml] {
+            let content = snapshot.serialize(format).unwrap();
+            assert_eq!(Snapshot::parse(&content, format).unwrap(), snapshot);
+        }
+    }
+
+    #[test]
+    fn can_parse_toml_snapshot() {
+        let content = r#"
+version = 2
+fuzz_seed = "0x1"
+
+[tests."src/Test.t.sol".Test]
+"deposit()" = { gas = 7222, tolerance = 2.0 }
+"#;
+        let snapshot = Snapshot::parse(content, SnapshotFormat::Toml).unwrap();
+        assert_eq!(snapshot.fuzz_seed, Some(1.into()));
+        assert_eq!(snapshot.entries[0].gas_used, TestKindGas::Standard(7222));
+        assert_eq!(snapshot.entries[0].tolerance, Some(2.0));
+
+        assert!(Snapshot::parse("version = 3", SnapshotFormat::Toml).is_err());
+        assert_eq!(SnapshotFormat::of(".gas-snapshot"), SnapshotFormat::Legacy);
+    }
+
+    #[test]
+    fn can_summarize_diff() {
+        let test = |signature: &str, gas: u64| Test {
+            artifact_id: "src/Test.t.sol:Test".to_string(),
+            signature: signature.to_string(),
+            result: forge::TestResult {
+                success: true,
+                reason: None,
+                counterexample: None,
+                logs: vec![],
+                kind: forge::TestKind::Standard(gas),
+                traces: vec![],
+                labeled_addresses: Default::default(),
+                fuzz_stats: None,
+                inputs: None,
+                cached: false,
+            },
+        };
+        let entry = |signature: &str, gas: u64| {
+            SnapshotEntry::from_str(&format!("Test:{signature} (gas: {gas})")).unwrap()
+        };
+
+        let report = SnapshotDiffReport::new(
+            vec![test("a()", 110), test("b()", 90), test("c()", 100), test("d()", 1)],
+            vec![entry("a()", 100), entry("b()", 100), entry("c()", 100), entry("e()", 1)],
+        );
+        assert_eq!(report.gas_change(), 0);
+        assert_eq!(report.regressions().map(|diff| diff.name()).collect::<Vec<_>>(), ["Test::a()"]);
+        assert_eq!(
+            report.improvements().map(|diff| diff.name()).collect::<Vec<_>>(),
+            ["Test::b()"]
+        );
+        assert_eq!(report.added, ["Test::d()"]);
+        assert_eq!(report.removed, ["Test::e()"]);
+
+        let md = report.to_markdown();
+        assert!(md.contains("| `Test::a()` | 100 | 110 | +10 (+10.000%) |"));
+        assert!(!md.contains("`Test::c()` |"));
+    }
 }
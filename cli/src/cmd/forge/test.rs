@@ -4,33 +4,47 @@ use crate::{
         forge::{build::CoreBuildArgs, run::RunArgs, watch::WatchArgs},
         Cmd,
     },
-    compile::ProjectCompiler,
+    compile::{self, ProjectCompiler},
     utils,
     utils::FoundryPathExt,
 };
 use ansi_term::Colour;
-use clap::{AppSettings, Parser};
-use ethers::solc::FileFilter;
+use clap::{AppSettings, Parser, ValueHint};
+use ethers::{
+    solc::{artifacts::output_selection::ContractOutputSelection, FileFilter},
+    types::U256,
+};
+use eyre::Context;
 use forge::{
     decode::decode_console_logs,
     executor::opts::EvmOpts,
+    gas_profile::GasProfile,
     gas_report::GasReport,
+    reporter::{JunitReporter, Reporter, TapReporter},
+    shard::{self, Shard},
     trace::{
-        identifier::{EtherscanIdentifier, LocalTraceIdentifier},
+        export::{TraceExport, TraceFormat},
+        identifier::{
+            CompositeIdentifier, EtherscanIdentifier, LocalTraceIdentifier, SourcifyIdentifier,
+        },
         CallTraceDecoderBuilder, TraceKind,
     },
     MultiContractRunner, MultiContractRunnerBuilder, SuiteResult, TestFilter, TestKind,
 };
 use foundry_common::evm::EvmArgs;
-use foundry_config::{figment::Figment, Config};
+use foundry_config::{figment::Figment, Config, TestReporter};
+use foundry_utils::SignaturesCache;
+use proptest::test_runner::TestRunner;
 use regex::Regex;
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
+    io,
     path::{Path, PathBuf},
     sync::mpsc::channel,
     thread,
     time::Duration,
 };
+use ui::LineBreakpoint;
 use watchexec::config::{InitConfig, RuntimeConfig};
 
 #[derive(Debug, Clone, Parser)]
@@ -69,6 +83,10 @@ pub struct Filter {
         conflicts_with = "pattern"
     )]
     pub path_pattern_inverse: Option<globset::Glob>,
+
+    /// Only run these tests, `artifact id -> test signatures`, set by `--rerun`
+    #[clap(skip)]
+    pub rerun_failures: Option<BTreeMap<String, BTreeSet<String>>>,
 }
 
 impl Filter {
@@ -129,6 +147,15 @@ impl TestFilter for Filter {
         if let Some(re) = &self.test_pattern_inverse {
             ok &= !re.is_match(test_name);
         }
+        if let Some(failures) = &self.rerun_failures {
+            // without the contract we can only check whether any contract has such a failing test,
+            // which is matched by either its name or its signature
+            ok &= failures.values().flatten().any(|sig| {
+                sig == RERUN_ALL_TESTS ||
+                    sig == test_name ||
+                    sig.split('(').next() == Some(test_name)
+            });
+        }
         ok
     }
 
@@ -141,6 +168,9 @@ impl TestFilter for Filter {
         if let Some(re) = &self.contract_pattern_inverse {
             ok &= !re.is_match(contract_name);
         }
+        if let Some(failures) = &self.rerun_failures {
+            ok &= failures.keys().any(|id| utils::get_contract_name(id) == contract_name);
+        }
         ok
     }
 
@@ -155,6 +185,17 @@ impl TestFilter for Filter {
         }
         ok
     }
+
+    fn matches_contract_test(&self, artifact_id: &str, test_name: impl AsRef<str>) -> bool {
+        let test_name = test_name.as_ref();
+        let is_failure = |failures: &BTreeMap<String, BTreeSet<String>>| {
+            failures
+                .get(artifact_id)
+                .map(|sigs| sigs.contains(RERUN_ALL_TESTS) || sigs.contains(test_name))
+                .unwrap_or_default()
+        };
+        self.matches_test(test_name) && self.rerun_failures.as_ref().map_or(true, is_failure)
+    }
 }
 
 // Loads project's figment and merges the build cli arguments into it
@@ -184,18 +225,120 @@ pub struct TestArgs {
     #[clap(long, value_name = "TEST FUNCTION")]
     debug: Option<Regex>,
 
+    /// Set a breakpoint in the debugger at the given source line.
+    ///
+    /// The file may be given as any suffix of its path, e.g. `Contract.t.sol:42`.
+    #[clap(long, requires = "debug", value_name = "FILE:LINE")]
+    breakpoint: Vec<LineBreakpoint>,
+
     /// Print a gas report.
     #[clap(long, env = "FORGE_GAS_REPORT")]
     gas_report: bool,
 
+    /// Break down the gas of every function in the gas report by the test contracts that called
+    /// it.
+    #[clap(long, requires = "gas-report")]
+    gas_report_breakdown: bool,
+
+    /// Write the gas report to a file, as Markdown if the file has the `.md` extension and as
+    /// JSON otherwise.
+    ///
+    /// JSON reports can be used as the baseline of later runs.
+    #[clap(long, value_name = "FILE", requires = "gas-report", value_hint = ValueHint::FilePath)]
+    gas_report_output: Option<PathBuf>,
+
+    /// Compare the gas report to a JSON gas report of a previous run and print the changes.
+    #[clap(long, value_name = "FILE", requires = "gas-report", value_hint = ValueHint::FilePath)]
+    gas_report_baseline: Option<PathBuf>,
+
+    /// Fail if the gas of any function or deployment increased by more than this percentage over
+    /// the baseline.
+    #[clap(long, value_name = "PERCENT", requires = "gas-report-baseline")]
+    gas_report_threshold: Option<f64>,
+
+    /// Export the traces of all tests to a file.
+    ///
+    /// The traces are grouped by test contract and test, see --trace-format for the formats.
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    export_traces: Option<PathBuf>,
+
+    /// The format of exported traces.
+    ///
+    /// `json` exports every trace as a tree of decoded calls and logs, `chrome` exports the
+    /// traces in the trace event format of chrome://tracing and Perfetto, with gas as the time
+    /// axis.
+    #[clap(long, value_name = "FORMAT", default_value = "json", requires = "export-traces")]
+    trace_format: TraceFormat,
+
+    /// Write a gas profile of all tests to a file, keeping the call hierarchy.
+    ///
+    /// The profile is written in the folded stack format of flamegraph tools, or rendered as a
+    /// flamegraph if the file has the `.svg` extension.
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    gas_profile: Option<PathBuf>,
+
+    /// The seed of every fuzz test, to reproduce the fuzz runs of a previous run.
+    ///
+    /// Passing the seed reported for a fuzz test reproduces its runs. Accepts a decimal or a hex
+    /// number.
+    #[clap(long, value_name = "SEED", parse(try_from_str = utils::parse_u256))]
+    pub fuzz_seed: Option<U256>,
+
     /// Exit with code 0 even if a test fails.
     #[clap(long, env = "FORGE_ALLOW_FAILURE")]
     allow_failure: bool,
 
+    /// Do not use the test result cache, and run all tests.
+    ///
+    /// By default, passing unit tests of unchanged test contracts are not executed again, and
+    /// their results are reported from the test result cache instead. Results are never cached
+    /// if `ffi` is enabled.
+    #[clap(long)]
+    no_cache: bool,
+
+    /// Only run the tests that failed in the last run.
+    ///
+    /// All tests of a contract whose `setUp` failed are run again. Failures of tests that are not
+    /// run, e.g. because of a filter, are remembered for the next rerun.
+    #[clap(long)]
+    rerun: bool,
+
+    /// Only run the k-th of n shares of the test contracts, to split the tests across n machines.
+    ///
+    /// Every test contract is run by exactly one shard, as long as all shards use the same
+    /// filters.
+    #[clap(long, value_name = "K/N")]
+    shard: Option<Shard>,
+
+    /// A JSON report (--json) of a previous run, whose durations are used to balance the shards.
+    ///
+    /// By default, test contracts are assigned to shards by the hash of their identifier.
+    #[clap(long, value_name = "REPORT", requires = "shard", value_hint = ValueHint::FilePath)]
+    shard_weights: Option<PathBuf>,
+
+    /// Merge the JSON reports (--json) of several shards into a single report instead of running
+    /// any tests.
+    #[clap(long, value_name = "REPORTS", multiple_values = true, value_hint = ValueHint::FilePath)]
+    merge_reports: Vec<PathBuf>,
+
+    /// List the tests that match the filters, grouped by file and contract, instead of running
+    /// them.
+    ///
+    /// Combine with --json to print the list as JSON.
+    #[clap(long, help_heading = "DISPLAY OPTIONS")]
+    list: bool,
+
     /// Output test results in JSON format.
     #[clap(long, short, help_heading = "DISPLAY OPTIONS")]
     json: bool,
 
+    /// The format to report test results in: human, junit or tap.
+    ///
+    /// The junit and tap reports are printed to stdout as the results of each test contract come
+    /// in.
+    #[clap(long, value_name = "REPORTER", help_heading = "DISPLAY OPTIONS")]
+    reporter: Option<TestReporter>,
+
     #[clap(flatten, next_help_heading = "EVM OPTIONS")]
     evm_opts: EvmArgs,
 
@@ -238,6 +381,11 @@ impl TestArgs {
         Ok((config, evm_opts))
     }
 
+    /// Returns whether the tests are only listed instead of run
+    pub fn is_list(&self) -> bool {
+        self.list
+    }
+
     /// Returns whether `BuildArgs` was configured with `--watch`
     pub fn is_watch(&self) -> bool {
         self.watch.watch.is_some()
@@ -289,17 +437,54 @@ impl Test {
     }
 }
 
+/// The name of the file the failing tests of the last run are persisted in, relative to the cache
+/// directory
+pub const TEST_FAILURES_FILENAME: &str = "test-failures.json";
+
+/// Recorded in place of the failing tests of a contract whose `setUp` failed, so all of its tests
+/// are run again
+pub const RERUN_ALL_TESTS: &str = "*";
+
+/// Returns the signature a failing test is recorded under, see [`TestOutcome::failed_tests`]
+fn rerun_signature(sig: &str) -> &str {
+    // the cases of a table-driven test are reported as `<signature> #<case>` and the runs of a
+    // test with `setUp` variants as `<signature> [<variant>]`, but they can only be rerun together
+    match sig.split(' ').next().unwrap_or(sig) {
+        "setUp()" => RERUN_ALL_TESTS,
+        sig => sig,
+    }
+}
+
+/// Reads the failing tests of the last run from the cache directory, see
+/// [`TestOutcome::persist_failures`]
+///
+/// Returns an empty set if there were no failures.
+pub fn read_last_failures(cache_dir: &Path) -> eyre::Result<BTreeMap<String, BTreeSet<String>>> {
+    let path = cache_dir.join(TEST_FAILURES_FILENAME);
+    if !path.exists() {
+        return Ok(Default::default())
+    }
+    let content = std::fs::read_to_string(&path)
+        .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
 /// Represents the bundled results of all tests
 pub struct TestOutcome {
     /// Whether failures are allowed
     pub allow_failure: bool,
     /// Results for each suite of tests `contract -> SuiteResult`
     pub results: BTreeMap<String, SuiteResult>,
+    /// Whether failures were already reported by a [`Reporter`], in which case they're not
+    /// summarized again, to keep the output machine readable
+    pub reported: bool,
+    /// The gas report of the run, if one was requested
+    pub gas_report: Option<GasReport>,
 }
 
 impl TestOutcome {
     fn new(results: BTreeMap<String, SuiteResult>, allow_failure: bool) -> Self {
-        Self { results, allow_failure }
+        Self { results, allow_failure, reported: false, gas_report: None }
     }
 
     /// Iterator over all succeeding tests and their names
@@ -327,10 +512,64 @@ impl TestOutcome {
             .map(|(artifact_id, (signature, result))| Test { artifact_id, signature, result })
     }
 
+    /// Returns the signatures of all failing tests, by the artifact identifier of their contract.
+    ///
+    /// A failing `setUp` is recorded as [`RERUN_ALL_TESTS`].
+    pub fn failed_tests(&self) -> BTreeMap<String, BTreeSet<String>> {
+        self.results
+            .iter()
+            .map(|(id, SuiteResult { test_results, .. })| {
+                let failed = test_results
+                    .iter()
+                    .filter(|(_, result)| !result.success)
+                    .map(|(sig, _)| rerun_signature(sig).to_string())
+                    .collect::<BTreeSet<_>>();
+                (id.clone(), failed)
+            })
+            .filter(|(_, failed)| !failed.is_empty())
+            .collect()
+    }
+
+    /// Persists the failing tests to the cache directory, so they can be run again with
+    /// `forge test --rerun`
+    ///
+    /// The failures recorded by earlier runs are kept, unless the test was run again and passed,
+    /// so runs of a subset of the tests don't forget the failures of the others.
+    pub fn persist_failures(&self, cache_dir: &Path) -> eyre::Result<()> {
+        let mut failures = read_last_failures(cache_dir).unwrap_or_else(|err| {
+            tracing::warn!(?err, "ignoring unreadable test failures");
+            Default::default()
+        });
+        for (id, SuiteResult { test_results, .. }) in &self.results {
+            if let Some(failed) = failures.get_mut(id) {
+                // `setUp` ran again if any test of the contract did
+                if !test_results.is_empty() {
+                    failed.remove(RERUN_ALL_TESTS);
+                }
+                for sig in test_results.keys() {
+                    failed.remove(rerun_signature(sig));
+                }
+            }
+        }
+        for (id, failed) in self.failed_tests() {
+            failures.entry(id).or_default().extend(failed);
+        }
+        failures.retain(|_, failed| !failed.is_empty());
+
+        std::fs::create_dir_all(cache_dir)?;
+        let path = cache_dir.join(TEST_FAILURES_FILENAME);
+        std::fs::write(&path, serde_json::to_string_pretty(&failures)?)
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
     /// Checks if there are any failures and failures are disallowed
     pub fn ensure_ok(&self) -> eyre::Result<()> {
         if !self.allow_failure {
             let failures = self.failures().count();
+            if failures > 0 && self.reported {
+                std::process::exit(1);
+            }
             if failures > 0 {
                 println!();
                 println!("Failed tests:");
@@ -372,7 +611,9 @@ impl TestOutcome {
 }
 
 fn short_test_result(name: &str, result: &forge::TestResult) {
-    let status = if result.success {
+    let status = if result.cached {
+        Colour::Green.paint("[PASS] (cached)")
+    } else if result.success {
         Colour::Green.paint("[PASS]")
     } else {
         let txt = match (&result.reason, &result.counterexample) {
@@ -391,12 +632,26 @@ fn short_test_result(name: &str, result: &forge::TestResult) {
         Colour::Red.paint(txt)
     };
 
-    println!("{} {} {}", status, name, result.kind.gas_used());
+    match (&result.fuzz_stats, &result.inputs) {
+        (Some(stats), _) => println!("{} {} {} {}", status, name, result.kind.gas_used(), stats),
+        (None, Some(inputs)) => println!(
+            "{} {} (args=[{}]) {}",
+            status,
+            name,
+            inputs.join(", "),
+            result.kind.gas_used()
+        ),
+        (None, None) => println!("{} {} {}", status, name, result.kind.gas_used()),
+    }
 }
 
 pub fn custom_run(args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<TestOutcome> {
     // Merge all configs
-    let (config, mut evm_opts) = args.config_and_evm_opts()?;
+    let (mut config, mut evm_opts) = args.config_and_evm_opts()?;
+
+    if !args.merge_reports.is_empty() {
+        return merge_reports(&args.merge_reports, args.allow_failure)
+    }
 
     // Setup the fuzzer
     // TODO: Add CLI Options to modify the persistence
@@ -407,24 +662,70 @@ pub fn custom_run(args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<Test
         max_global_rejects: config.fuzz_max_global_rejects,
         ..Default::default()
     };
-    let fuzzer = proptest::test_runner::TestRunner::new(cfg);
+    let fuzzer = TestRunner::new(cfg);
+
+    let shard = match (args.shard.clone(), &args.shard_weights) {
+        (Some(shard), Some(weights)) => Some(
+            shard.with_weights(
+                shard::read_durations(weights)
+                    .wrap_err_with(|| format!("Failed to read shard weights from {weights:?}"))?,
+            ),
+        ),
+        (shard, _) => shard,
+    };
+
+    // Machine readable reports and lists are printed to stdout, so they must not be mixed with
+    // human output. JSON reports of shards are merged later on, so they must stay parsable too
+    let reporter = args.reporter.unwrap_or(config.test_reporter);
+    let machine_readable = reporter != TestReporter::Human || args.json;
+
     let mut filter = args.filter();
+    if args.rerun {
+        let failures = read_last_failures(&config.cache_path)?;
+        if failures.is_empty() {
+            if !machine_readable {
+                println!("No failures recorded by the last run");
+            }
+            return Ok(TestOutcome::new(BTreeMap::new(), args.allow_failure))
+        }
+        filter.rerun_failures = Some(failures);
+    }
+
+    // The storage changes of traces are decoded using the storage layouts of the contracts
+    if evm_opts.verbosity >= 6 &&
+        !config.extra_output.contains(&ContractOutputSelection::StorageLayout)
+    {
+        config.extra_output.push(ContractOutputSelection::StorageLayout);
+    }
 
     // Set up the project
     let project = config.project()?;
     let compiler = ProjectCompiler::default();
     let output = if config.sparse_mode {
         compiler.compile_sparse(&project, filter.clone())
+    } else if machine_readable {
+        compile::suppress_compile(&project)
     } else {
         compiler.compile(&project)
     }?;
+    let storage_layouts = utils::storage_layouts(&output);
 
     // Determine print verbosity and executor verbosity
     let verbosity = evm_opts.verbosity;
-    if args.gas_report && evm_opts.verbosity < 3 {
+    let analyze_traces = args.export_traces.is_some() || args.gas_profile.is_some();
+    if (args.gas_report || analyze_traces) && evm_opts.verbosity < 3 {
         evm_opts.verbosity = 3;
     }
 
+    // Cached results have no logs or traces, so we only use the cache if those aren't displayed
+    let result_cache = (config.cache &&
+        !args.no_cache &&
+        args.debug.is_none() &&
+        !args.gas_report &&
+        !analyze_traces &&
+        verbosity < 2)
+        .then(|| config.cache_path.clone());
+
     // Prepare the test builder
     let evm_spec = crate::utils::evm_spec(&config.evm_version);
     let mut runner = MultiContractRunnerBuilder::default()
@@ -433,8 +734,19 @@ pub fn custom_run(args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<Test
         .evm_spec(evm_spec)
         .sender(evm_opts.sender)
         .with_fork(utils::get_fork(&evm_opts, &config.rpc_storage_caching))
+        .coverage_guided(config.fuzz_coverage_guided)
+        .with_result_cache(result_cache)
+        .test_timeout(config.test_timeout.map(Duration::from_secs))
+        .fuzz_timeout(config.fuzz_timeout.map(Duration::from_secs))
+        .fuzz_seed(args.fuzz_seed.or(config.fuzz_seed))
+        .shard(shard)
         .build(project.paths.root, output, evm_opts)?;
 
+    if args.list {
+        list(&runner, &filter, args.json, include_fuzz_tests)?;
+        return Ok(TestOutcome::new(BTreeMap::new(), args.allow_failure))
+    }
+
     if args.debug.is_some() {
         filter.test_pattern = args.debug;
         match runner.count_filtered_tests(&filter) {
@@ -468,6 +780,7 @@ pub fn custom_run(args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<Test
                         sig,
                         args: Vec::new(),
                         debug: true,
+                        breakpoint: args.breakpoint,
                         opts: args.opts,
                         evm_opts: args.evm_opts,
                     };
@@ -482,17 +795,184 @@ pub fn custom_run(args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<Test
                         Use --match-contract and --match-path to further limit the search."))
             }
     } else {
-        test(
+        let cache_dir = config.cache.then(|| config.cache_path.clone());
+        let gas_report_threshold = config.gas_report_threshold;
+        let gas_report = args.gas_report.then(|| {
+            GasReport::new(config.gas_reports.clone()).with_breakdown(args.gas_report_breakdown)
+        });
+        let reporter: Option<Box<dyn Reporter>> = match reporter {
+            TestReporter::Human => None,
+            TestReporter::Junit => Some(Box::new(JunitReporter::new(io::stdout()))),
+            TestReporter::Tap => Some(Box::new(TapReporter::new(io::stdout()))),
+        };
+        let local_identifier = LocalTraceIdentifier::new(&runner.known_contracts)
+            .with_immutable_references(&runner.immutable_references);
+        // The decoding shared by the traces of all tests
+        let decoder = CallTraceDecoderBuilder::new()
+            .with_chain_id(runner.chain_id())
+            .with_precompiles(utils::load_precompiles(&config)?)
+            .with_signatures(SignaturesCache::load())
+            .with_storage_layouts(storage_layouts);
+        let outcome = test(
             config,
             runner,
+            &decoder,
+            &local_identifier,
             verbosity,
             filter,
             args.json,
+            reporter,
             args.allow_failure,
             include_fuzz_tests,
-            args.gas_report,
-        )
+            gas_report,
+        )?;
+        if let Some(cache_dir) = cache_dir {
+            outcome.persist_failures(&cache_dir)?;
+        }
+        if let Some(gas_report) = &outcome.gas_report {
+            compare_gas_report(
+                gas_report,
+                args.gas_report_output.as_deref(),
+                args.gas_report_baseline.as_deref(),
+                args.gas_report_threshold.or(gas_report_threshold),
+            )?;
+        }
+        if analyze_traces {
+            let results = decode_traces(outcome.results.clone(), &local_identifier, &decoder);
+            if let Some(path) = &args.export_traces {
+                export_traces(&results, path, args.trace_format)?;
+            }
+            if let Some(path) = &args.gas_profile {
+                let mut profile = GasProfile::default();
+                results
+                    .values()
+                    .flat_map(|suite| suite.test_results.values())
+                    .for_each(|result| profile.analyze(&result.traces));
+                profile
+                    .write(path, "forge test")
+                    .wrap_err_with(|| format!("Failed to write gas profile to {path:?}"))?;
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+/// Merges the JSON reports of test shards and prints the merged report as JSON
+fn merge_reports(paths: &[PathBuf], allow_failure: bool) -> eyre::Result<TestOutcome> {
+    let reports = paths
+        .iter()
+        .map(|path| {
+            let content = std::fs::read_to_string(path)?;
+            serde_json::from_str(&content)
+                .wrap_err_with(|| format!("Failed to read test report {path:?}"))
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    let results = shard::merge_reports(reports);
+    println!("{}", serde_json::to_string(&results)?);
+
+    // failures are part of the report, so they're not summarized again
+    Ok(TestOutcome { reported: true, ..TestOutcome::new(results, allow_failure) })
+}
+
+/// Writes the gas report to a file and compares it to a baseline, failing if any gas increase
+/// exceeds the threshold
+fn compare_gas_report(
+    gas_report: &GasReport,
+    output: Option<&Path>,
+    baseline: Option<&Path>,
+    threshold: Option<f64>,
+) -> eyre::Result<()> {
+    let diff = baseline
+        .map(|path| {
+            GasReport::read(path)
+                .wrap_err_with(|| format!("Failed to read gas report baseline {path:?}"))
+                .map(|baseline| gas_report.diff(&baseline))
+        })
+        .transpose()?;
+
+    if let Some(path) = output {
+        gas_report
+            .write(path, diff.as_ref())
+            .wrap_err_with(|| format!("Failed to write gas report to {path:?}"))?;
+    }
+
+    if let Some(diff) = diff {
+        println!("{diff}");
+        if let Some(threshold) = threshold {
+            let regressions = diff.regressions(threshold);
+            if !regressions.is_empty() {
+                eyre::bail!(
+                    "{} gas {} exceeded the threshold of {threshold}% over the baseline",
+                    regressions.len(),
+                    if regressions.len() == 1 { "increase" } else { "increases" }
+                )
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decodes the traces of all tests with the local contracts
+fn decode_traces(
+    mut results: BTreeMap<String, SuiteResult>,
+    local_identifier: &LocalTraceIdentifier,
+    decoder: &CallTraceDecoderBuilder,
+) -> BTreeMap<String, SuiteResult> {
+    for suite in results.values_mut() {
+        for result in suite.test_results.values_mut() {
+            let mut decoder = decoder
+                .clone()
+                .with_labels(result.labeled_addresses.clone())
+                .with_events(local_identifier.events())
+                .build();
+            for (_, trace) in &mut result.traces {
+                decoder.identify(trace, local_identifier);
+                decoder.decode(trace);
+            }
+        }
+    }
+    results
+}
+
+/// Writes the traces of all tests to a file
+fn export_traces(
+    results: &BTreeMap<String, SuiteResult>,
+    path: &Path,
+    format: TraceFormat,
+) -> eyre::Result<()> {
+    let mut export = TraceExport::default();
+    for (contract, suite) in results {
+        for (name, result) in &suite.test_results {
+            export.add(contract, name, &result.traces);
+        }
+    }
+    export.write(path, format).wrap_err_with(|| format!("Failed to export traces to {path:?}"))
+}
+
+/// Prints the tests that match the filter, grouped by file and contract
+fn list(
+    runner: &MultiContractRunner,
+    filter: &Filter,
+    json: bool,
+    include_fuzz_tests: bool,
+) -> eyre::Result<()> {
+    let tests = runner.list(filter, include_fuzz_tests);
+    if json {
+        println!("{}", serde_json::to_string(&tests)?);
+        return Ok(())
+    }
+
+    for (file, contracts) in tests {
+        println!("{file}");
+        for (contract, signatures) in contracts {
+            println!("  {contract}");
+            for signature in signatures {
+                println!("    {signature}");
+            }
+        }
     }
+    Ok(())
 }
 
 /// Runs all the tests
@@ -500,20 +980,38 @@ pub fn custom_run(args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<Test
 fn test(
     config: Config,
     mut runner: MultiContractRunner,
+    decoder: &CallTraceDecoderBuilder,
+    local_identifier: &LocalTraceIdentifier,
     verbosity: u8,
     filter: Filter,
     json: bool,
+    reporter: Option<Box<dyn Reporter>>,
     allow_failure: bool,
     include_fuzz_tests: bool,
-    gas_reporting: bool,
+    mut gas_report: Option<GasReport>,
 ) -> eyre::Result<TestOutcome> {
     if json {
         let results = runner.test(&filter, None, include_fuzz_tests)?;
         println!("{}", serde_json::to_string(&results)?);
         Ok(TestOutcome::new(results, allow_failure))
+    } else if let Some(mut reporter) = reporter {
+        let (tx, rx) = channel::<(String, SuiteResult)>();
+        let handle =
+            thread::spawn(move || runner.test(&filter, Some(tx), include_fuzz_tests).unwrap());
+
+        let mut results = BTreeMap::new();
+        for (contract_name, suite_result) in rx {
+            reporter.report_suite(&contract_name, &suite_result)?;
+            results.insert(contract_name, suite_result);
+        }
+        reporter.finish()?;
+
+        // reattach the thread
+        let _ = handle.join();
+
+        Ok(TestOutcome { reported: true, ..TestOutcome::new(results, allow_failure) })
     } else {
         // Set up identifiers
-        let local_identifier = LocalTraceIdentifier::new(&runner.known_contracts);
         let remote_chain_id = runner.evm_opts.get_remote_chain_id();
         // Do not re-query etherscan for contracts that you've already queried today.
         // TODO: Make this configurable.
@@ -524,6 +1022,15 @@ fn test(
             remote_chain_id.and_then(Config::foundry_etherscan_cache_dir),
             cache_ttl,
         );
+        let sourcify_identifier = SourcifyIdentifier::new(
+            remote_chain_id,
+            config.sourcify.as_deref().and_then(|repository| repository.parse().ok()),
+        );
+        // Local contracts take precedence over verified contracts of the forked chain
+        let identifier = CompositeIdentifier::default()
+            .with(local_identifier)
+            .with(&etherscan_identifier)
+            .with(&sourcify_identifier);
 
         // Set up test reporter channel
         let (tx, rx) = channel::<(String, SuiteResult)>();
@@ -533,7 +1040,6 @@ fn test(
             thread::spawn(move || runner.test(&filter, Some(tx), include_fuzz_tests).unwrap());
 
         let mut results: BTreeMap<String, SuiteResult> = BTreeMap::new();
-        let mut gas_report = GasReport::new(config.gas_reports);
         for (contract_name, suite_result) in rx {
             let mut tests = suite_result.test_results.clone();
             println!();
@@ -562,7 +1068,8 @@ fn test(
 
                 if !result.traces.is_empty() {
                     // Identify addresses in each trace
-                    let mut decoder = CallTraceDecoderBuilder::new()
+                    let mut decoder = decoder
+                        .clone()
                         .with_labels(result.labeled_addresses.clone())
                         .with_events(local_identifier.events())
                         .build();
@@ -570,8 +1077,7 @@ fn test(
                     // Decode the traces
                     let mut decoded_traces = Vec::new();
                     for (kind, trace) in &mut result.traces {
-                        decoder.identify(trace, &local_identifier);
-                        decoder.identify(trace, &etherscan_identifier);
+                        decoder.identify(trace, &identifier);
 
                         let should_include = match kind {
                             // At verbosity level 3, we only display traces for failed tests
@@ -589,7 +1095,7 @@ fn test(
 
                         // We decode the trace if we either need to build a gas report or we need
                         // to print it
-                        if should_include || gas_reporting {
+                        if should_include || gas_report.is_some() {
                             decoder.decode(trace);
                         }
 
@@ -603,8 +1109,8 @@ fn test(
                         decoded_traces.into_iter().for_each(|trace| println!("{trace}"));
                     }
 
-                    if gas_reporting {
-                        gas_report.analyze(&result.traces);
+                    if let Some(gas_report) = &mut gas_report {
+                        gas_report.analyze(&contract_name, &result.traces);
                     }
                 }
             }
@@ -616,13 +1122,14 @@ fn test(
             results.insert(contract_name, suite_result);
         }
 
-        if gas_reporting {
-            println!("{}", gas_report.finalize());
+        let gas_report = gas_report.map(GasReport::finalize);
+        if let Some(gas_report) = &gas_report {
+            println!("{gas_report}");
         }
 
         // reattach the thread
         let _ = handle.join();
 
-        Ok(TestOutcome::new(results, allow_failure))
+        Ok(TestOutcome { gas_report, ..TestOutcome::new(results, allow_failure) })
     }
 }
@@ -3,6 +3,7 @@
 use crate::term;
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
 use ethers::solc::{report::NoReporter, Artifact, FileFilter, Project, ProjectCompileOutput};
+use foundry_utils::SignaturesCache;
 use std::{collections::BTreeMap, fmt::Display, path::PathBuf};
 
 /// Compiles the provided [`Project`], throws if there's any compiler error and logs whether
@@ -147,7 +148,10 @@ If you are in a subdirectory in a Git repository, try adding `--root .`"#,
 
         if output.has_compiler_errors() {
             eyre::bail!(output.to_string())
-        } else if output.is_unchanged() {
+        }
+        cache_signatures(&output);
+
+        if output.is_unchanged() {
             println!("No files changed, compilation skipped");
         } else {
             // print the compiler output / warnings
@@ -225,6 +229,7 @@ If you are in a subdirectory in a Git repository, try adding `--root .`"#,
     if output.has_compiler_errors() {
         eyre::bail!(output.to_string())
     }
+    cache_signatures(&output);
 
     Ok(output)
 }
@@ -236,6 +241,21 @@ pub fn compile_files(project: &Project, files: Vec<PathBuf>) -> eyre::Result<Pro
     if output.has_compiler_errors() {
         eyre::bail!(output.to_string())
     }
+    cache_signatures(&output);
     println!("{output}");
     Ok(output)
 }
+
+/// Adds the signatures of the functions and events of all compiled contracts to the local
+/// signatures cache, so they can be decoded in other projects as well
+fn cache_signatures(output: &ProjectCompileOutput) {
+    let mut cache = SignaturesCache::default();
+    for (_, artifact) in output.artifacts() {
+        if let Some(abi) = artifact.get_abi() {
+            cache.add_abi(&abi);
+        }
+    }
+    if let Err(err) = SignaturesCache::save(&cache) {
+        tracing::warn!(target : "forge_compile", "could not update the signatures cache: {:?}", err);
+    }
+}
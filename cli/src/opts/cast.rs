@@ -628,6 +628,34 @@ If an address is specified, then the ABI is fetched from Etherscan."#
         about = "Runs a published transaction in a local environment and prints the trace."
     )]
     Run(RunArgs),
+    #[clap(
+        name = "signatures",
+        about = "Manage the local cache of function and event signatures.",
+        long_about = r#"Manage the local cache of function and event signatures.
+
+The cache is filled with the ABIs of compiled projects and with the signatures found on 4byte.directory, and is used to decode calldata and traces offline."#
+    )]
+    Signatures {
+        #[clap(subcommand)]
+        command: SignaturesSubcommands,
+    },
+}
+
+#[derive(Debug, Parser)]
+pub enum SignaturesSubcommands {
+    #[clap(
+        name = "import",
+        about = "Import signatures from a signatures file, an ABI or an artifact into the cache."
+    )]
+    Import {
+        #[clap(help = "The path of the file to import.", value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+    },
+    #[clap(name = "export", about = "Export the cached signatures to a file.")]
+    Export {
+        #[clap(help = "The path of the file to export to.", value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+    },
 }
 
 #[derive(Debug, Parser)]
@@ -1,11 +1,16 @@
 use ethers::{
-    abi::token::{LenientTokenizer, Tokenizer},
-    solc::EvmVersion,
-    types::U256,
+    abi::{
+        token::{LenientTokenizer, Tokenizer},
+        Abi,
+    },
+    solc::{artifacts::StorageLayout, EvmVersion, ProjectCompileOutput},
+    types::{Address, U256},
 };
+use eyre::WrapErr;
 use forge::executor::{opts::EvmOpts, Fork, SpecId};
 use foundry_config::{caching::StorageCachingConfig, Config};
 use std::{
+    collections::BTreeMap,
     future::Future,
     path::{Path, PathBuf},
     str::FromStr,
@@ -59,6 +64,44 @@ impl<T: AsRef<Path>> FoundryPathExt for T {
     }
 }
 
+/// Reads the ABIs of the precompiles configured in `foundry.toml`, named after their ABI files
+pub fn load_precompiles(config: &Config) -> eyre::Result<Vec<(Address, String, Abi)>> {
+    #[derive(serde::Deserialize)]
+    struct Artifact {
+        abi: Abi,
+    }
+
+    config
+        .precompiles
+        .iter()
+        .map(|(address, path)| {
+            let path = config.__root.0.join(path);
+            let content = std::fs::read_to_string(&path)
+                .wrap_err_with(|| format!("failed to read the ABI of precompile {address:?}"))?;
+            let abi = serde_json::from_str::<Abi>(&content)
+                .or_else(|_| serde_json::from_str::<Artifact>(&content).map(|a| a.abi))
+                .wrap_err_with(|| format!("{} is not an ABI", path.display()))?;
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.split('.').next())
+                .unwrap_or_default()
+                .to_string();
+            Ok((*address, name, abi))
+        })
+        .collect()
+}
+
+/// Returns the storage layouts of the compiled contracts by their name.
+///
+/// Artifacts only contain their storage layout if it is part of the `extra_output`.
+pub fn storage_layouts(output: &ProjectCompileOutput) -> BTreeMap<String, StorageLayout> {
+    output
+        .artifacts()
+        .filter_map(|(name, artifact)| Some((name, artifact.storage_layout.clone()?)))
+        .collect()
+}
+
 /// Initializes a tracing Subscriber for logging
 #[allow(dead_code)]
 pub fn subscriber() {
@@ -227,13 +227,22 @@ impl TestProject {
         let forge = self.root.join(format!("../forge{}", env::consts::EXE_SUFFIX));
         let mut cmd = process::Command::new(forge);
         cmd.current_dir(self.inner.root());
+        cmd.env("FOUNDRY_SIGNATURES_CACHE", self.signatures_cache_path());
         cmd
     }
 
     /// Returns the path to the cast executable.
     pub fn cast_bin(&self) -> process::Command {
         let cast = self.root.join(format!("../cast{}", env::consts::EXE_SUFFIX));
-        process::Command::new(cast)
+        let mut cmd = process::Command::new(cast);
+        cmd.env("FOUNDRY_SIGNATURES_CACHE", self.signatures_cache_path());
+        cmd
+    }
+
+    /// Returns the path of the signatures cache of the project's commands, so they don't write to
+    /// the user's cache
+    pub fn signatures_cache_path(&self) -> PathBuf {
+        self.root().join("cache").join("signatures.json")
     }
 
     /// Returns the `Config` as spit out by `forge config`
@@ -455,6 +455,65 @@ contract BTest is DSTest {
     let _ = cmd.output();
 });
 
+// test that structured snapshots record the fuzz seed and allow tolerances
+forgetest!(can_check_structured_snapshot, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    let add_test = |loops: usize| {
+        prj.inner()
+            .add_source(
+                "ATest.t.sol",
+                format!(
+                    r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {{
+    uint256 number;
+    function testExample() public {{
+        for (uint256 i = 0; i < {loops}; i++) {{
+            number++;
+        }}
+    }}
+    function testFuzz(uint256 x) public {{
+        number = x % 100;
+    }}
+}}
+   "#
+                ),
+            )
+            .unwrap();
+    };
+
+    add_test(10);
+    let snap = prj.root().join("gas-snapshot.toml");
+    cmd.args(["snapshot", "--include-fuzz-tests", "--snap"]).arg(&snap);
+    cmd.assert_non_empty_stdout();
+    let content = std::fs::read_to_string(&snap).unwrap();
+    assert!(content.contains("version = 2"));
+    assert!(content.contains("fuzz_seed = \"0x0\""));
+    assert!(content.contains("[tests.\"src/ATest.t.sol\".ATest"));
+
+    // fuzz tests are run with the seed of the snapshot, so the snapshot matches
+    cmd.forge_fuse().args(["snapshot", "--include-fuzz-tests", "--check"]).arg(&snap);
+    assert!(cmd.unchecked_output().status.success());
+
+    // a small regression fails the check, unless it is within the tolerance of the entry
+    add_test(11);
+    cmd.forge_fuse().args(["snapshot", "--include-fuzz-tests", "--check"]).arg(&snap);
+    let output = cmd.unchecked_output();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Diff in \"ATest::testExample()\""));
+
+    let content = content.replace(
+        "[tests.\"src/ATest.t.sol\".ATest.\"testExample()\"]\n",
+        "[tests.\"src/ATest.t.sol\".ATest.\"testExample()\"]\ntolerance = 50.0\n",
+    );
+    std::fs::write(&snap, content).unwrap();
+    cmd.forge_fuse().args(["snapshot", "--include-fuzz-tests", "--check"]).arg(&snap);
+    assert!(cmd.unchecked_output().status.success());
+});
+
 // test that `forge build` does not print `(with warnings)` if there arent any
 forgetest!(can_compile_without_warnings, |prj: TestProject, mut cmd: TestCommand| {
     let config = Config {
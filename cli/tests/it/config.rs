@@ -12,7 +12,7 @@ use foundry_cli_test_utils::{
 };
 use foundry_config::{
     caching::{CachedChains, CachedEndpoints, StorageCachingConfig},
-    Config, OptimizerDetails, SolcReq,
+    Config, OptimizerDetails, SolcReq, TestReporter,
 };
 use std::{fs, path::PathBuf, str::FromStr};
 
@@ -37,6 +37,7 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         force: true,
         evm_version: EvmVersion::Byzantium,
         gas_reports: vec!["Contract".to_string()],
+        gas_report_threshold: Some(5.0),
         solc: Some(SolcReq::Local(PathBuf::from("custom-solc"))),
         auto_detect_solc: false,
         offline: true,
@@ -58,8 +59,13 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         path_pattern: None,
         path_pattern_inverse: None,
         fuzz_runs: 1000,
+        fuzz_seed: Some(1337u64.into()),
         fuzz_max_local_rejects: 2000,
         fuzz_max_global_rejects: 100203,
+        fuzz_coverage_guided: true,
+        test_timeout: Some(60),
+        fuzz_timeout: Some(600),
+        test_reporter: TestReporter::Tap,
         ffi: true,
         sender: "00a329c0648769A73afAc7F9381D08FB43dBEA72".parse().unwrap(),
         tx_origin: "00a329c0648769A73afAc7F9F81E08FB43dBEA72".parse().unwrap(),
@@ -77,6 +83,8 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         memory_limit: 2u64.pow(25),
         eth_rpc_url: Some("localhost".to_string()),
         etherscan_api_key: None,
+        sourcify: Some("https://repo.sourcify.dev".to_string()),
+        precompiles: [(Address::from_low_u64_be(0x64), "abi/ArbSys.json".into())].into(),
         verbosity: 4,
         remappings: vec![Remapping::from_str("ds-test=lib/ds-test/").unwrap().into()],
         libraries: vec![
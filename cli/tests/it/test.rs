@@ -128,3 +128,377 @@ contract FailTest is DSTest {
     cmd.args(["test", "--match-path", "*src/ATest.t.sol"]);
     cmd.stdout().contains("[PASS]") && !cmd.stdout().contains("[FAIL]")
 });
+
+// tests that `--rerun` only runs the tests that failed in the previous run
+forgetest!(can_rerun_failed_tests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "RerunTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract RerunTest is DSTest {
+    function testPass() external {
+        assertTrue(true);
+    }
+
+    function testFailure() external {
+        assertTrue(false);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.arg("test");
+    let stdout = String::from_utf8_lossy(&cmd.unchecked_output().stdout).to_string();
+    assert!(stdout.contains("testPass()"));
+    assert!(stdout.contains("testFailure()"));
+
+    cmd.arg("--rerun");
+    let stdout = String::from_utf8_lossy(&cmd.unchecked_output().stdout).to_string();
+    assert!(!stdout.contains("testPass()"));
+    assert!(stdout.contains("testFailure()"));
+});
+
+// tests that `--rerun` runs no tests if the last run recorded no failures
+forgetest!(can_rerun_without_failures, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "RerunPassTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract RerunPassTest is DSTest {
+    function testPass() external {
+        assertTrue(true);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    // nothing recorded yet
+    cmd.args(["test", "--rerun"]);
+    assert!(cmd.stdout_lossy().contains("No failures recorded by the last run"));
+
+    cmd.forge_fuse().arg("test");
+    assert!(cmd.stdout_lossy().contains("testPass()"));
+
+    cmd.arg("--rerun");
+    let stdout = cmd.stdout_lossy();
+    assert!(stdout.contains("No failures recorded by the last run"));
+    assert!(!stdout.contains("testPass()"));
+});
+
+// tests that `--rerun` runs all tests of a contract whose `setUp` failed, and that filtered runs
+// keep the failures of the contracts they don't run
+forgetest!(can_rerun_failed_setup, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "RerunSetUpTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract RerunSetUpTest is DSTest {
+    function setUp() public {
+        require(false, "setup failure");
+    }
+
+    function testA() external {}
+}
+
+contract RerunOtherTest is DSTest {
+    function testPass() external {
+        assertTrue(true);
+    }
+
+    function testFailure() external {
+        assertTrue(false);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.arg("test");
+    let stdout = String::from_utf8_lossy(&cmd.unchecked_output().stdout).to_string();
+    assert!(stdout.contains("setUp()"));
+    assert!(stdout.contains("testFailure()"));
+
+    // only rerun the contract with the failing `setUp`
+    cmd.args(["--rerun", "--match-contract", "RerunSetUpTest"]);
+    let stdout = String::from_utf8_lossy(&cmd.unchecked_output().stdout).to_string();
+    assert!(stdout.contains("setUp()"));
+    assert!(!stdout.contains("testFailure()"));
+
+    // the failures of the other contract are still recorded
+    cmd.forge_fuse().args(["test", "--rerun"]);
+    let stdout = String::from_utf8_lossy(&cmd.unchecked_output().stdout).to_string();
+    assert!(stdout.contains("setUp()"));
+    assert!(stdout.contains("testFailure()"));
+    assert!(!stdout.contains("testPass()"));
+});
+
+// tests that `--reporter junit` prints nothing but the JUnit XML report
+forgetest!(can_report_junit, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPass() external {
+        assertTrue(true);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--reporter", "junit"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+    assert!(stdout.contains(r#"<testcase name="testPass()" classname="src/ATest.t.sol:ATest">"#));
+    assert!(stdout.trim_end().ends_with("</testsuites>"));
+});
+
+// tests that `--list --json` prints the matching tests without running them
+forgetest!(can_list_tests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testFail() external {
+        assertTrue(false);
+    }
+    function testFuzz(uint256 x) external {
+        assertTrue(x > 0);
+    }
+    function helper() external {}
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--list", "--json"]);
+    let tests: serde_json::Value = serde_json::from_str(&cmd.stdout()).unwrap();
+    assert_eq!(
+        tests,
+        serde_json::json!({ "src/ATest.t.sol": { "ATest": ["testFail()", "testFuzz(uint256)"] } })
+    );
+});
+
+// tests that every test contract is run by exactly one shard and that the shard reports merge
+forgetest!(can_shard_tests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    for i in 0..4 {
+        prj.inner()
+            .add_source(
+                format!("Shard{i}.t.sol"),
+                format!(
+                    r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract Shard{i}Test is DSTest {{
+    function testPass() external {{
+        assertTrue(true);
+    }}
+}}
+   "#
+                ),
+            )
+            .unwrap();
+    }
+
+    let mut reports = Vec::new();
+    for shard in ["1/2", "2/2"] {
+        cmd.forge_fuse().args(["test", "--json", "--shard", shard]);
+        let report = prj.root().join(format!("shard-{}.json", &shard[..1]));
+        std::fs::write(&report, cmd.stdout()).unwrap();
+        reports.push(report);
+    }
+
+    cmd.forge_fuse().args(["test", "--merge-reports"]).args(&reports);
+    let merged: serde_json::Value = serde_json::from_str(&cmd.stdout()).unwrap();
+    let contracts = merged.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+    assert_eq!(
+        contracts,
+        (0..4).map(|i| format!("src/Shard{i}.t.sol:Shard{i}Test")).collect::<Vec<_>>()
+    );
+});
+
+// tests that the traces of all tests can be exported as JSON and in the chrome trace format
+forgetest!(can_export_traces, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "Counter.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract Counter {
+    uint256 public number;
+    function increment() external {
+        number++;
+    }
+}
+contract CounterTest is DSTest {
+    function testIncrement() external {
+        Counter counter = new Counter();
+        counter.increment();
+        assertEq(counter.number(), 1);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let path = prj.root().join("traces.json");
+    cmd.arg("test").arg("--export-traces").arg(&path);
+    cmd.stdout();
+    let traces: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let run = &traces["src/Counter.t.sol:CounterTest"]["testIncrement()"];
+    let execution =
+        run.as_array().unwrap().iter().find(|trace| trace["kind"] == "Execution").unwrap();
+    assert_eq!(execution["trace"]["function"], "testIncrement");
+    assert_eq!(execution["trace"]["calls"][1]["function"], "increment");
+
+    let path = prj.root().join("chrome.json");
+    cmd.forge_fuse()
+        .arg("test")
+        .arg("--export-traces")
+        .arg(&path)
+        .args(["--trace-format", "chrome"]);
+    cmd.stdout();
+    let traces: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert!(traces["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|event| event["name"] == "Counter::increment"));
+});
+
+// tests that gas profiles keep the call hierarchy of the tests
+forgetest!(can_write_gas_profile, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "Counter.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract Counter {
+    uint256 public number;
+    function increment() external {
+        number++;
+    }
+}
+contract CounterTest is DSTest {
+    function testIncrement() external {
+        Counter counter = new Counter();
+        counter.increment();
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let path = prj.root().join("profile.folded");
+    cmd.arg("test").arg("--gas-profile").arg(&path);
+    cmd.stdout();
+    let folded = std::fs::read_to_string(&path).unwrap();
+    assert!(folded
+        .lines()
+        .any(|line| line.starts_with("CounterTest::testIncrement;Counter::increment ")));
+
+    let path = prj.root().join("profile.svg");
+    cmd.forge_fuse().arg("test").arg("--gas-profile").arg(&path);
+    cmd.stdout();
+    assert!(std::fs::read_to_string(&path).unwrap().starts_with("<svg"));
+});
+
+// tests that gas reports can be diffed against a baseline and fail on regressions
+forgetest!(can_diff_gas_report_against_baseline, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    let add_counter = |loops: usize| {
+        prj.inner()
+            .add_source(
+                "Counter.t.sol",
+                format!(
+                    r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract Counter {{
+    uint256 public number;
+    function increment() external {{
+        for (uint256 i = 0; i < {loops}; i++) {{
+            number++;
+        }}
+    }}
+}}
+contract CounterTest is DSTest {{
+    function testIncrement() external {{
+        Counter counter = new Counter();
+        counter.increment();
+    }}
+}}
+   "#
+                ),
+            )
+            .unwrap();
+    };
+
+    add_counter(1);
+    let baseline = prj.root().join("gas-report.json");
+    cmd.args(["test", "--gas-report", "--gas-report-output"]).arg(&baseline);
+    cmd.stdout();
+    assert!(baseline.exists());
+
+    // an unchanged run passes any threshold
+    cmd.forge_fuse()
+        .args(["test", "--gas-report", "--gas-report-threshold", "0", "--gas-report-baseline"])
+        .arg(&baseline);
+    assert!(cmd.stdout().contains("No gas changes relative to the baseline"));
+
+    add_counter(10);
+    let markdown = prj.root().join("gas-report.md");
+    cmd.forge_fuse()
+        .args(["test", "--gas-report", "--gas-report-threshold", "5", "--gas-report-baseline"])
+        .arg(&baseline)
+        .arg("--gas-report-output")
+        .arg(&markdown);
+    let output = cmd.unchecked_output();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("exceeded the threshold of 5%"));
+    assert!(std::fs::read_to_string(&markdown).unwrap().contains("### Gas changes"));
+});
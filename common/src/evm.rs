@@ -84,6 +84,8 @@ pub struct EvmArgs {
     /// - 3: Print execution traces for failing tests
     /// - 4: Print execution traces for all tests, and setup traces for failing tests
     /// - 5: Print execution and setup traces for all tests
+    /// - 6: Print execution and setup traces for all tests, with the storage slots written in each
+    ///   call
     #[clap(long, short, parse(from_occurrences), verbatim_doc_comment)]
     #[serde(skip)]
     pub verbosity: u8,
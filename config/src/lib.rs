@@ -3,6 +3,8 @@
 
 use std::{
     borrow::Cow,
+    collections::BTreeMap,
+    fmt,
     path::{Path, PathBuf},
     str::FromStr,
 };
@@ -109,6 +111,8 @@ pub struct Config {
     pub evm_version: EvmVersion,
     /// list of contracts to report gas of
     pub gas_reports: Vec<String>,
+    /// the increase in gas, in percent, over the baseline of a gas report above which a run fails
+    pub gas_report_threshold: Option<f64>,
     /// The Solc instance to use if any.
     ///
     /// This takes precedence over `auto_detect_solc`, if a version is set then this overrides
@@ -140,6 +144,12 @@ pub struct Config {
     pub eth_rpc_url: Option<String>,
     /// etherscan API key
     pub etherscan_api_key: Option<String>,
+    /// the Sourcify repository to identify contracts of remote chains with, either the path of a
+    /// local mirror or the url of a repository, e.g. `https://repo.sourcify.dev`
+    pub sourcify: Option<String>,
+    /// contracts at fixed addresses, e.g. the precompiles of a chain, to decode calls to in
+    /// traces, mapped to the path of their ABI or an artifact, relative to the project root
+    pub precompiles: BTreeMap<Address, PathBuf>,
     /// list of solidity error codes to always silence in the compiler output
     pub ignored_error_codes: Vec<SolidityErrorCode>,
     /// Only run test functions matching the specified regex pattern.
@@ -162,6 +172,8 @@ pub struct Config {
     pub path_pattern_inverse: Option<globset::Glob>,
     /// The number of test cases that must execute for each property test
     pub fuzz_runs: u32,
+    /// The seed of the fuzzer, for reproducible fuzz runs
+    pub fuzz_seed: Option<U256>,
     /// Whether to allow ffi cheatcodes in test
     pub ffi: bool,
     /// The address which will be executing all tests
@@ -226,6 +238,15 @@ pub struct Config {
     /// by proptest, to be encountered during usage of `vm.assume`
     /// cheatcode.
     pub fuzz_max_global_rejects: u32,
+    /// Whether fuzz tests keep inputs that reach new branches in a corpus and mutate them
+    /// preferentially.
+    pub fuzz_coverage_guided: bool,
+    /// The wall-clock time in seconds after which a single test call is halted and fails
+    pub test_timeout: Option<u64>,
+    /// The wall-clock time in seconds after which a fuzz campaign is stopped and fails
+    pub fuzz_timeout: Option<u64>,
+    /// The format `forge test` reports test results in
+    pub test_reporter: TestReporter,
     /// Print the names of the compiled contracts
     pub names: bool,
     /// Print the sizes of the compiled contracts
@@ -989,6 +1010,7 @@ impl Default for Config {
             force: false,
             evm_version: Default::default(),
             gas_reports: vec!["*".to_string()],
+            gas_report_threshold: None,
             solc: None,
             auto_detect_solc: true,
             offline: false,
@@ -1006,8 +1028,13 @@ impl Default for Config {
             path_pattern: None,
             path_pattern_inverse: None,
             fuzz_runs: 256,
+            fuzz_seed: None,
             fuzz_max_local_rejects: 1024,
             fuzz_max_global_rejects: 65536,
+            fuzz_coverage_guided: false,
+            test_timeout: None,
+            fuzz_timeout: None,
+            test_reporter: TestReporter::Human,
             ffi: false,
             sender: "00a329c0648769A73afAc7F9381E08FB43dBEA72".parse().unwrap(),
             tx_origin: "00a329c0648769A73afAc7F9381E08FB43dBEA72".parse().unwrap(),
@@ -1025,6 +1052,8 @@ impl Default for Config {
             memory_limit: 2u64.pow(25),
             eth_rpc_url: None,
             etherscan_api_key: None,
+            sourcify: None,
+            precompiles: Default::default(),
             verbosity: 0,
             remappings: vec![],
             libraries: vec![],
@@ -1177,6 +1206,47 @@ impl<T: AsRef<str>> From<T> for SolcReq {
     }
 }
 
+/// The formats `forge test` can report test results in
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestReporter {
+    /// Human readable output
+    Human,
+    /// JUnit XML
+    Junit,
+    /// Test Anything Protocol
+    Tap,
+}
+
+impl Default for TestReporter {
+    fn default() -> Self {
+        TestReporter::Human
+    }
+}
+
+impl fmt::Display for TestReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TestReporter::Human => f.write_str("human"),
+            TestReporter::Junit => f.write_str("junit"),
+            TestReporter::Tap => f.write_str("tap"),
+        }
+    }
+}
+
+impl FromStr for TestReporter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(TestReporter::Human),
+            "junit" => Ok(TestReporter::Junit),
+            "tap" => Ok(TestReporter::Tap),
+            _ => Err(format!("Unknown test reporter: {s}, expected one of: human, junit, tap")),
+        }
+    }
+}
+
 /// A Provider that ensures all keys are snake case
 struct ForcedSnakeCaseData<F: Format>(Data<F>);
 
@@ -1,7 +1,7 @@
 use crate::{abi::HEVM_ABI, CallKind};
 use ethers::types::{Address, U256};
 use revm::{Memory, OpCode};
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display};
 
 /// An arena of [DebugNode]s
 #[derive(Default, Debug, Clone)]
@@ -61,19 +61,49 @@ impl DebugArena {
     /// - The address of the contract being executed
     /// - A [Vec] of debug steps along that contract's execution path
     /// - An enum denoting the type of call this is
+    /// - The depth of the call
+    ///
+    /// The items are in execution order: the steps of a caller are split into separate items
+    /// around each of its sub-calls.
     ///
     /// This makes it easy to pretty print the execution steps.
-    pub fn flatten(&self, entry: usize) -> Vec<(Address, Vec<DebugStep>, CallKind)> {
+    pub fn flatten(&self, entry: usize) -> Vec<(Address, Vec<DebugStep>, CallKind, usize)> {
         let node = &self.arena[entry];
 
         let mut flattened = vec![];
         if !node.steps.is_empty() {
-            flattened.push((node.address, node.steps.clone(), node.kind));
+            flattened.push((node.address, node.steps.clone(), node.kind, node.depth));
         }
         flattened.extend(node.children.iter().flat_map(|child| self.flatten(*child)));
 
         flattened
     }
+
+    /// Returns the named breakpoints set with `vm.breakpoint`, along with the index of their call
+    /// and step in the flattened arena, see [DebugArena::flatten].
+    pub fn breakpoints(&self, entry: usize) -> Vec<(String, usize, usize)> {
+        fn collect(
+            arena: &DebugArena,
+            idx: usize,
+            call: &mut usize,
+            breakpoints: &mut Vec<(String, usize, usize)>,
+        ) {
+            let node = &arena.arena[idx];
+            if !node.steps.is_empty() {
+                breakpoints.extend(
+                    node.breakpoints.iter().map(|(step, name)| (name.clone(), *call, *step)),
+                );
+                *call += 1;
+            }
+            for child in &node.children {
+                collect(arena, *child, call, breakpoints);
+            }
+        }
+
+        let mut breakpoints = vec![];
+        collect(self, entry, &mut 0, &mut breakpoints);
+        breakpoints
+    }
 }
 
 /// A node in the arena
@@ -95,6 +125,8 @@ pub struct DebugNode {
     pub depth: usize,
     /// The debug steps
     pub steps: Vec<DebugStep>,
+    /// The named breakpoints set with `vm.breakpoint`, by the index of the step they were set at
+    pub breakpoints: BTreeMap<usize, String>,
 }
 
 impl DebugNode {
@@ -187,3 +219,81 @@ impl Display for Instruction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{inspector::Debugger, CHEATCODE_ADDRESS};
+
+    /// Pushes steps with the given instruction counters to the current node
+    fn steps(debugger: &mut Debugger, ics: impl IntoIterator<Item = usize>) {
+        let node = &mut debugger.arena.arena[debugger.head];
+        node.steps.extend(ics.into_iter().map(|ic| DebugStep { ic, ..Default::default() }));
+    }
+
+    #[test]
+    fn flattens_in_execution_order() {
+        let mut debugger = Debugger::default();
+        debugger.enter(0, Address::repeat_byte(0xa), CallKind::Call);
+        steps(&mut debugger, 0..2);
+        debugger.enter(1, Address::repeat_byte(0xb), CallKind::Call);
+        steps(&mut debugger, 0..1);
+        debugger.enter(2, Address::repeat_byte(0xc), CallKind::Call);
+        steps(&mut debugger, 0..1);
+        debugger.exit();
+        steps(&mut debugger, 1..2);
+        debugger.exit();
+        steps(&mut debugger, 2..3);
+        // a call to an account without code
+        debugger.enter(1, Address::repeat_byte(0xd), CallKind::Call);
+        debugger.exit();
+        steps(&mut debugger, 3..4);
+
+        let flattened = debugger
+            .arena
+            .flatten(0)
+            .into_iter()
+            .map(|(address, steps, _, depth)| {
+                (address, steps.iter().map(|step| step.ic).collect::<Vec<_>>(), depth)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            flattened,
+            vec![
+                (Address::repeat_byte(0xa), vec![0, 1], 0),
+                (Address::repeat_byte(0xb), vec![0], 1),
+                (Address::repeat_byte(0xc), vec![0], 2),
+                (Address::repeat_byte(0xb), vec![1], 1),
+                (Address::repeat_byte(0xa), vec![2], 0),
+                (Address::repeat_byte(0xa), vec![3], 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_find_breakpoints() {
+        let mut debugger = Debugger::default();
+        debugger.enter(0, Address::repeat_byte(0xa), CallKind::Call);
+        steps(&mut debugger, 0..2);
+        debugger.arena.arena[debugger.head].breakpoints.insert(1, "a".to_string());
+        // the breakpoint cheatcode, which is recorded at the next step of the caller
+        debugger.enter(1, CHEATCODE_ADDRESS, CallKind::Call);
+        steps(&mut debugger, 0..1);
+        debugger.exit();
+        debugger.arena.arena[debugger.head].breakpoints.insert(0, "b".to_string());
+        steps(&mut debugger, 2..4);
+        // a call to an account without code, which is not part of the flattened arena
+        debugger.enter(1, Address::repeat_byte(0xb), CallKind::Call);
+        debugger.exit();
+        debugger.arena.arena[debugger.head].breakpoints.insert(1, "c".to_string());
+        steps(&mut debugger, 4..6);
+
+        assert_eq!(
+            debugger.arena.breakpoints(0),
+            vec![("a".to_string(), 0, 1), ("b".to_string(), 2, 0), ("c".to_string(), 3, 1)]
+        );
+        let flattened = debugger.arena.flatten(0);
+        assert_eq!(flattened[2].1[0].ic, 2);
+        assert_eq!(flattened[3].1[1].ic, 5);
+    }
+}
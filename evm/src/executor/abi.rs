@@ -45,6 +45,7 @@ ethers::contract::abigen!(
             setNonce(address,uint64)
             getNonce(address)
             chainId(uint256)
+            breakpoint(string)
     ]"#,
 );
 pub use hevm_mod::{HEVMCalls, HEVM_ABI};
@@ -3,7 +3,7 @@ use revm::{
     db::{DatabaseRef, EmptyDB},
     Env, SpecId,
 };
-use std::{path::PathBuf, sync::Arc};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use super::{
     fork::SharedBackend,
@@ -144,6 +144,13 @@ impl ExecutorBuilder {
         self
     }
 
+    /// Records the storage slots written in each call in the traces, if tracing is enabled
+    #[must_use]
+    pub fn with_storage_tracing(mut self) -> Self {
+        self.inspector_config.storage_tracing = true;
+        self
+    }
+
     /// Enables the debugger
     #[must_use]
     pub fn with_debugger(mut self) -> Self {
@@ -151,6 +158,20 @@ impl ExecutorBuilder {
         self
     }
 
+    /// Enables `JUMPI` edge coverage
+    #[must_use]
+    pub fn with_coverage(mut self) -> Self {
+        self.inspector_config.coverage = true;
+        self
+    }
+
+    /// Sets the wall-clock time after which calls are halted
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.inspector_config.timeout = timeout;
+        self
+    }
+
     /// Sets the EVM spec to use
     #[must_use]
     pub fn with_spec(mut self, spec: SpecId) -> Self {
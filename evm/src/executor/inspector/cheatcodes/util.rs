@@ -60,6 +60,8 @@ pub fn apply<DB: Database>(
             state.labels.insert(inner.0, inner.1.clone());
             Ok(Bytes::new())
         }
+        // Breakpoints are recorded by the debugger
+        HEVMCalls::Breakpoint(_) => Ok(Bytes::new()),
         _ => return None,
     })
 }
@@ -0,0 +1,42 @@
+use ethers::types::Address;
+use revm::{opcode, Database, EVMData, Inspector, Interpreter, Return};
+use std::collections::HashSet;
+
+/// A branch in the bytecode of a contract.
+///
+/// Consists of the address of the contract, the program counter of the `JUMPI` instruction and
+/// whether the jump was taken or not.
+pub type Edge = (Address, usize, bool);
+
+/// An inspector that records the `JUMPI` edges hit during execution.
+///
+/// Used as feedback for coverage-guided fuzzing.
+#[derive(Default, Debug)]
+pub struct EdgeCoverage {
+    /// The edges hit so far
+    pub edges: HashSet<Edge>,
+}
+
+impl<DB> Inspector<DB> for EdgeCoverage
+where
+    DB: Database,
+{
+    fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+        _: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        let pc = interpreter.program_counter();
+        if interpreter.contract.code[pc] == opcode::JUMPI {
+            // The destination is on top of the stack, followed by the condition
+            let stack = interpreter.stack().data();
+            if stack.len() >= 2 {
+                let taken = !stack[stack.len() - 2].is_zero();
+                self.edges.insert((interpreter.contract().address, pc, taken));
+            }
+        }
+
+        Return::Continue
+    }
+}
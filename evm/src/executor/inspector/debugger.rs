@@ -1,4 +1,5 @@
 use crate::{
+    abi::HEVMCalls,
     debug::{DebugArena, DebugNode, DebugStep, Instruction},
     executor::{
         inspector::utils::{gas_used, get_create_address},
@@ -7,7 +8,7 @@ use crate::{
     CallKind,
 };
 use bytes::Bytes;
-use ethers::types::Address;
+use ethers::{abi::AbiDecode, types::Address};
 use revm::{
     opcode, spec_opcode_gas, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector,
     Interpreter, Memory, Return, SpecId,
@@ -45,6 +46,9 @@ pub struct Debugger {
     ///
     /// For more information on gas blocks, see [current_gas_block].
     pub previous_gas_block: u64,
+    /// The name of the breakpoint set with `vm.breakpoint`, which is recorded at the next step
+    /// of the caller
+    pub pending_breakpoint: Option<String>,
 }
 
 impl Debugger {
@@ -113,6 +117,9 @@ where
                 ),
                 ..Default::default()
             });
+            if let Ok(HEVMCalls::Breakpoint(inner)) = HEVMCalls::decode(&call.input) {
+                self.pending_breakpoint = Some(inner.0);
+            }
         }
 
         (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
@@ -166,7 +173,11 @@ where
             self.current_gas_block += opcode_info.gas;
         }
 
-        self.arena.arena[self.head].steps.push(DebugStep {
+        let node = &mut self.arena.arena[self.head];
+        if let Some(name) = self.pending_breakpoint.take() {
+            node.breakpoints.insert(node.steps.len(), name);
+        }
+        node.steps.push(DebugStep {
             pc,
             stack: interpreter.stack().data().clone(),
             memory: interpreter.memory.clone(),
@@ -10,6 +10,12 @@ pub use tracer::Tracer;
 mod debugger;
 pub use debugger::Debugger;
 
+mod coverage;
+pub use coverage::{Edge, EdgeCoverage};
+
+mod timeout;
+pub use timeout::Timeout;
+
 mod stack;
 pub use stack::{InspectorData, InspectorStack};
 
@@ -17,6 +23,7 @@ mod cheatcodes;
 pub use cheatcodes::Cheatcodes;
 
 use revm::BlockEnv;
+use std::time::Duration;
 
 #[derive(Default, Clone, Debug)]
 pub struct InspectorStackConfig {
@@ -30,8 +37,14 @@ pub struct InspectorStackConfig {
     pub block: BlockEnv,
     /// Whether or not tracing is enabled
     pub tracing: bool,
+    /// Whether or not the storage slots written in each call are recorded in the traces
+    pub storage_tracing: bool,
     /// Whether or not the debugger is enabled
     pub debugger: bool,
+    /// Whether or not `JUMPI` edge coverage is recorded
+    pub coverage: bool,
+    /// The wall-clock time after which a call is halted
+    pub timeout: Option<Duration>,
 }
 
 impl InspectorStackConfig {
@@ -45,11 +58,16 @@ impl InspectorStackConfig {
         }
 
         if self.tracing {
-            stack.tracer = Some(Tracer::default());
+            stack.tracer =
+                Some(Tracer { record_storage: self.storage_tracing, ..Default::default() });
         }
         if self.debugger {
             stack.debugger = Some(Debugger::default());
         }
+        if self.coverage {
+            stack.coverage = Some(EdgeCoverage::default());
+        }
+        stack.timeout = self.timeout.map(Timeout::new);
         stack
     }
 }
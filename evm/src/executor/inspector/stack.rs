@@ -1,4 +1,4 @@
-use super::{Cheatcodes, Debugger, LogCollector, Tracer};
+use super::{Cheatcodes, Debugger, Edge, EdgeCoverage, LogCollector, Timeout, Tracer};
 use crate::{debug::DebugArena, trace::CallTraceArena};
 use bytes::Bytes;
 use ethers::{
@@ -6,7 +6,7 @@ use ethers::{
     types::{Address, H256},
 };
 use revm::{db::Database, CallInputs, CreateInputs, EVMData, Gas, Inspector, Interpreter, Return};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 
 /// Helper macro to call the same method on multiple inspectors without resorting to dynamic
 /// dispatch
@@ -26,6 +26,8 @@ pub struct InspectorData {
     pub traces: Option<CallTraceArena>,
     pub debug: Option<DebugArena>,
     pub cheatcodes: Option<Cheatcodes>,
+    pub coverage: Option<HashSet<Edge>>,
+    pub timed_out: bool,
 }
 
 /// An inspector that calls multiple inspectors in sequence.
@@ -38,6 +40,8 @@ pub struct InspectorStack {
     pub logs: Option<LogCollector>,
     pub cheatcodes: Option<Cheatcodes>,
     pub debugger: Option<Debugger>,
+    pub coverage: Option<EdgeCoverage>,
+    pub timeout: Option<Timeout>,
 }
 
 impl InspectorStack {
@@ -52,6 +56,8 @@ impl InspectorStack {
             traces: self.tracer.map(|tracer| tracer.traces),
             debug: self.debugger.map(|debugger| debugger.arena),
             cheatcodes: self.cheatcodes,
+            coverage: self.coverage.map(|coverage| coverage.edges),
+            timed_out: self.timeout.map_or(false, |timeout| timeout.timed_out),
         }
     }
 }
@@ -90,7 +96,14 @@ where
     ) -> Return {
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.debugger,
+                &mut self.tracer,
+                &mut self.logs,
+                &mut self.cheatcodes,
+                &mut self.coverage,
+                &mut self.timeout
+            ],
             {
                 let status = inspector.step(interpreter, data, is_static);
 
@@ -0,0 +1,48 @@
+use revm::{Database, EVMData, Inspector, Interpreter, Return};
+use std::time::{Duration, Instant};
+
+/// The number of steps between two checks of the clock, since checking it on every step is
+/// expensive.
+const CHECK_INTERVAL: usize = 1024;
+
+/// An inspector that halts execution once it ran for longer than a timeout.
+///
+/// Once the timeout expired, every frame is halted as if it ran out of gas.
+#[derive(Debug, Clone)]
+pub struct Timeout {
+    /// The instant after which execution is halted
+    deadline: Instant,
+    /// The number of steps executed so far
+    steps: usize,
+    /// Whether the timeout expired, in which case execution was halted
+    pub timed_out: bool,
+}
+
+impl Timeout {
+    pub fn new(timeout: Duration) -> Self {
+        Self { deadline: Instant::now() + timeout, steps: 0, timed_out: false }
+    }
+}
+
+impl<DB> Inspector<DB> for Timeout
+where
+    DB: Database,
+{
+    fn step(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        self.steps += 1;
+        if !self.timed_out && self.steps % CHECK_INTERVAL == 0 {
+            self.timed_out = Instant::now() >= self.deadline;
+        }
+
+        if self.timed_out {
+            Return::OutOfGas
+        } else {
+            Return::Continue
+        }
+    }
+}
@@ -2,7 +2,7 @@ use crate::{
     executor::inspector::utils::{gas_used, get_create_address},
     trace::{
         CallTrace, CallTraceArena, LogCallOrder, RawOrDecodedCall, RawOrDecodedLog,
-        RawOrDecodedReturnData,
+        RawOrDecodedReturnData, StorageChange,
     },
     CallKind,
 };
@@ -10,14 +10,24 @@ use bytes::Bytes;
 use ethers::{
     abi::RawLog,
     types::{Address, H256, U256},
+    utils::keccak256,
 };
-use revm::{return_ok, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Return};
+use revm::{
+    opcode, return_ok, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter,
+    Return,
+};
+
+/// The largest preimage of a keccak256 hash that is recorded, larger ones are not mapping keys
+const MAX_PREIMAGE_SIZE: usize = 1024;
 
 /// An inspector that collects call traces.
 #[derive(Default, Debug)]
 pub struct Tracer {
     pub trace_stack: Vec<usize>,
     pub traces: CallTraceArena,
+    /// Whether the storage slots written in each call are recorded, along with the preimages of
+    /// the hashes mapping slots are derived from
+    pub record_storage: bool,
 }
 
 impl Tracer {
@@ -66,6 +76,62 @@ impl<DB> Inspector<DB> for Tracer
 where
     DB: Database,
 {
+    fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        if !self.record_storage {
+            return Return::Continue
+        }
+
+        let pc = interpreter.program_counter();
+        let stack = interpreter.stack().data();
+        match interpreter.contract.code[pc] {
+            opcode::SSTORE if !is_static && stack.len() >= 2 => {
+                // The slot is on top of the stack, followed by the value
+                let address = interpreter.contract().address;
+                let slot = stack[stack.len() - 1];
+                let value = stack[stack.len() - 2];
+                // Look up the previous value without loading the slot, which would make it warm
+                let previous = data
+                    .subroutine
+                    .state()
+                    .get(&address)
+                    .and_then(|account| account.storage.get(&slot).copied())
+                    .unwrap_or_else(|| data.db.storage(address, slot));
+
+                let node =
+                    &mut self.traces.arena[*self.trace_stack.last().expect("no ongoing trace")];
+                node.ordering.push(LogCallOrder::Storage(node.storage_changes.len()));
+                node.storage_changes.push(StorageChange {
+                    slot,
+                    previous,
+                    value,
+                    decoded: Vec::new(),
+                });
+            }
+            opcode::SHA3 if stack.len() >= 2 => {
+                // The offset is on top of the stack, followed by the size
+                let memory = interpreter.memory.data();
+                let (offset, size) = (stack[stack.len() - 1], stack[stack.len() - 2]);
+                let len = U256::from(memory.len());
+                if size >= U256::from(32) &&
+                    size <= U256::from(MAX_PREIMAGE_SIZE) &&
+                    offset <= len &&
+                    size <= len - offset
+                {
+                    let preimage = memory[offset.as_usize()..(offset + size).as_usize()].to_vec();
+                    self.traces.preimages.insert(H256::from(keccak256(&preimage)), preimage);
+                }
+            }
+            _ => {}
+        }
+
+        Return::Continue
+    }
+
     fn call(
         &mut self,
         data: &mut EVMData<'_, DB>,
@@ -26,7 +26,7 @@ pub use revm::db::DatabaseRef;
 
 pub use revm::Env;
 
-use self::inspector::{InspectorData, InspectorStackConfig};
+use self::inspector::{Edge, InspectorData, InspectorStackConfig};
 use crate::{debug::DebugArena, trace::CallTraceArena, CALLER};
 use bytes::Bytes;
 use ethers::{
@@ -40,7 +40,10 @@ use revm::{
     db::{CacheDB, DatabaseCommit, EmptyDB},
     return_ok, Account, BlockEnv, CreateScheme, Return, TransactOut, TransactTo, TxEnv, EVM,
 };
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
 
 /// A mapping of addresses to their changed state.
 pub type StateChangeset = HashMap<Address, Account>;
@@ -59,6 +62,8 @@ pub enum EvmError {
         debug: Option<DebugArena>,
         labels: BTreeMap<Address, String>,
         state_changeset: Option<StateChangeset>,
+        /// Whether the call was halted because it ran for longer than the executor's timeout
+        timed_out: bool,
     },
     /// Error which occurred during ABI encoding/decoding
     #[error(transparent)]
@@ -107,6 +112,8 @@ pub struct CallResult<D: Detokenize> {
     /// This is only present if the changed state was not committed to the database (i.e. if you
     /// used `call` and `call_raw` not `call_committing` or `call_raw_committing`).
     pub state_changeset: Option<StateChangeset>,
+    /// Whether the call was halted because it ran for longer than the executor's timeout
+    pub timed_out: bool,
 }
 
 /// The result of a raw call.
@@ -135,6 +142,10 @@ pub struct RawCallResult {
     /// This is only present if the changed state was not committed to the database (i.e. if you
     /// used `call` and `call_raw` not `call_committing` or `call_raw_committing`).
     pub state_changeset: Option<StateChangeset>,
+    /// The `JUMPI` edges hit during the call, if coverage is enabled
+    pub coverage: Option<HashSet<Edge>>,
+    /// Whether the call was halted because it ran for longer than the executor's timeout
+    pub timed_out: bool,
 }
 
 impl Default for RawCallResult {
@@ -150,6 +161,8 @@ impl Default for RawCallResult {
             traces: None,
             debug: None,
             state_changeset: None,
+            coverage: None,
+            timed_out: false,
         }
     }
 }
@@ -222,16 +235,52 @@ where
         self
     }
 
+    pub fn set_storage_tracing(&mut self, storage_tracing: bool) -> &mut Self {
+        self.inspector_config.storage_tracing = storage_tracing;
+        self
+    }
+
     pub fn set_debugger(&mut self, debugger: bool) -> &mut Self {
         self.inspector_config.debugger = debugger;
         self
     }
 
+    pub fn set_coverage(&mut self, coverage: bool) -> &mut Self {
+        self.inspector_config.coverage = coverage;
+        self
+    }
+
+    /// Whether `JUMPI` edge coverage is recorded for calls
+    pub fn coverage_enabled(&self) -> bool {
+        self.inspector_config.coverage
+    }
+
+    /// Whether the `ffi` cheatcode is allowed
+    pub fn ffi_enabled(&self) -> bool {
+        self.inspector_config.cheatcodes.as_ref().map_or(false, |cheatcodes| cheatcodes.ffi)
+    }
+
     pub fn set_gas_limit(&mut self, gas_limit: U256) -> &mut Self {
         self.gas_limit = gas_limit;
         self
     }
 
+    /// The gas limit for calls and deployments
+    pub fn gas_limit(&self) -> U256 {
+        self.gas_limit
+    }
+
+    /// Sets the wall-clock time after which calls are halted
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.inspector_config.timeout = timeout;
+        self
+    }
+
+    /// The wall-clock time after which calls are halted, if any
+    pub fn timeout(&self) -> Option<Duration> {
+        self.inspector_config.timeout
+    }
+
     /// Calls the `setUp()` function on a contract.
     pub fn setup(&mut self, address: Address) -> std::result::Result<CallResult<()>, EvmError> {
         self.call_committing::<(), _, _>(*CALLER, address, "setUp()", (), 0.into(), None)
@@ -262,6 +311,8 @@ where
             traces,
             debug,
             state_changeset,
+            timed_out,
+            ..
         } = self.call_raw_committing(from, to, calldata, value)?;
         match status {
             return_ok!() => {
@@ -276,6 +327,7 @@ where
                     traces,
                     debug,
                     state_changeset,
+                    timed_out,
                 })
             }
             _ => {
@@ -291,6 +343,7 @@ where
                     debug,
                     labels,
                     state_changeset,
+                    timed_out,
                 })
             }
         }
@@ -310,7 +363,7 @@ where
 
         // Build VM
         let mut evm = EVM::new();
-        evm.env = self.build_env(from, TransactTo::Call(to), calldata, value);
+        evm.env = self.build_env(from, TransactTo::Call(to), calldata, value, self.gas_limit);
         evm.database(&mut self.db);
 
         // Run the call
@@ -321,7 +374,7 @@ where
             _ => Bytes::default(),
         };
 
-        let InspectorData { logs, labels, traces, debug, cheatcodes } =
+        let InspectorData { logs, labels, traces, debug, cheatcodes, coverage, timed_out } =
             inspector.collect_inspector_states();
 
         // Persist the changed block environment
@@ -341,6 +394,8 @@ where
             traces,
             debug,
             state_changeset: None,
+            coverage,
+            timed_out,
         })
     }
 
@@ -355,6 +410,24 @@ where
         args: T,
         value: U256,
         abi: Option<&Abi>,
+    ) -> std::result::Result<CallResult<D>, EvmError> {
+        self.call_with_gas_limit(from, to, func, args, value, abi, self.gas_limit)
+    }
+
+    /// Performs a call to an account on the current state of the VM, with the given gas limit
+    /// instead of the executor's.
+    ///
+    /// The state after the call is not persisted.
+    #[allow(clippy::too_many_arguments)]
+    pub fn call_with_gas_limit<D: Detokenize, T: Tokenize, F: IntoFunction>(
+        &self,
+        from: Address,
+        to: Address,
+        func: F,
+        args: T,
+        value: U256,
+        abi: Option<&Abi>,
+        gas_limit: U256,
     ) -> std::result::Result<CallResult<D>, EvmError> {
         let func = func.into();
         let calldata = Bytes::from(encode_function_data(&func, args)?.to_vec());
@@ -369,7 +442,9 @@ where
             traces,
             debug,
             state_changeset,
-        } = self.call_raw(from, to, calldata, value)?;
+            timed_out,
+            ..
+        } = self.call_raw_with_gas_limit(from, to, calldata, value, gas_limit)?;
         match status {
             return_ok!() => {
                 let result = decode_function_data(&func, result, false)?;
@@ -383,6 +458,7 @@ where
                     traces,
                     debug,
                     state_changeset,
+                    timed_out,
                 })
             }
             _ => {
@@ -398,6 +474,7 @@ where
                     debug,
                     labels,
                     state_changeset,
+                    timed_out,
                 })
             }
         }
@@ -412,12 +489,27 @@ where
         to: Address,
         calldata: Bytes,
         value: U256,
+    ) -> Result<RawCallResult> {
+        self.call_raw_with_gas_limit(from, to, calldata, value, self.gas_limit)
+    }
+
+    /// Performs a raw call to an account on the current state of the VM, with the given gas limit
+    /// instead of the executor's.
+    ///
+    /// The state after the call is not persisted.
+    pub fn call_raw_with_gas_limit(
+        &self,
+        from: Address,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+        gas_limit: U256,
     ) -> Result<RawCallResult> {
         let stipend = stipend(&calldata, self.env.cfg.spec_id);
 
         // Build VM
         let mut evm = EVM::new();
-        evm.env = self.build_env(from, TransactTo::Call(to), calldata, value);
+        evm.env = self.build_env(from, TransactTo::Call(to), calldata, value, gas_limit);
         evm.database(&self.db);
 
         // Run the call
@@ -428,7 +520,7 @@ where
             _ => Bytes::default(),
         };
 
-        let InspectorData { logs, labels, traces, debug, .. } =
+        let InspectorData { logs, labels, traces, debug, coverage, timed_out, .. } =
             inspector.collect_inspector_states();
         Ok(RawCallResult {
             status,
@@ -441,13 +533,21 @@ where
             traces,
             debug,
             state_changeset: Some(state_changeset),
+            coverage,
+            timed_out,
         })
     }
 
     /// Deploys a contract and commits the new state to the underlying database.
     pub fn deploy(&mut self, from: Address, code: Bytes, value: U256) -> Result<DeployResult> {
         let mut evm = EVM::new();
-        evm.env = self.build_env(from, TransactTo::Create(CreateScheme::Create), code, value);
+        evm.env = self.build_env(
+            from,
+            TransactTo::Create(CreateScheme::Create),
+            code,
+            value,
+            self.gas_limit,
+        );
         evm.database(&mut self.db);
 
         let mut inspector = self.inspector_config.stack();
@@ -512,17 +612,20 @@ where
         should_fail ^ success
     }
 
-    fn build_env(&self, caller: Address, transact_to: TransactTo, data: Bytes, value: U256) -> Env {
+    fn build_env(
+        &self,
+        caller: Address,
+        transact_to: TransactTo,
+        data: Bytes,
+        value: U256,
+        gas_limit: U256,
+    ) -> Env {
         Env {
             cfg: self.env.cfg.clone(),
             // We always set the gas price to 0 so we can execute the transaction regardless of
             // network conditions - the actual gas price is kept in `self.block` and is applied by
             // the cheatcode handler if it is enabled
-            block: BlockEnv {
-                basefee: 0.into(),
-                gas_limit: self.gas_limit,
-                ..self.env.block.clone()
-            },
+            block: BlockEnv { basefee: 0.into(), gas_limit, ..self.env.block.clone() },
             tx: TxEnv {
                 caller,
                 transact_to,
@@ -531,7 +634,7 @@ where
                 // As above, we set the gas price to 0.
                 gas_price: 0.into(),
                 gas_priority_fee: None,
-                gas_limit: self.gas_limit.as_u64(),
+                gas_limit: gas_limit.as_u64(),
                 ..self.env.tx.clone()
             },
         }
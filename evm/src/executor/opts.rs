@@ -7,6 +7,9 @@ use serde::{Deserialize, Deserializer, Serialize};
 
 use super::fork::environment;
 
+/// The chain id of local environments that don't configure one
+pub const DEFAULT_CHAIN_ID: u64 = 99;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct EvmOpts {
     #[serde(flatten)]
@@ -63,7 +66,7 @@ impl EvmOpts {
                     gas_limit: self.gas_limit(),
                 },
                 cfg: CfgEnv {
-                    chain_id: self.env.chain_id.unwrap_or(99).into(),
+                    chain_id: self.env.chain_id.unwrap_or(DEFAULT_CHAIN_ID).into(),
                     spec_id: SpecId::LONDON,
                     perf_all_precompiles_have_balance: false,
                     memory_limit: self.memory_limit,
@@ -0,0 +1,77 @@
+use ethers::{abi::Function, types::Bytes};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The reference implementation a fuzzed function is compared against in differential fuzzing.
+#[derive(Clone, Debug)]
+pub enum DifferentialTarget {
+    /// Another function of the same contract, taking the same inputs
+    Function(Function),
+    /// An external program, invoked with the hex encoded calldata as its last argument.
+    ///
+    /// The program runs in the `root` directory if given, so relative paths in its arguments are
+    /// resolved against the project root rather than the current directory.
+    ///
+    /// The program is expected to print the hex encoded return data to stdout, and to exit with a
+    /// non-zero status code to signal a revert.
+    Ffi { args: Vec<String>, root: Option<PathBuf> },
+}
+
+impl fmt::Display for DifferentialTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DifferentialTarget::Function(func) => f.write_str(&func.name),
+            DifferentialTarget::Ffi { args, .. } => write!(f, "`{}`", args.join(" ")),
+        }
+    }
+}
+
+/// The outcome of a call to either side of a differential fuzz case
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DifferentialOutcome {
+    pub reverted: bool,
+    pub result: Bytes,
+}
+
+impl fmt::Display for DifferentialOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.reverted { "reverted with" } else { "returned" };
+        write!(f, "{} 0x{}", verb, hex::encode(&self.result))
+    }
+}
+
+/// Runs the external reference program with the given calldata, from the `root` directory if
+/// given
+pub(crate) fn run_ffi(
+    args: &[String],
+    root: Option<&Path>,
+    calldata: &Bytes,
+) -> Result<DifferentialOutcome, String> {
+    let (program, rest) = args.split_first().ok_or_else(|| "missing ffi command".to_string())?;
+    let mut command = Command::new(program);
+    command.args(rest).arg(format!("0x{}", hex::encode(calldata)));
+    if let Some(root) = root {
+        command.current_dir(root);
+    }
+    let output = command.output().map_err(|err| format!("could not run {program}: {err}"))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stdout = stdout.trim();
+    let result = hex::decode(stdout.strip_prefix("0x").unwrap_or(stdout))
+        .map_err(|err| format!("invalid output of {program}: {err}"))?;
+
+    Ok(DifferentialOutcome { reverted: !output.status.success(), result: result.into() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_empty_ffi_command() {
+        assert_eq!(run_ffi(&[], None, &Bytes::default()).unwrap_err(), "missing ffi command");
+    }
+}
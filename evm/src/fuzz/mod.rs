@@ -1,23 +1,34 @@
 mod strategies;
 
+mod differential;
+pub use differential::DifferentialTarget;
+
 pub use proptest::test_runner::{Config as FuzzConfig, Reason};
 
 use crate::{
-    executor::{Executor, RawCallResult},
+    executor::{inspector::Edge, Executor, RawCallResult},
     trace::CallTraceArena,
 };
+use differential::{run_ffi, DifferentialOutcome};
 use ethers::{
     abi::{Abi, Function, RawLog, Token},
-    types::{Address, Bytes},
+    core::rand::Rng,
+    types::{Address, Bytes, H256, U256},
 };
-use proptest::test_runner::{TestCaseError, TestError, TestRunner};
+use proptest::test_runner::{RngAlgorithm, TestCaseError, TestError, TestRng, TestRunner};
 use revm::db::DatabaseRef;
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, collections::BTreeMap, fmt};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashSet},
+    fmt,
+    time::{Duration, Instant},
+};
 use strategies::{
-    build_initial_state, collect_state_from_call, fuzz_calldata, fuzz_calldata_from_state,
-    EvmFuzzState,
+    build_initial_state, collect_state_from_call, fuzz_calldata, fuzz_calldata_from_corpus,
+    fuzz_calldata_from_state, EvmFuzzState, FuzzCorpus,
 };
+pub use strategies::{FuzzConstraints, FuzzFixtures, ParamConstraint};
 
 /// Magic return code for the `assume` cheatcode
 pub const ASSUME_MAGIC_RETURN_CODE: &[u8] = b"FOUNDRY::ASSUME";
@@ -34,6 +45,16 @@ pub struct FuzzedExecutor<'a, DB: DatabaseRef> {
     runner: TestRunner,
     /// The account that calls tests
     sender: Address,
+    /// The reference implementation to compare the fuzzed function against, if any
+    differential: Option<DifferentialTarget>,
+    /// The wall-clock time after which the fuzz campaign is stopped
+    timeout: Option<Duration>,
+    /// The seed of the fuzzer's RNG, if it is fixed
+    seed: Option<H256>,
+    /// The gas limit of the fuzzed calls
+    gas_limit: U256,
+    /// The values generated more often for the fuzzed parameters
+    fixtures: FuzzFixtures,
 }
 
 impl<'a, DB> FuzzedExecutor<'a, DB>
@@ -42,13 +63,96 @@ where
 {
     /// Instantiates a fuzzed executor given a testrunner
     pub fn new(executor: &'a Executor<DB>, runner: TestRunner, sender: Address) -> Self {
-        Self { executor, runner, sender }
+        Self {
+            executor,
+            runner,
+            sender,
+            differential: None,
+            timeout: None,
+            seed: None,
+            gas_limit: executor.gas_limit(),
+            fixtures: Default::default(),
+        }
+    }
+
+    /// Generates the given values more often for the fuzzed parameters
+    #[must_use]
+    pub fn with_fixtures(mut self, fixtures: FuzzFixtures) -> Self {
+        self.fixtures = fixtures;
+        self
+    }
+
+    /// Makes the fuzzed calls with the given gas limit instead of the executor's
+    #[must_use]
+    pub fn with_gas_limit(mut self, gas_limit: U256) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    /// Seeds the fuzzer's RNG with the given seed, to reproduce the fuzz run that reported it.
+    ///
+    /// Otherwise, the seed is drawn from the test runner's RNG.
+    #[must_use]
+    pub fn with_seed(mut self, seed: Option<H256>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Fails the fuzz campaign if it runs for longer than `timeout`.
+    ///
+    /// The cases that would run after the timeout expired are skipped.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Compares the fuzzed function against a reference implementation.
+    ///
+    /// Every fuzz input is run through both the function and the reference, and the test fails if
+    /// their return data or revert status differ.
+    #[must_use]
+    pub fn with_differential(mut self, target: DifferentialTarget) -> Self {
+        self.differential = Some(target);
+        self
+    }
+
+    /// Runs the calldata of a fuzz case through the reference implementation
+    fn call_reference(
+        &self,
+        target: &DifferentialTarget,
+        address: Address,
+        calldata: &Bytes,
+    ) -> Result<DifferentialOutcome, String> {
+        match target {
+            DifferentialTarget::Function(func) => {
+                let mut data = func.short_signature().to_vec();
+                data.extend_from_slice(&calldata.as_ref()[4..]);
+                let call = self
+                    .executor
+                    .call_raw_with_gas_limit(
+                        self.sender,
+                        address,
+                        data.into(),
+                        0.into(),
+                        self.gas_limit,
+                    )
+                    .map_err(|err| err.to_string())?;
+                Ok(DifferentialOutcome { reverted: call.reverted, result: call.result.into() })
+            }
+            DifferentialTarget::Ffi { args, root } => run_ffi(args, root.as_deref(), calldata),
+        }
     }
 
     /// Fuzzes the provided function, assuming it is available at the contract at `address`
     /// If `should_fail` is set to `true`, then it will stop only when there's a success
     /// test case.
     ///
+    /// All generated inputs satisfy the given parameter `constraints`.
+    ///
+    /// If the executor records edge coverage, inputs that reach new `JUMPI` edges are kept in a
+    /// corpus and mutated preferentially.
+    ///
     /// Returns a list of all the consumed gas and calldata of every fuzz case
     pub fn fuzz(
         &self,
@@ -56,7 +160,25 @@ where
         address: Address,
         should_fail: bool,
         errors: Option<&Abi>,
+        constraints: &FuzzConstraints,
     ) -> FuzzTestResult {
+        let start = Instant::now();
+
+        // Seed a fresh runner so the seed of this run can be reported and replayed
+        let mut runner = self.runner.clone();
+        let seed = self.seed.unwrap_or_else(|| H256::from(runner.rng().gen::<[u8; 32]>()));
+        let mut runner = TestRunner::new_with_rng(
+            runner.config().clone(),
+            TestRng::from_seed(RngAlgorithm::ChaCha, seed.as_bytes()),
+        );
+
+        // Counts the executed and rejected fuzz cases
+        let runs = Cell::new(0usize);
+        let rejects = Cell::new(0usize);
+
+        // Stores why the campaign timed out, if it did
+        let timed_out: RefCell<Option<String>> = RefCell::new(None);
+
         // Stores the consumed gas and calldata of every successful fuzz call
         let cases: RefCell<Vec<FuzzCase>> = RefCell::new(Default::default());
 
@@ -66,18 +188,64 @@ where
         // Stores fuzz state for use with [fuzz_calldata_from_state]
         let state: EvmFuzzState = build_initial_state(&self.executor.db);
 
+        // Stores the inputs that reached new edges, and all edges seen so far
+        let corpus: FuzzCorpus = Default::default();
+        let edges: RefCell<HashSet<Edge>> = Default::default();
+
         // TODO: We should have a `FuzzerOpts` struct where we can configure the fuzzer. When we
         // have that, we should add a way to configure strategy weights
-        let strat = proptest::strategy::Union::new_weighted(vec![
-            (60, fuzz_calldata(func.clone())),
-            (40, fuzz_calldata_from_state(func.clone(), state.clone())),
-        ]);
+        let strat = if self.executor.coverage_enabled() {
+            proptest::strategy::Union::new_weighted(vec![
+                (30, fuzz_calldata(func.clone(), constraints, &self.fixtures)),
+                (20, fuzz_calldata_from_state(func.clone(), state.clone(), constraints)),
+                (
+                    50,
+                    fuzz_calldata_from_corpus(
+                        func.clone(),
+                        corpus.clone(),
+                        constraints,
+                        &self.fixtures,
+                    ),
+                ),
+            ])
+        } else {
+            proptest::strategy::Union::new_weighted(vec![
+                (60, fuzz_calldata(func.clone(), constraints, &self.fixtures)),
+                (40, fuzz_calldata_from_state(func.clone(), state.clone(), constraints)),
+            ])
+        };
         tracing::debug!(func = ?func.name, should_fail, "fuzzing");
-        let run_result = self.runner.clone().run(&strat, |calldata| {
+        let run_result = runner.run(&strat, |calldata| {
+            // Skip the remaining cases once the campaign timed out
+            if timed_out.borrow().is_some() {
+                return Ok(())
+            }
+            if let Some(timeout) = self.timeout.filter(|timeout| start.elapsed() >= *timeout) {
+                *timed_out.borrow_mut() =
+                    Some(format!("Fuzz campaign timed out after {timeout:?}"));
+                return Ok(())
+            }
+
+            runs.set(runs.get() + 1);
+            let case_start = Instant::now();
             let call = self
                 .executor
-                .call_raw(self.sender, address, calldata.0.clone(), 0.into())
+                .call_raw_with_gas_limit(
+                    self.sender,
+                    address,
+                    calldata.0.clone(),
+                    0.into(),
+                    self.gas_limit,
+                )
                 .expect("could not make raw evm call");
+
+            // A call halted by the executor's timeout did not fail on its own
+            if let Some(timeout) =
+                self.executor.timeout().filter(|timeout| case_start.elapsed() >= *timeout)
+            {
+                *timed_out.borrow_mut() = Some(format!("Fuzz case timed out after {timeout:?}"));
+                return Ok(())
+            }
             let state_changeset =
                 call.state_changeset.as_ref().expect("we should have a state changeset");
 
@@ -86,15 +254,49 @@ where
 
             // When assume cheat code is triggered return a special string "FOUNDRY::ASSUME"
             if call.result.as_ref() == ASSUME_MAGIC_RETURN_CODE {
+                rejects.set(rejects.get() + 1);
                 return Err(TestCaseError::reject("ASSUME: Too many rejects"))
             }
 
-            let success = self.executor.is_success(
-                address,
-                call.reverted,
-                state_changeset.clone(),
-                should_fail,
-            );
+            // Keep inputs that reach new edges in the corpus
+            if let Some(ref coverage) = call.coverage {
+                let mut edges = edges.borrow_mut();
+                let known = edges.len();
+                edges.extend(coverage.iter().copied());
+                if edges.len() > known {
+                    corpus.borrow_mut().push(calldata.clone());
+                }
+            }
+
+            // In differential mode, the test passes if both implementations behave the same
+            let (success, mismatch) = match self.differential {
+                Some(ref target) => {
+                    let outcome = DifferentialOutcome {
+                        reverted: call.reverted,
+                        result: call.result.clone().into(),
+                    };
+                    match self.call_reference(target, address, &calldata) {
+                        Ok(reference) if reference == outcome => (true, None),
+                        Ok(reference) => (
+                            false,
+                            Some(format!(
+                                "Differential mismatch: {} {outcome}, but {target} {reference}",
+                                func.name
+                            )),
+                        ),
+                        Err(err) => (false, Some(format!("Differential reference failed: {err}"))),
+                    }
+                }
+                None => (
+                    self.executor.is_success(
+                        address,
+                        call.reverted,
+                        state_changeset.clone(),
+                        should_fail,
+                    ),
+                    None,
+                ),
+            };
 
             if success {
                 cases.borrow_mut().push(FuzzCase {
@@ -109,27 +311,38 @@ where
                 // failure - when a fuzz case fails, proptest will try to run at least one more
                 // case to find a minimal failure case.
                 *counterexample.borrow_mut() = (calldata, call);
-                Err(TestCaseError::fail(
-                    match foundry_utils::decode_revert(
+                Err(TestCaseError::fail(match mismatch {
+                    Some(mismatch) => mismatch,
+                    None => match foundry_utils::decode_revert(
                         counterexample.borrow().1.result.as_ref(),
                         errors,
                     ) {
                         Ok(e) => e,
                         Err(_) => "".to_string(),
                     },
-                ))
+                }))
             }
         });
 
+        tracing::debug!(corpus = corpus.borrow().len(), edges = edges.borrow().len(), "fuzzed");
+
+        let timed_out = timed_out.into_inner();
         let (calldata, call) = counterexample.into_inner();
         let mut result = FuzzTestResult {
             cases: FuzzedCases::new(cases.into_inner()),
-            success: run_result.is_ok(),
+            success: run_result.is_ok() && timed_out.is_none(),
             reason: None,
             counterexample: None,
             logs: call.logs,
             traces: call.traces,
             labeled_addresses: call.labels,
+            stats: FuzzStats {
+                runs: runs.get(),
+                rejects: rejects.get(),
+                duration: start.elapsed(),
+                seed,
+                dictionary_values: state.borrow().drawn.len(),
+            },
         };
 
         match run_result {
@@ -145,7 +358,7 @@ where
                     .expect("could not decode fuzzer inputs");
                 result.counterexample = Some(CounterExample { calldata, args });
             }
-            _ => (),
+            Ok(()) => result.reason = timed_out,
         }
 
         result
@@ -194,6 +407,55 @@ pub struct FuzzTestResult {
 
     /// Labeled addresses
     pub labeled_addresses: BTreeMap<Address, String>,
+
+    /// Statistics of the fuzz run
+    pub stats: FuzzStats,
+}
+
+/// Statistics of a fuzz test run
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FuzzStats {
+    /// The number of executed fuzz cases, including rejected ones
+    pub runs: usize,
+    /// The number of fuzz cases rejected by `vm.assume`
+    pub rejects: usize,
+    /// The wall time of the fuzz run, serialized in seconds
+    #[serde(with = "duration_secs")]
+    pub duration: Duration,
+    /// The seed of the fuzzer's RNG, which reproduces the run when passed to `--fuzz-seed`
+    pub seed: H256,
+    /// The number of distinct values of the fuzz dictionary that were used to generate inputs
+    pub dictionary_values: usize,
+}
+
+/// (De)serializes a [Duration] as fractional seconds
+mod duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        if secs.is_finite() && secs >= 0.0 {
+            Ok(Duration::from_secs_f64(secs))
+        } else {
+            Err(serde::de::Error::custom(format!("invalid duration: {secs}")))
+        }
+    }
+}
+
+impl fmt::Display for FuzzStats {
+    /// Omits the number of runs, which is displayed alongside the gas usage
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "(rejects: {}, dictionary: {}, time: {:.2?}, seed: {:?})",
+            self.rejects, self.dictionary_values, self.duration, self.seed
+        )
+    }
 }
 
 /// Container type for all successful test cases
@@ -1,13 +1,24 @@
-use super::fuzz_param;
+use super::{fuzz_param, FuzzConstraints, FuzzFixtures};
 use ethers::{abi::Function, types::Bytes};
 use proptest::prelude::{BoxedStrategy, Strategy};
 
 /// Given a function, it returns a strategy which generates valid calldata
-/// for that function's input types.
-pub fn fuzz_calldata(func: Function) -> BoxedStrategy<Bytes> {
+/// for that function's input types, satisfying the given parameter constraints and preferring the
+/// given parameter fixtures.
+pub fn fuzz_calldata(
+    func: Function,
+    constraints: &FuzzConstraints,
+    fixtures: &FuzzFixtures,
+) -> BoxedStrategy<Bytes> {
     // We need to compose all the strategies generated for each parameter in all
     // possible combinations
-    let strats = func.inputs.iter().map(|input| fuzz_param(&input.kind)).collect::<Vec<_>>();
+    let strats = func
+        .inputs
+        .iter()
+        .map(|input| {
+            fuzz_param(&input.kind, constraints.get(&input.name), fixtures.get(&input.name))
+        })
+        .collect::<Vec<_>>();
 
     strats
         .prop_map(move |tokens| {
@@ -0,0 +1,240 @@
+use ethers::{
+    abi::{Function, ParamType},
+    types::U256,
+};
+use std::collections::BTreeMap;
+
+/// A constraint on the values generated for a single fuzzed parameter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamConstraint {
+    /// Only generate values in the inclusive range `min..=max`
+    Range(U256, U256),
+    /// Never generate the zero value
+    NotZero,
+}
+
+impl ParamConstraint {
+    /// Whether the constraint can be applied to values of the given type.
+    ///
+    /// Constraints on array types apply to each element of the array.
+    fn supports(&self, kind: &ParamType) -> bool {
+        match (self, kind) {
+            (_, ParamType::Array(inner)) | (_, ParamType::FixedArray(inner, _)) => {
+                self.supports(inner)
+            }
+            (ParamConstraint::Range(_, _), ParamType::Uint(_)) => true,
+            (ParamConstraint::NotZero, ParamType::Uint(_)) |
+            (ParamConstraint::NotZero, ParamType::Int(_)) |
+            (ParamConstraint::NotZero, ParamType::Address) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Constraints for the parameters of a fuzzed function, keyed by parameter name.
+///
+/// Constraints are declared with `@custom:fuzz` NatSpec annotations on the test function, for
+/// example `@custom:fuzz amount range 1..1e24` or `@custom:fuzz addr notzero`, and are applied
+/// by the strategies so that every generated value satisfies them by construction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FuzzConstraints {
+    params: BTreeMap<String, Vec<ParamConstraint>>,
+}
+
+impl FuzzConstraints {
+    /// Parses an annotation of the form `<param> <constraint>` and adds it to the set.
+    ///
+    /// Supported constraints are `range <min>..<max>` (inclusive) and `notzero`.
+    pub fn add_annotation(&mut self, annotation: &str) -> Result<(), String> {
+        let mut parts = annotation.split_whitespace();
+        let (param, kind) = match (parts.next(), parts.next()) {
+            (Some(param), Some(kind)) => (param, kind),
+            _ => {
+                return Err(format!(
+                    "invalid fuzz annotation `{annotation}`, expected `<param> <constraint>`"
+                ))
+            }
+        };
+
+        let constraint = match kind {
+            "notzero" => ParamConstraint::NotZero,
+            "range" => {
+                let range = parts.next().ok_or_else(|| {
+                    format!("missing range for `{param}`, expected `<min>..<max>`")
+                })?;
+                let (min, max) = range
+                    .split_once("..")
+                    .ok_or_else(|| format!("invalid range `{range}`, expected `<min>..<max>`"))?;
+                let (min, max) = (parse_uint(min)?, parse_uint(max)?);
+                if min > max {
+                    return Err(format!("invalid range `{range}`, min is larger than max"))
+                }
+                ParamConstraint::Range(min, max)
+            }
+            _ => return Err(format!("unknown fuzz constraint `{kind}`")),
+        };
+
+        if let Some(extra) = parts.next() {
+            return Err(format!("unexpected `{extra}` in fuzz annotation `{annotation}`"))
+        }
+
+        self.params.entry(param.to_string()).or_default().push(constraint);
+        Ok(())
+    }
+
+    /// Returns the constraints of the given parameter
+    pub fn get(&self, param: &str) -> &[ParamConstraint] {
+        self.params.get(param).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    /// Checks that every constrained parameter exists on the function, that the constraints can be
+    /// applied to the parameter's type and that the resulting ranges are not empty.
+    pub fn validate(&self, func: &Function) -> Result<(), String> {
+        for (name, constraints) in &self.params {
+            let kind = &func
+                .inputs
+                .iter()
+                .find(|input| &input.name == name)
+                .ok_or_else(|| format!("unknown parameter `{name}`"))?
+                .kind;
+
+            if let Some(constraint) = constraints.iter().find(|c| !c.supports(kind)) {
+                return Err(format!("{constraint:?} can not be applied to `{name}` ({kind})"))
+            }
+
+            if let Some(bits) = uint_bits(kind) {
+                let (min, max) = uint_bounds(bits, constraints);
+                if min > max {
+                    return Err(format!("`{name}` can not satisfy all of its constraints"))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the inclusive bounds of a `uint<bits>` value under the given constraints.
+///
+/// The returned `min` is larger than `max` if the constraints can not be satisfied.
+pub fn uint_bounds(bits: usize, constraints: &[ParamConstraint]) -> (U256, U256) {
+    let mut min = U256::zero();
+    let mut max = if bits < 256 { (U256::one() << bits) - 1 } else { U256::MAX };
+    for constraint in constraints {
+        match constraint {
+            ParamConstraint::Range(lo, hi) => {
+                min = min.max(*lo);
+                max = max.min(*hi);
+            }
+            ParamConstraint::NotZero => min = min.max(U256::one()),
+        }
+    }
+    (min, max)
+}
+
+/// Maps an arbitrary value into the inclusive range `min..=max`
+pub fn bound_uint(value: U256, min: U256, max: U256) -> U256 {
+    let range = max - min;
+    if range == U256::MAX {
+        value
+    } else {
+        min + value % (range + 1)
+    }
+}
+
+/// Returns the bit size of the (element) type if it is an unsigned integer
+fn uint_bits(kind: &ParamType) -> Option<usize> {
+    match kind {
+        ParamType::Uint(bits) => Some(*bits),
+        ParamType::Array(inner) | ParamType::FixedArray(inner, _) => uint_bits(inner),
+        _ => None,
+    }
+}
+
+/// Parses an unsigned integer in decimal (`1000`, `1_000`), scientific (`1e24`) or hexadecimal
+/// (`0x3e8`) notation.
+fn parse_uint(s: &str) -> Result<U256, String> {
+    let s = s.replace('_', "");
+    let invalid = || format!("invalid number `{s}`");
+
+    if let Some(hex) = s.strip_prefix("0x") {
+        return U256::from_str_radix(hex, 16).map_err(|_| invalid())
+    }
+
+    match s.split_once(|c: char| c == 'e' || c == 'E') {
+        Some((mantissa, exponent)) => {
+            let mantissa = U256::from_dec_str(mantissa).map_err(|_| invalid())?;
+            let exponent: usize = exponent.parse().map_err(|_| invalid())?;
+            if exponent > 77 {
+                return Err(invalid())
+            }
+            mantissa.checked_mul(U256::exp10(exponent)).ok_or_else(invalid)
+        }
+        None => U256::from_dec_str(&s).map_err(|_| invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::AbiParser;
+
+    #[test]
+    fn can_parse_annotations() {
+        let mut constraints = FuzzConstraints::default();
+        constraints.add_annotation("amount range 1..1e24").unwrap();
+        constraints.add_annotation("addr notzero").unwrap();
+        constraints.add_annotation("small range 0x10..1_000").unwrap();
+
+        assert_eq!(constraints.get("amount"), &[ParamConstraint::Range(1.into(), U256::exp10(24))]);
+        assert_eq!(constraints.get("addr"), &[ParamConstraint::NotZero]);
+        assert_eq!(constraints.get("small"), &[ParamConstraint::Range(16.into(), 1000.into())]);
+        assert!(constraints.get("other").is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_annotations() {
+        let mut constraints = FuzzConstraints::default();
+        assert!(constraints.add_annotation("amount").is_err());
+        assert!(constraints.add_annotation("amount range 10..1").is_err());
+        assert!(constraints.add_annotation("amount range 1-10").is_err());
+        assert!(constraints.add_annotation("amount positive").is_err());
+        assert!(constraints.add_annotation("amount notzero please").is_err());
+        assert!(constraints.is_empty());
+    }
+
+    #[test]
+    fn can_validate_constraints() {
+        let func = AbiParser::default()
+            .parse_function("function testBounds(uint8 amount, address addr, bytes data)")
+            .unwrap();
+
+        let mut constraints = FuzzConstraints::default();
+        constraints.add_annotation("amount range 1..100").unwrap();
+        constraints.add_annotation("addr notzero").unwrap();
+        assert!(constraints.validate(&func).is_ok());
+
+        let mut constraints = FuzzConstraints::default();
+        constraints.add_annotation("amount range 300..400").unwrap();
+        assert!(constraints.validate(&func).is_err());
+
+        let mut constraints = FuzzConstraints::default();
+        constraints.add_annotation("data notzero").unwrap();
+        assert!(constraints.validate(&func).is_err());
+
+        let mut constraints = FuzzConstraints::default();
+        constraints.add_annotation("missing notzero").unwrap();
+        assert!(constraints.validate(&func).is_err());
+    }
+
+    #[test]
+    fn can_bound_uint() {
+        assert_eq!(bound_uint(0.into(), 1.into(), 10.into()), 1.into());
+        assert_eq!(bound_uint(10.into(), 1.into(), 10.into()), 1.into());
+        assert_eq!(bound_uint(9.into(), 1.into(), 10.into()), 10.into());
+        assert_eq!(bound_uint(U256::MAX, U256::zero(), U256::MAX), U256::MAX);
+    }
+}
@@ -0,0 +1,67 @@
+use super::{fuzz_param, FuzzConstraints, FuzzFixtures};
+use ethers::{
+    abi::{Function, Token},
+    types::Bytes,
+};
+use proptest::{
+    prelude::{any, BoxedStrategy, Strategy},
+    sample::Index,
+};
+use std::{cell::RefCell, rc::Rc};
+
+/// The calldata of fuzz cases that reached new `JUMPI` edges in the fuzzed contract.
+///
+/// Wrapped in a shareable container.
+pub type FuzzCorpus = Rc<RefCell<Vec<Bytes>>>;
+
+/// Given a function and a corpus of interesting inputs, it returns a strategy which mutates the
+/// inputs in the corpus.
+///
+/// A mutation replaces a single argument of a corpus entry with a freshly generated value, keeping
+/// the arguments that got the input to its branches. If the corpus is empty, entirely fresh
+/// calldata is generated.
+pub fn fuzz_calldata_from_corpus(
+    func: Function,
+    corpus: FuzzCorpus,
+    constraints: &FuzzConstraints,
+    fixtures: &FuzzFixtures,
+) -> BoxedStrategy<Bytes> {
+    let strats = func
+        .inputs
+        .iter()
+        .map(|input| {
+            fuzz_param(&input.kind, constraints.get(&input.name), fixtures.get(&input.name))
+        })
+        .collect::<Vec<_>>();
+
+    (any::<Index>(), any::<Index>(), strats)
+        .prop_map(move |(entry, arg, fresh)| {
+            let tokens = mutate_entry(&func, &corpus.borrow(), entry, arg, fresh);
+            tracing::trace!(input = ?tokens);
+            func.encode_input(&tokens).unwrap().into()
+        })
+        .boxed()
+}
+
+/// Replaces the argument at `arg` of the corpus entry at `entry` with its fresh value
+fn mutate_entry(
+    func: &Function,
+    corpus: &[Bytes],
+    entry: Index,
+    arg: Index,
+    fresh: Vec<Token>,
+) -> Vec<Token> {
+    if corpus.is_empty() || fresh.is_empty() {
+        return fresh
+    }
+
+    let calldata = &corpus[entry.index(corpus.len())];
+    match func.decode_input(&calldata.as_ref()[4..]) {
+        Ok(mut tokens) => {
+            let arg = arg.index(tokens.len());
+            tokens[arg] = fresh[arg].clone();
+            tokens
+        }
+        Err(_) => fresh,
+    }
+}
@@ -0,0 +1,30 @@
+use ethers::types::U256;
+use std::collections::BTreeMap;
+
+/// Values that are generated more often for the fuzzed parameters, keyed by parameter name.
+///
+/// These are the values returned by the `fixture_<param>()` functions of the test contract.
+/// Unlike [`FuzzConstraints`](super::FuzzConstraints), fixtures do not restrict the generated
+/// values, they are an additional source of inputs for the integer strategies.
+///
+/// Signed integers are stored as their two's complement.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FuzzFixtures {
+    params: BTreeMap<String, Vec<U256>>,
+}
+
+impl FuzzFixtures {
+    /// Adds values that are generated more often for the given parameter
+    pub fn insert(&mut self, param: &str, values: Vec<U256>) {
+        self.params.entry(param.to_string()).or_default().extend(values);
+    }
+
+    /// Returns the fixtures of the given parameter
+    pub fn get(&self, param: &str) -> &[U256] {
+        self.params.get(param).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
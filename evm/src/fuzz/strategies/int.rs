@@ -0,0 +1,260 @@
+use ethers::core::rand::Rng;
+use proptest::{
+    strategy::{NewTree, Strategy, ValueTree},
+    test_runner::TestRunner,
+};
+
+use ethers::types::{I256, U256};
+
+/// Value tree for signed ints (up to int256).
+/// This is very similar to [proptest::BinarySearch] and shrinks towards zero.
+pub struct IntValueTree {
+    /// Lower base (in terms of magnitude)
+    lo: I256,
+    /// Current value
+    curr: I256,
+    /// Higher base (in terms of magnitude)
+    hi: I256,
+    /// If true cannot be simplified or complexified
+    fixed: bool,
+}
+
+impl IntValueTree {
+    /// Create a new tree
+    /// # Arguments
+    /// * `start` - Starting value for the tree
+    /// * `fixed` - If `true` the tree would only contain one element and won't be simplified.
+    fn new(start: I256, fixed: bool) -> Self {
+        Self { lo: I256::zero(), curr: start, hi: start, fixed }
+    }
+
+    fn reposition(&mut self) -> bool {
+        let interval = self.hi - self.lo;
+        let new_mid = self.lo + interval / I256::from(2);
+
+        if new_mid == self.curr {
+            false
+        } else {
+            self.curr = new_mid;
+            true
+        }
+    }
+
+    /// Whether `lhs` is strictly further away from zero than `rhs`, assuming both have the same
+    /// sign
+    fn magnitude_greater(lhs: I256, rhs: I256) -> bool {
+        if lhs.is_zero() || lhs == rhs {
+            return false
+        }
+        (lhs > rhs) ^ lhs.is_negative()
+    }
+}
+
+impl ValueTree for IntValueTree {
+    type Value = I256;
+
+    fn current(&self) -> Self::Value {
+        self.curr
+    }
+
+    fn simplify(&mut self) -> bool {
+        if self.fixed || !IntValueTree::magnitude_greater(self.hi, self.lo) {
+            return false
+        }
+
+        self.hi = self.curr;
+        self.reposition()
+    }
+
+    fn complicate(&mut self) -> bool {
+        if self.fixed || !IntValueTree::magnitude_greater(self.hi, self.lo) {
+            return false
+        }
+
+        let lo = if self.curr.is_negative() {
+            self.curr.saturating_sub(I256::one())
+        } else {
+            self.curr.saturating_add(I256::one())
+        };
+        // never move past the value we started shrinking from
+        self.lo = if IntValueTree::magnitude_greater(lo, self.hi) { self.hi } else { lo };
+        self.reposition()
+    }
+}
+
+/// Value tree for signed ints (up to int256).
+/// The strategy combines 3 different strategies, each assigned a specific weight:
+/// 1. Generate purely random value in a range. This will first choose bit size uniformly (up `bits`
+/// param). Then generate a value for this bit size and pick a random sign.
+/// 2. Generate a random value around the edges (+/- 3 around min and max possible value, or one of
+/// -1, 0 and 1)
+/// 3. Generate a value from a predefined fixtures set
+#[derive(Debug)]
+pub struct IntStrategy {
+    /// Bit size of int (e.g. 256)
+    bits: usize,
+    /// A set of fixtures to be generated
+    fixtures: Vec<I256>,
+    /// The weight for edge cases (+/- 3 around min and max possible value, -1, 0 and 1)
+    edge_weight: usize,
+    /// The weight for fixtures
+    fixtures_weight: usize,
+    /// The weight for purely random values
+    random_weight: usize,
+}
+
+impl IntStrategy {
+    /// Create a new strategy.
+    /// #Arguments
+    /// * `bits` - Size of int in bits
+    /// * `fixtures` - A set of fixed values to be generated (according to fixtures weight)
+    pub fn new(bits: usize, fixtures: Vec<I256>) -> Self {
+        Self {
+            bits,
+            fixtures,
+            edge_weight: 10usize,
+            fixtures_weight: 40usize,
+            random_weight: 50usize,
+        }
+    }
+
+    /// The highest value of the int type
+    fn max(&self) -> I256 {
+        if self.bits < 256 {
+            I256::from_raw((U256::one() << (self.bits - 1)) - 1)
+        } else {
+            I256::MAX
+        }
+    }
+
+    /// The lowest value of the int type
+    fn min(&self) -> I256 {
+        if self.bits < 256 {
+            -self.max() - I256::one()
+        } else {
+            I256::MIN
+        }
+    }
+
+    fn generate_edge_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let rng = runner.rng();
+
+        // Choose if we want values around min, max or zero
+        let offset = I256::from(rng.gen_range(0i32..4));
+        let start = match rng.gen_range(0..3) {
+            0 => self.min() + offset,
+            1 => self.max() - offset,
+            _ => I256::from(rng.gen_range(-1i32..=1)),
+        };
+
+        Ok(IntValueTree::new(start, false))
+    }
+
+    fn generate_fixtures_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let (min, max) = (self.min(), self.max());
+        let fixtures = self
+            .fixtures
+            .iter()
+            .filter(|fixture| (min..=max).contains(*fixture))
+            .collect::<Vec<_>>();
+
+        // generate edge cases if there's no fixtures
+        if fixtures.is_empty() {
+            return self.generate_edge_tree(runner)
+        }
+        let idx = runner.rng().gen_range(0..fixtures.len());
+
+        Ok(IntValueTree::new(*fixtures[idx], false))
+    }
+
+    fn generate_random_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let rng = runner.rng();
+        // generate random number of bits uniformly, leaving one bit for the sign
+        let bits = rng.gen_range(0..self.bits);
+
+        let magnitude = U256::from_big_endian(&rng.gen::<[u8; 32]>()) & ((U256::one() << bits) - 1);
+
+        // a negative value is the bitwise inverse of its magnitude minus one, so `!magnitude`
+        // covers the range down to the min value of the type
+        let start =
+            if rng.gen_bool(0.5) { I256::from_raw(magnitude) } else { I256::from_raw(!magnitude) };
+
+        Ok(IntValueTree::new(start, false))
+    }
+}
+
+impl Strategy for IntStrategy {
+    type Tree = IntValueTree;
+    type Value = I256;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let total_weight = self.random_weight + self.fixtures_weight + self.edge_weight;
+        let bias = runner.rng().gen_range(0..total_weight);
+        // randomly select one of 3 strategies
+        match bias {
+            x if x < self.edge_weight => self.generate_edge_tree(runner),
+            x if x < self.edge_weight + self.fixtures_weight => self.generate_fixtures_tree(runner),
+            _ => self.generate_random_tree(runner),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::test_runner::Config;
+
+    #[test]
+    fn generates_within_type_range() {
+        let strat = IntStrategy::new(8, vec![I256::from(-100), I256::from(1000)]);
+        let mut runner = TestRunner::new(Config::default());
+
+        for _ in 0..256 {
+            let mut tree = strat.new_tree(&mut runner).unwrap();
+            assert!((I256::from(-128)..=I256::from(127)).contains(&tree.current()));
+
+            while tree.simplify() {
+                assert!((I256::from(-128)..=I256::from(127)).contains(&tree.current()));
+            }
+            assert_eq!(tree.current(), I256::zero());
+        }
+    }
+
+    #[test]
+    fn shrinks_towards_zero() {
+        let mut tree = IntValueTree::new(I256::from(-1000), false);
+        while tree.simplify() {
+            assert!(tree.current() <= I256::zero() && tree.current() > I256::from(-1000));
+        }
+        assert_eq!(tree.current(), I256::zero());
+
+        // complicating after simplifying moves back towards the start value
+        let mut tree = IntValueTree::new(I256::MIN, false);
+        assert!(tree.simplify());
+        assert!(tree.complicate());
+        assert!(tree.current() < I256::zero());
+    }
+
+    #[test]
+    fn shrinks_negative_within_start() {
+        let start = I256::from(-128);
+        let within_start = |value: I256| (start..=I256::zero()).contains(&value);
+
+        let mut tree = IntValueTree::new(start, false);
+        while tree.simplify() {
+            assert!(within_start(tree.current()));
+            while tree.complicate() {
+                assert!(within_start(tree.current()));
+            }
+        }
+        assert!(within_start(tree.current()));
+        assert!(!tree.complicate());
+
+        // a tree that cannot shrink any further stays at its value
+        let mut tree = IntValueTree::new(start, false);
+        tree.lo = start;
+        assert!(!tree.simplify());
+        assert!(!tree.complicate());
+        assert_eq!(tree.current(), start);
+    }
+}
@@ -1,13 +1,29 @@
 mod uint;
 pub use uint::UintStrategy;
 
+mod int;
+pub use int::IntStrategy;
+
+mod tuple;
+pub use tuple::TupleStrategy;
+
+mod constraints;
+pub use constraints::{FuzzConstraints, ParamConstraint};
+
+mod fixtures;
+pub use fixtures::FuzzFixtures;
+
 mod param;
 pub use param::{fuzz_param, fuzz_param_from_state};
 
 mod calldata;
 pub use calldata::fuzz_calldata;
 
+mod corpus;
+pub use corpus::{fuzz_calldata_from_corpus, FuzzCorpus};
+
 mod state;
 pub use state::{
     build_initial_state, collect_state_from_call, fuzz_calldata_from_state, EvmFuzzState,
+    FuzzDictionary,
 };
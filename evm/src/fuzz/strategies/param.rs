@@ -2,69 +2,75 @@ use ethers::{
     abi::{ParamType, Token, Tokenizable},
     types::{Address, Bytes, I256, U256},
 };
-use proptest::prelude::*;
+use proptest::{prelude::*, strategy::Union};
 
-use super::state::EvmFuzzState;
+use super::{
+    constraints::{bound_uint, uint_bounds, ParamConstraint},
+    state::EvmFuzzState,
+    tuple::TupleStrategy,
+};
 
 /// The max length of arrays we fuzz for is 256.
 pub const MAX_ARRAY_LEN: usize = 256;
 
 /// Given a parameter type, returns a strategy for generating values for that type.
 ///
+/// Every generated value satisfies the given constraints, and integers are generated from the
+/// given fixtures more often. Constraints and fixtures of arrays are applied to each of their
+/// elements.
+///
 /// Works with ABI Encoder v2 tuples.
-pub fn fuzz_param(param: &ParamType) -> impl Strategy<Value = Token> {
+pub fn fuzz_param(
+    param: &ParamType,
+    constraints: &[ParamConstraint],
+    fixtures: &[U256],
+) -> BoxedStrategy<Token> {
+    let not_zero = constraints.contains(&ParamConstraint::NotZero);
     match param {
         ParamType::Address => {
             // The key to making this work is the `boxed()` call which type erases everything
             // https://altsysrq.github.io/proptest-book/proptest/tutorial/transforming-strategies.html
-            any::<[u8; 20]>().prop_map(|x| Address::from_slice(&x).into_token()).boxed()
+            any::<[u8; 20]>()
+                .prop_map(move |x| non_zero_address(Address::from_slice(&x), not_zero).into_token())
+                .boxed()
         }
         ParamType::Bytes => any::<Vec<u8>>().prop_map(|x| Bytes::from(x).into_token()).boxed(),
-        // For ints and uints we sample from a U256, then wrap it to the correct size with a
-        // modulo operation. Note that this introduces modulo bias, but it can be removed with
-        // rejection sampling if it's determined the bias is too severe. Rejection sampling may
-        // slow down tests as it resamples bad values, so may want to benchmark the performance
-        // hit and weigh that against the current bias before implementing
-        ParamType::Int(n) => match n / 8 {
-            32 => any::<[u8; 32]>()
-                .prop_map(move |x| I256::from_raw(U256::from(&x)).into_token())
-                .boxed(),
-            y @ 1..=31 => any::<[u8; 32]>()
-                .prop_map(move |x| {
-                    // Generate a uintN in the correct range, then shift it to the range of intN
-                    // by subtracting 2^(N-1)
-                    let uint = U256::from(&x) % U256::from(2).pow(U256::from(y * 8));
-                    let max_int_plus1 = U256::from(2).pow(U256::from(y * 8 - 1));
-                    let num = I256::from_raw(uint.overflowing_sub(max_int_plus1).0);
-                    num.into_token()
-                })
-                .boxed(),
-            _ => panic!("unsupported solidity type int{n}"),
-        },
+        ParamType::Int(n) => {
+            let fixtures = fixtures.iter().copied().map(I256::from_raw).collect();
+            super::IntStrategy::new(*n, fixtures)
+                .prop_map(move |x| non_zero_int(x, not_zero).into_token())
+                .boxed()
+        }
         ParamType::Uint(n) => {
-            super::UintStrategy::new(*n, vec![]).prop_map(|x| x.into_token()).boxed()
+            let (min, max) = uint_bounds(*n, constraints);
+            super::UintStrategy::new(*n, fixtures.to_vec())
+                .with_bounds(min, max)
+                .prop_map(|x| x.into_token())
+                .boxed()
         }
         ParamType::Bool => any::<bool>().prop_map(|x| x.into_token()).boxed(),
         ParamType::String => any::<Vec<u8>>()
             .prop_map(|x| Token::String(unsafe { std::str::from_utf8_unchecked(&x).to_string() }))
             .boxed(),
-        ParamType::Array(param) => proptest::collection::vec(fuzz_param(param), 0..MAX_ARRAY_LEN)
-            .prop_map(Token::Array)
-            .boxed(),
+        ParamType::Array(param) => {
+            fuzz_array(fuzz_param(param, constraints, fixtures)).prop_map(Token::Array).boxed()
+        }
         ParamType::FixedBytes(size) => (0..*size as u64)
             .map(|_| any::<u8>())
             .collect::<Vec<_>>()
             .prop_map(Token::FixedBytes)
             .boxed(),
-        ParamType::FixedArray(param, size) => {
-            std::iter::repeat_with(|| fuzz_param(param).prop_map(|param| param.into_token()))
+        ParamType::FixedArray(param, size) => TupleStrategy::new(
+            std::iter::repeat_with(|| fuzz_param(param, constraints, fixtures))
                 .take(*size)
-                .collect::<Vec<_>>()
-                .prop_map(Token::FixedArray)
-                .boxed()
-        }
+                .collect(),
+        )
+        .prop_map(Token::FixedArray)
+        .boxed(),
         ParamType::Tuple(params) => {
-            params.iter().map(fuzz_param).collect::<Vec<_>>().prop_map(Token::Tuple).boxed()
+            TupleStrategy::new(params.iter().map(|param| fuzz_param(param, &[], &[])).collect())
+                .prop_map(Token::Tuple)
+                .boxed()
         }
     }
 }
@@ -72,27 +78,44 @@ pub fn fuzz_param(param: &ParamType) -> impl Strategy<Value = Token> {
 /// Given a parameter type, returns a strategy for generating values for that type, given some EVM
 /// fuzz state.
 ///
+/// Values taken from the state are mapped onto the given constraints.
+///
 /// Works with ABI Encoder v2 tuples.
-pub fn fuzz_param_from_state(param: &ParamType, state: EvmFuzzState) -> BoxedStrategy<Token> {
+pub fn fuzz_param_from_state(
+    param: &ParamType,
+    state: EvmFuzzState,
+    constraints: &[ParamConstraint],
+) -> BoxedStrategy<Token> {
+    let not_zero = constraints.contains(&ParamConstraint::NotZero);
+
     // These are to comply with lifetime requirements
-    let state_len = state.borrow().len();
+    let state_len = state.borrow().values.len();
     let s = state.clone();
 
-    // Select a value from the state
+    // Select a value from the state, and remember that it was used
     let value = any::<prop::sample::Index>()
         .prop_map(move |index| index.index(state_len))
-        .prop_map(move |index| *s.borrow().iter().nth(index).unwrap());
+        .prop_map(move |index| {
+            let mut dictionary = s.borrow_mut();
+            let value = *dictionary.values.iter().nth(index).unwrap();
+            dictionary.drawn.insert(value);
+            value
+        });
 
     // Convert the value based on the parameter type
     match param {
-        ParamType::Address => {
-            value.prop_map(move |value| Address::from_slice(&value[12..]).into_token()).boxed()
-        }
+        ParamType::Address => value
+            .prop_map(move |value| {
+                non_zero_address(Address::from_slice(&value[12..]), not_zero).into_token()
+            })
+            .boxed(),
         ParamType::Bytes => value.prop_map(move |value| Bytes::from(value).into_token()).boxed(),
         ParamType::Int(n) => match n / 8 {
-            32 => {
-                value.prop_map(move |value| I256::from_raw(U256::from(value)).into_token()).boxed()
-            }
+            32 => value
+                .prop_map(move |value| {
+                    non_zero_int(I256::from_raw(U256::from(value)), not_zero).into_token()
+                })
+                .boxed(),
             y @ 1..=31 => value
                 .prop_map(move |value| {
                     // Generate a uintN in the correct range, then shift it to the range of intN
@@ -100,18 +123,19 @@ pub fn fuzz_param_from_state(param: &ParamType, state: EvmFuzzState) -> BoxedStr
                     let uint = U256::from(value) % U256::from(2usize).pow(U256::from(y * 8));
                     let max_int_plus1 = U256::from(2usize).pow(U256::from(y * 8 - 1));
                     let num = I256::from_raw(uint.overflowing_sub(max_int_plus1).0);
-                    num.into_token()
+                    non_zero_int(num, not_zero).into_token()
                 })
                 .boxed(),
             _ => panic!("unsupported solidity type int{n}"),
         },
         ParamType::Uint(n) => match n / 8 {
-            32 => value.prop_map(move |value| U256::from(value).into_token()).boxed(),
-            y @ 1..=31 => value
-                .prop_map(move |value| {
-                    (U256::from(value) % (U256::from(2usize).pow(U256::from(y * 8)))).into_token()
-                })
-                .boxed(),
+            1..=32 => {
+                // Bounding to the type's range also takes care of wrapping the value to uintN
+                let (min, max) = uint_bounds(*n, constraints);
+                value
+                    .prop_map(move |value| bound_uint(U256::from(value), min, max).into_token())
+                    .boxed()
+            }
             _ => panic!("unsupported solidity type uint{n}"),
         },
         ParamType::Bool => value.prop_map(move |value| Token::Bool(value[31] == 1)).boxed(),
@@ -120,35 +144,72 @@ pub fn fuzz_param_from_state(param: &ParamType, state: EvmFuzzState) -> BoxedStr
                 Token::String(unsafe { std::str::from_utf8_unchecked(&value[..]).to_string() })
             })
             .boxed(),
-        ParamType::Array(param) => {
-            proptest::collection::vec(fuzz_param_from_state(param, state), 0..MAX_ARRAY_LEN)
-                .prop_map(Token::Array)
-                .boxed()
-        }
+        ParamType::Array(param) => fuzz_array(fuzz_param_from_state(param, state, constraints))
+            .prop_map(Token::Array)
+            .boxed(),
         ParamType::FixedBytes(size) => {
             let size = *size;
             value.prop_map(move |value| Token::FixedBytes(value[32 - size..].to_vec())).boxed()
         }
-        ParamType::FixedArray(param, size) => {
-            let fixed_size = *size;
-            proptest::collection::vec(fuzz_param_from_state(param, state), fixed_size)
-                .prop_map(Token::FixedArray)
-                .boxed()
-        }
-        ParamType::Tuple(params) => params
-            .iter()
-            .map(|p| fuzz_param_from_state(p, state.clone()))
-            .collect::<Vec<_>>()
-            .prop_map(Token::Tuple)
-            .boxed(),
+        ParamType::FixedArray(param, size) => TupleStrategy::new(
+            std::iter::repeat_with(|| fuzz_param_from_state(param, state.clone(), constraints))
+                .take(*size)
+                .collect(),
+        )
+        .prop_map(Token::FixedArray)
+        .boxed(),
+        ParamType::Tuple(params) => TupleStrategy::new(
+            params.iter().map(|p| fuzz_param_from_state(p, state.clone(), &[])).collect(),
+        )
+        .prop_map(Token::Tuple)
+        .boxed(),
+    }
+}
+
+/// Returns a strategy for dynamic arrays of the given elements.
+///
+/// The length is biased towards the edge cases: empty arrays, arrays with a single element and
+/// arrays of the max length. Shrinking falls back to the shorter of these cases.
+fn fuzz_array(element: BoxedStrategy<Token>) -> BoxedStrategy<Vec<Token>> {
+    Union::new_weighted(vec![
+        (1, Just(vec![]).boxed()),
+        (1, proptest::collection::vec(element.clone(), 1).boxed()),
+        (7, proptest::collection::vec(element.clone(), 0..=MAX_ARRAY_LEN).boxed()),
+        (1, proptest::collection::vec(element, MAX_ARRAY_LEN).boxed()),
+    ])
+    .boxed()
+}
+
+/// Replaces the zero address with `0x..01` if the value must not be zero
+fn non_zero_address(address: Address, not_zero: bool) -> Address {
+    if not_zero && address.is_zero() {
+        Address::from_low_u64_be(1)
+    } else {
+        address
+    }
+}
+
+/// Replaces zero with one if the value must not be zero
+fn non_zero_int(num: I256, not_zero: bool) -> I256 {
+    if not_zero && num.is_zero() {
+        I256::one()
+    } else {
+        num
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::fuzz::strategies::{build_initial_state, fuzz_calldata, fuzz_calldata_from_state};
-    use ethers::abi::AbiParser;
+    use crate::fuzz::strategies::{
+        build_initial_state, fuzz_calldata, fuzz_calldata_from_state, FuzzConstraints, FuzzFixtures,
+    };
+    use ethers::{
+        abi::AbiParser,
+        types::{I256, U256},
+    };
+    use proptest::prop_assert;
     use revm::db::{CacheDB, EmptyDB};
+    use std::cell::Cell;
 
     #[test]
     fn can_fuzz_array() {
@@ -159,8 +220,8 @@ mod tests {
         let state = build_initial_state(&db);
 
         let strat = proptest::strategy::Union::new_weighted(vec![
-            (60, fuzz_calldata(func.clone())),
-            (40, fuzz_calldata_from_state(func, state)),
+            (60, fuzz_calldata(func.clone(), &Default::default(), &Default::default())),
+            (40, fuzz_calldata_from_state(func, state, &Default::default())),
         ]);
 
         let cfg = proptest::test_runner::Config { failure_persistence: None, ..Default::default() };
@@ -168,4 +229,92 @@ mod tests {
 
         let _ = runner.run(&strat, |_| Ok(()));
     }
+
+    #[test]
+    fn can_fuzz_with_constraints() {
+        let f = "function testBounds(uint256 amount, address addr, uint8[] small)";
+        let func = AbiParser::default().parse_function(f).unwrap();
+
+        let mut constraints = FuzzConstraints::default();
+        constraints.add_annotation("amount range 1..1e24").unwrap();
+        constraints.add_annotation("addr notzero").unwrap();
+        constraints.add_annotation("small range 10..20").unwrap();
+
+        let db = CacheDB::new(EmptyDB());
+        let state = build_initial_state(&db);
+
+        let strat = proptest::strategy::Union::new_weighted(vec![
+            (60, fuzz_calldata(func.clone(), &constraints, &Default::default())),
+            (40, fuzz_calldata_from_state(func.clone(), state, &constraints)),
+        ]);
+
+        let cfg = proptest::test_runner::Config { failure_persistence: None, ..Default::default() };
+        let mut runner = proptest::test_runner::TestRunner::new(cfg);
+
+        runner
+            .run(&strat, |calldata| {
+                let args = func.decode_input(&calldata.as_ref()[4..]).unwrap();
+
+                let amount = args[0].clone().into_uint().unwrap();
+                prop_assert!(amount >= U256::one() && amount <= U256::exp10(24));
+                prop_assert!(!args[1].clone().into_address().unwrap().is_zero());
+                for value in args[2].clone().into_array().unwrap() {
+                    let value = value.into_uint().unwrap();
+                    prop_assert!(value >= 10.into() && value <= 20.into());
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn can_fuzz_nested_params() {
+        let f = "function testNested((int8,uint16[2])[] values, int24[3] ints)";
+        let func = AbiParser::default().parse_function(f).unwrap();
+
+        let cfg = proptest::test_runner::Config { failure_persistence: None, ..Default::default() };
+        let mut runner = proptest::test_runner::TestRunner::new(cfg);
+
+        runner
+            .run(
+                &fuzz_calldata(func.clone(), &Default::default(), &Default::default()),
+                |calldata| {
+                    let args = func.decode_input(&calldata.as_ref()[4..]).unwrap();
+                    prop_assert!(
+                        args[0].clone().into_array().unwrap().len() <= super::MAX_ARRAY_LEN
+                    );
+                    prop_assert!(args[1].clone().into_fixed_array().unwrap().len() == 3);
+                    Ok(())
+                },
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn can_fuzz_with_fixtures() {
+        let f = "function testFixtures(uint256 amount, int8 delta)";
+        let func = AbiParser::default().parse_function(f).unwrap();
+
+        let mut fixtures = FuzzFixtures::default();
+        fixtures.insert("amount", vec![1337.into()]);
+        fixtures.insert("delta", vec![I256::from(-42).into_raw()]);
+
+        let cfg = proptest::test_runner::Config { failure_persistence: None, ..Default::default() };
+        let mut runner = proptest::test_runner::TestRunner::new(cfg);
+
+        let (amount_seen, delta_seen) = (Cell::new(false), Cell::new(false));
+        runner
+            .run(&fuzz_calldata(func.clone(), &Default::default(), &fixtures), |calldata| {
+                let args = func.decode_input(&calldata.as_ref()[4..]).unwrap();
+                if args[0].clone().into_uint().unwrap() == U256::from(1337) {
+                    amount_seen.set(true);
+                }
+                if I256::from_raw(args[1].clone().into_int().unwrap()) == I256::from(-42) {
+                    delta_seen.set(true);
+                }
+                Ok(())
+            })
+            .unwrap();
+        assert!(amount_seen.get() && delta_seen.get());
+    }
 }
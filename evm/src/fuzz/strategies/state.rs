@@ -1,4 +1,4 @@
-use super::fuzz_param_from_state;
+use super::{fuzz_param_from_state, FuzzConstraints};
 use crate::executor::StateChangeset;
 use bytes::Bytes;
 use ethers::{
@@ -10,23 +10,42 @@ use revm::{
     db::{CacheDB, DatabaseRef},
     opcode, spec_opcode_gas, SpecId,
 };
-use std::{cell::RefCell, collections::HashSet, io::Write, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashSet},
+    io::Write,
+    rc::Rc,
+};
 
 /// A set of arbitrary 32 byte data from the VM used to generate values for the strategy.
 ///
 /// Wrapped in a shareable container.
-pub type EvmFuzzState = Rc<RefCell<HashSet<[u8; 32]>>>;
+pub type EvmFuzzState = Rc<RefCell<FuzzDictionary>>;
+
+/// The values collected from the VM, along with the ones the strategies drew from them
+#[derive(Debug, Default)]
+pub struct FuzzDictionary {
+    /// Arbitrary 32 byte data from the VM, ordered so that seeded runs draw the same values
+    pub values: BTreeSet<[u8; 32]>,
+    /// The distinct values that were used to generate fuzz inputs
+    pub drawn: HashSet<[u8; 32]>,
+}
 
 /// Given a function and some state, it returns a strategy which generated valid calldata for the
 /// given function's input types, based on state taken from the EVM.
+///
+/// The values taken from the state are mapped onto the given parameter constraints.
 pub fn fuzz_calldata_from_state(
     func: Function,
     state: EvmFuzzState,
+    constraints: &FuzzConstraints,
 ) -> BoxedStrategy<ethers::types::Bytes> {
     let strats = func
         .inputs
         .iter()
-        .map(|input| fuzz_param_from_state(&input.kind, state.clone()))
+        .map(|input| {
+            fuzz_param_from_state(&input.kind, state.clone(), constraints.get(&input.name))
+        })
         .collect::<Vec<_>>();
 
     strats
@@ -48,7 +67,7 @@ This is a bug, please open an issue: https://github.com/foundry-rs/foundry/issue
 
 /// Builds the initial [EvmFuzzState] from a database.
 pub fn build_initial_state<DB: DatabaseRef>(db: &CacheDB<DB>) -> EvmFuzzState {
-    let mut state: HashSet<[u8; 32]> = HashSet::new();
+    let mut state: BTreeSet<[u8; 32]> = BTreeSet::new();
     for (address, storage) in db.storage() {
         let info = db.basic(*address);
 
@@ -71,7 +90,7 @@ pub fn build_initial_state<DB: DatabaseRef>(db: &CacheDB<DB>) -> EvmFuzzState {
         state.insert(H256::from(Address::random()).into());
     }
 
-    Rc::new(RefCell::new(state))
+    Rc::new(RefCell::new(FuzzDictionary { values: state, drawn: HashSet::new() }))
 }
 
 /// Collects state changes from a [StateChangeset] and logs into an [EvmFuzzState].
@@ -80,7 +99,8 @@ pub fn collect_state_from_call(
     state_changeset: &StateChangeset,
     state: EvmFuzzState,
 ) {
-    let state = &mut *state.borrow_mut();
+    let mut dictionary = state.borrow_mut();
+    let state = &mut dictionary.values;
 
     for (address, account) in state_changeset {
         // Insert basic account information
@@ -0,0 +1,108 @@
+use ethers::abi::Token;
+use proptest::{
+    strategy::{BoxedStrategy, NewTree, Strategy, ValueTree},
+    test_runner::TestRunner,
+};
+
+/// Value tree for tuples (structs) and fixed size arrays.
+///
+/// The components are shrunk one at a time, in order: a component is simplified as far as
+/// possible before moving on to the next one, so a counterexample only differs from the simplest
+/// value in the components that actually matter.
+pub struct TupleValueTree {
+    /// The value trees of the components
+    components: Vec<Box<dyn ValueTree<Value = Token>>>,
+    /// The index of the component that is currently being simplified
+    shrinker: usize,
+    /// The index of the component that was simplified last, if it can still be complicated
+    prev_shrinker: Option<usize>,
+}
+
+impl ValueTree for TupleValueTree {
+    type Value = Vec<Token>;
+
+    fn current(&self) -> Self::Value {
+        self.components.iter().map(|component| component.current()).collect()
+    }
+
+    fn simplify(&mut self) -> bool {
+        while self.shrinker < self.components.len() {
+            if self.components[self.shrinker].simplify() {
+                self.prev_shrinker = Some(self.shrinker);
+                return true
+            }
+            self.shrinker += 1;
+        }
+        false
+    }
+
+    fn complicate(&mut self) -> bool {
+        if let Some(shrinker) = self.prev_shrinker {
+            if self.components[shrinker].complicate() {
+                return true
+            }
+            self.prev_shrinker = None;
+        }
+        false
+    }
+}
+
+/// Strategy for tuples (structs) and fixed size arrays, generating each component with its own
+/// strategy.
+#[derive(Debug)]
+pub struct TupleStrategy {
+    /// The strategies of the components
+    components: Vec<BoxedStrategy<Token>>,
+}
+
+impl TupleStrategy {
+    /// Create a new strategy.
+    /// #Arguments
+    /// * `components` - The strategies of the components, in order
+    pub fn new(components: Vec<BoxedStrategy<Token>>) -> Self {
+        Self { components }
+    }
+}
+
+impl Strategy for TupleStrategy {
+    type Tree = TupleValueTree;
+    type Value = Vec<Token>;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let components = self
+            .components
+            .iter()
+            .map(|component| component.new_tree(runner))
+            .collect::<Result<_, _>>()?;
+        Ok(TupleValueTree { components, shrinker: 0, prev_shrinker: None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzz::strategies::UintStrategy;
+    use ethers::{abi::Tokenizable, types::U256};
+    use proptest::test_runner::Config;
+
+    #[test]
+    fn shrinks_component_by_component() {
+        let uint = || UintStrategy::new(256, vec![]).prop_map(|x| x.into_token()).boxed();
+        let strat = TupleStrategy::new(vec![uint(), uint()]);
+        let mut runner = TestRunner::new(Config::default());
+
+        for _ in 0..256 {
+            let mut tree = strat.new_tree(&mut runner).unwrap();
+            let start = tree.current();
+
+            // the second component is untouched until the first one is fully simplified
+            while tree.simplify() {
+                let current = tree.current();
+                if current[0] != Token::Uint(U256::zero()) {
+                    assert_eq!(current[1], start[1]);
+                }
+            }
+            assert_eq!(tree.current(), vec![Token::Uint(U256::zero()); 2]);
+        }
+    }
+}
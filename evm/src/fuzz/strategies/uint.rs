@@ -22,10 +22,11 @@ pub struct UintValueTree {
 impl UintValueTree {
     /// Create a new tree
     /// # Arguments
+    /// * `min` - Lowest value the tree can be simplified to
     /// * `start` - Starting value for the tree
     /// * `fixed` - If `true` the tree would only contain one element and won't be simplified.
-    fn new(start: U256, fixed: bool) -> Self {
-        Self { lo: 0.into(), curr: start, hi: start, fixed }
+    fn new(min: U256, start: U256, fixed: bool) -> Self {
+        Self { lo: min, curr: start, hi: start, fixed }
     }
 
     fn reposition(&mut self) -> bool {
@@ -73,12 +74,19 @@ impl ValueTree for UintValueTree {
 /// param). Then generate a value for this bit size.
 /// 2. Generate a random value around the edges (+/- 3 around 0 and max possible value)
 /// 3. Generate a value from a predefined fixtures set
+///
+/// All generated values are within the strategy's bounds, which default to the full range of the
+/// uint type.
 #[derive(Debug)]
 pub struct UintStrategy {
     /// Bit sise of uint (e.g. 256)
     bits: usize,
     /// A set of fixtures to be generated
     fixtures: Vec<U256>,
+    /// The lowest value that can be generated
+    min: U256,
+    /// The highest value that can be generated
+    max: U256,
     /// The weight for edge cases (+/- 3 around 0 and max possible value)
     edge_weight: usize,
     /// The weight for fixtures
@@ -93,45 +101,63 @@ impl UintStrategy {
     /// * `bits` - Size of uint in bits
     /// * `fixtures` - A set of fixed values to be generated (according to fixtures weight)
     pub fn new(bits: usize, fixtures: Vec<U256>) -> Self {
+        let max = if bits < 256 { (U256::from(1u8) << U256::from(bits)) - 1 } else { U256::MAX };
         Self {
             bits,
             fixtures,
+            min: U256::zero(),
+            max,
             edge_weight: 10usize,
             fixtures_weight: 40usize,
             random_weight: 50usize,
         }
     }
 
+    /// Restricts the generated values to the inclusive range `min..=max`.
+    ///
+    /// Shrinking never leaves the range either.
+    #[must_use]
+    pub fn with_bounds(mut self, min: U256, max: U256) -> Self {
+        self.min = self.min.max(min);
+        self.max = self.max.min(max);
+        self
+    }
+
     fn generate_edge_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
         let rng = runner.rng();
 
-        // Choose if we want values around 0 or max
+        // Choose if we want values around min or max
         let is_min = rng.gen_bool(0.5);
         let offset = U256::from(rng.gen_range(0..4));
-        let max = if self.bits < 256 {
-            (U256::from(1u8) << U256::from(self.bits)) - 1
+        let start = if is_min {
+            self.min.saturating_add(offset).min(self.max)
         } else {
-            U256::MAX
+            self.max.saturating_sub(offset).max(self.min)
         };
-        let start = if is_min { offset } else { max - offset };
 
-        Ok(UintValueTree::new(start, false))
+        Ok(UintValueTree::new(self.min, start, false))
     }
 
     fn generate_fixtures_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let fixtures = self
+            .fixtures
+            .iter()
+            .filter(|fixture| (self.min..=self.max).contains(*fixture))
+            .collect::<Vec<_>>();
+
         // generate edge cases if there's no fixtures
-        if self.fixtures.is_empty() {
+        if fixtures.is_empty() {
             return self.generate_edge_tree(runner)
         }
-        let idx = runner.rng().gen_range(0..self.fixtures.len());
+        let idx = runner.rng().gen_range(0..fixtures.len());
 
-        Ok(UintValueTree::new(self.fixtures[idx], false))
+        Ok(UintValueTree::new(self.min, *fixtures[idx], false))
     }
 
     fn generate_random_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
         let rng = runner.rng();
-        // generate random number of bits uniformly
-        let bits = rng.gen_range(0..=self.bits);
+        // generate random number of bits uniformly, up to the size of the range
+        let bits = rng.gen_range(0..=(self.max - self.min).bits());
 
         // init 2 128-bit randoms
         let mut higher: u128 = rng.gen_range(0..=u128::MAX);
@@ -154,9 +180,9 @@ impl UintStrategy {
         inner[1] = (lower >> 64) as u64;
         inner[2] = (higher & mask64) as u64;
         inner[3] = (higher >> 64) as u64;
-        let start: U256 = U256(inner);
+        let start = super::constraints::bound_uint(U256(inner), self.min, self.max);
 
-        Ok(UintValueTree::new(start, false))
+        Ok(UintValueTree::new(self.min, start, false))
     }
 }
 
@@ -175,3 +201,25 @@ impl Strategy for UintStrategy {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::test_runner::Config;
+
+    #[test]
+    fn shrinks_within_bounds() {
+        let strat = UintStrategy::new(256, vec![]).with_bounds(100.into(), 1000.into());
+        let mut runner = TestRunner::new(Config::default());
+
+        for _ in 0..256 {
+            let mut tree = strat.new_tree(&mut runner).unwrap();
+            assert!((U256::from(100)..=U256::from(1000)).contains(&tree.current()));
+
+            while tree.simplify() {
+                assert!((U256::from(100)..=U256::from(1000)).contains(&tree.current()));
+            }
+            assert_eq!(tree.current(), 100.into());
+        }
+    }
+}
@@ -4,16 +4,21 @@ use super::{
 };
 use crate::{
     abi::{CHEATCODE_ADDRESS, CONSOLE_ABI, HARDHAT_CONSOLE_ABI, HARDHAT_CONSOLE_ADDRESS, HEVM_ABI},
-    trace::{node::CallTraceNode, utils},
+    trace::{node::CallTraceNode, precompiles, storage::StorageDecoder, utils},
 };
 use ethers::{
-    abi::{Abi, Address, Event, Function, Param, ParamType, Token},
+    abi::{param_type::Reader, Abi, AbiParser, Address, Event, EventParam, Function, Token},
+    solc::artifacts::StorageLayout,
     types::H256,
 };
-use std::collections::{BTreeMap, HashMap};
+use foundry_utils::SignaturesCache;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+};
 
 /// Build a new [CallTraceDecoder].
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct CallTraceDecoderBuilder {
     decoder: CallTraceDecoder,
 }
@@ -42,6 +47,35 @@ impl CallTraceDecoderBuilder {
         self
     }
 
+    /// Add the precompiles and system contracts of the chain, e.g. Arbitrum's ArbSys.
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        for (address, name, abi) in precompiles::system_contracts(chain_id) {
+            self.decoder.add_contract(address, name, abi);
+        }
+        self
+    }
+
+    /// Add contracts at fixed addresses, e.g. custom precompiles, by their name and ABI.
+    pub fn with_precompiles(mut self, precompiles: Vec<(Address, String, Abi)>) -> Self {
+        for (address, name, abi) in precompiles {
+            self.decoder.add_contract(address, &name, &abi);
+        }
+        self
+    }
+
+    /// Add a cache of signatures to decode calls and events the identified ABIs do not know.
+    pub fn with_signatures(mut self, signatures: SignaturesCache) -> Self {
+        self.decoder.signatures = signatures;
+        self
+    }
+
+    /// Add the storage layouts of contracts by their name, to decode the storage slots written
+    /// in calls to them.
+    pub fn with_storage_layouts(mut self, layouts: BTreeMap<String, StorageLayout>) -> Self {
+        self.decoder.storage_layouts.extend(layouts);
+        self
+    }
+
     /// Build the decoder.
     pub fn build(self) -> CallTraceDecoder {
         self.decoder
@@ -55,7 +89,7 @@ impl CallTraceDecoderBuilder {
 ///
 /// Note that a call trace decoder is required for each new set of traces, since addresses in
 /// different sets might overlap.
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct CallTraceDecoder {
     /// Information for decoding precompile calls.
     pub precompiles: HashMap<Address, Function>,
@@ -71,6 +105,10 @@ pub struct CallTraceDecoder {
     pub events: BTreeMap<(H256, usize), Vec<Event>>,
     /// All known errors
     pub errors: Abi,
+    /// Signatures of functions and events that are not part of any identified ABI
+    pub signatures: SignaturesCache,
+    /// The storage layouts of contracts by their name
+    pub storage_layouts: BTreeMap<String, StorageLayout>,
 }
 
 impl CallTraceDecoder {
@@ -86,78 +124,7 @@ impl CallTraceDecoder {
             .collect::<BTreeMap<[u8; 4], Vec<Function>>>();
 
         Self {
-            // TODO: These are the Ethereum precompiles. We should add a way to support precompiles
-            // for other networks, too.
-            precompiles: [
-                precompile(
-                    1,
-                    "ecrecover",
-                    [
-                        ParamType::FixedBytes(32),
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                    ],
-                    [ParamType::Address],
-                ),
-                precompile(2, "keccak", [ParamType::Bytes], [ParamType::FixedBytes(32)]),
-                precompile(3, "ripemd", [ParamType::Bytes], [ParamType::FixedBytes(32)]),
-                precompile(4, "identity", [ParamType::Bytes], [ParamType::Bytes]),
-                precompile(
-                    5,
-                    "modexp",
-                    [
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                        ParamType::Bytes,
-                    ],
-                    [ParamType::Bytes],
-                ),
-                precompile(
-                    6,
-                    "ecadd",
-                    [
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                    ],
-                    [ParamType::Uint(256), ParamType::Uint(256)],
-                ),
-                precompile(
-                    7,
-                    "ecmul",
-                    [ParamType::Uint(256), ParamType::Uint(256), ParamType::Uint(256)],
-                    [ParamType::Uint(256), ParamType::Uint(256)],
-                ),
-                precompile(
-                    8,
-                    "ecpairing",
-                    [
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                        ParamType::Uint(256),
-                    ],
-                    [ParamType::Uint(256)],
-                ),
-                precompile(
-                    9,
-                    "blake2f",
-                    [
-                        ParamType::Uint(4),
-                        ParamType::FixedBytes(64),
-                        ParamType::FixedBytes(128),
-                        ParamType::FixedBytes(16),
-                        ParamType::FixedBytes(1),
-                    ],
-                    [ParamType::FixedBytes(64)],
-                ),
-            ]
-            .into(),
+            precompiles: precompiles::ethereum(),
             contracts: Default::default(),
             labels: [
                 (CHEATCODE_ADDRESS, "VM".to_string()),
@@ -170,6 +137,8 @@ impl CallTraceDecoder {
                 .map(|event| ((event.signature(), indexed_inputs(event)), vec![event.clone()]))
                 .collect::<BTreeMap<(H256, usize), Vec<Event>>>(),
             errors: Abi::default(),
+            signatures: SignaturesCache::default(),
+            storage_layouts: Default::default(),
         }
     }
 
@@ -197,31 +166,39 @@ impl CallTraceDecoder {
             }
 
             if let Some(abi) = &identity.abi {
-                // Store known functions for the address
-                abi.functions()
-                    .map(|func| (func.short_signature(), func.clone()))
-                    .for_each(|(sig, func)| self.functions.entry(sig).or_default().push(func));
-
-                // Flatten events from all ABIs
-                abi.events()
-                    .map(|event| ((event.signature(), indexed_inputs(event)), event.clone()))
-                    .for_each(|(sig, event)| {
-                        self.events.entry(sig).or_default().push(event);
-                    });
-
-                // Flatten errors from all ABIs
-                abi.errors().for_each(|error| {
-                    let entry = self
-                        .errors
-                        .errors
-                        .entry(error.name.clone())
-                        .or_insert_with(Default::default);
-                    entry.push(error.clone());
-                });
+                self.add_abi(abi);
             }
         });
     }
 
+    /// Adds a contract at a fixed address, which takes precedence over identified contracts
+    pub fn add_contract(&mut self, address: Address, name: &str, abi: &Abi) {
+        self.contracts.insert(address, name.to_string());
+        self.labels.insert(address, name.to_string());
+        self.add_abi(abi);
+    }
+
+    fn add_abi(&mut self, abi: &Abi) {
+        // Store known functions for the address
+        abi.functions()
+            .map(|func| (func.short_signature(), func.clone()))
+            .for_each(|(sig, func)| self.functions.entry(sig).or_default().push(func));
+
+        // Flatten events from all ABIs
+        abi.events()
+            .map(|event| ((event.signature(), indexed_inputs(event)), event.clone()))
+            .for_each(|(sig, event)| {
+                self.events.entry(sig).or_default().push(event);
+            });
+
+        // Flatten errors from all ABIs
+        abi.errors().for_each(|error| {
+            let entry =
+                self.errors.errors.entry(error.name.clone()).or_insert_with(Default::default);
+            entry.push(error.clone());
+        });
+    }
+
     pub fn decode(&self, traces: &mut CallTraceArena) {
         for node in traces.arena.iter_mut() {
             // Set contract name
@@ -241,6 +218,8 @@ impl CallTraceDecoder {
                 if bytes.len() >= 4 {
                     if let Some(funcs) = self.functions.get(&bytes[0..4]) {
                         node.decode_function(funcs, &self.labels, &self.errors);
+                    } else if let Some(func) = self.cached_function(bytes) {
+                        node.decode_function(&[func], &self.labels, &self.errors);
                     }
                 } else {
                     node.trace.data = RawOrDecodedCall::Decoded("fallback".to_string(), Vec::new());
@@ -262,6 +241,19 @@ impl CallTraceDecoder {
 
             // Decode events
             self.decode_events(node);
+
+            // Decode storage changes
+            if !node.storage_changes.is_empty() {
+                let layout = node
+                    .trace
+                    .contract
+                    .as_ref()
+                    .and_then(|contract| self.storage_layouts.get(contract));
+                let storage = StorageDecoder::new(layout, &traces.preimages, &self.labels);
+                for change in node.storage_changes.iter_mut() {
+                    change.decoded = storage.decode(change);
+                }
+            }
         }
     }
 
@@ -273,53 +265,195 @@ impl CallTraceDecoder {
 
     fn decode_event(&self, log: &mut RawOrDecodedLog) {
         if let RawOrDecodedLog::Raw(raw_log) = log {
-            if let Some(events) = self.events.get(&(raw_log.topics[0], raw_log.topics.len() - 1)) {
-                for event in events {
-                    if let Ok(decoded) = event.parse_log(raw_log.clone()) {
-                        *log = RawOrDecodedLog::Decoded(
-                            event.name.clone(),
-                            decoded
-                                .params
-                                .into_iter()
-                                .map(|param| (param.name, self.apply_label(&param.value)))
-                                .collect(),
-                        );
-                        break
-                    }
+            if raw_log.topics.is_empty() {
+                return
+            }
+            let indexed = raw_log.topics.len() - 1;
+            // Fall back to the signatures cache for events no identified ABI knows
+            let events: Cow<[Event]> = match self.events.get(&(raw_log.topics[0], indexed)) {
+                Some(events) => Cow::Borrowed(events),
+                None => Cow::Owned(
+                    self.signatures
+                        .events(raw_log.topics[0].as_bytes())
+                        .iter()
+                        .filter_map(|sig| event_from_signature(sig, indexed))
+                        .collect(),
+                ),
+            };
+            for event in events.iter() {
+                if let Ok(decoded) = event.parse_log(raw_log.clone()) {
+                    *log = RawOrDecodedLog::Decoded(
+                        event.name.clone(),
+                        decoded
+                            .params
+                            .into_iter()
+                            .map(|param| (param.name, self.apply_label(&param.value)))
+                            .collect(),
+                    );
+                    break
                 }
             }
         }
     }
 
+    /// Returns the first function of the signatures cache that can decode the calldata
+    fn cached_function(&self, calldata: &[u8]) -> Option<Function> {
+        self.signatures
+            .functions(&calldata[..4])
+            .iter()
+            .filter_map(|sig| AbiParser::default().parse_function(sig).ok())
+            .find(|func| func.decode_input(&calldata[4..]).is_ok())
+    }
+
     fn apply_label(&self, token: &Token) -> String {
         utils::label(token, &self.labels)
     }
 }
 
-fn precompile<I, O>(number: u8, name: impl ToString, inputs: I, outputs: O) -> (Address, Function)
-where
-    I: IntoIterator<Item = ParamType>,
-    O: IntoIterator<Item = ParamType>,
-{
-    (
-        Address::from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, number]),
-        #[allow(deprecated)]
-        Function {
-            name: name.to_string(),
-            inputs: inputs
-                .into_iter()
-                .map(|kind| Param { name: "".to_string(), kind, internal_type: None })
-                .collect(),
-            outputs: outputs
-                .into_iter()
-                .map(|kind| Param { name: "".to_string(), kind, internal_type: None })
-                .collect(),
-            constant: None,
-            state_mutability: ethers::abi::StateMutability::Pure,
-        },
-    )
-}
-
 fn indexed_inputs(event: &Event) -> usize {
     event.inputs.iter().filter(|param| param.indexed).count()
 }
+
+/// Parses an event signature of the signatures cache, e.g. `Transfer(address,address,uint256)`.
+///
+/// Parameters marked as `indexed` are indexed. If none are marked, the first `indexed` parameters
+/// are assumed to be, since that is the most common layout.
+fn event_from_signature(signature: &str, indexed: usize) -> Option<Event> {
+    let (name, params) = signature.strip_suffix(')')?.split_once('(')?;
+    let params = split_params(params)
+        .into_iter()
+        .map(|param| {
+            let (kind, marked) = match param.strip_suffix(" indexed") {
+                Some(kind) => (kind, true),
+                None => (param, false),
+            };
+            Reader::read(kind.trim()).ok().map(|kind| (kind, marked))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let marked = params.iter().any(|(_, marked)| *marked);
+
+    let inputs = params
+        .into_iter()
+        .enumerate()
+        .map(|(i, (kind, is_marked))| EventParam {
+            name: format!("param{i}"),
+            kind,
+            indexed: if marked { is_marked } else { i < indexed },
+        })
+        .collect::<Vec<_>>();
+    if inputs.iter().filter(|param| param.indexed).count() != indexed {
+        return None
+    }
+    Some(Event { name: name.to_string(), inputs, anonymous: false })
+}
+
+/// Splits the parameters of a signature at the commas that are not part of a tuple
+fn split_params(params: &str) -> Vec<&str> {
+    if params.is_empty() {
+        return Vec::new()
+    }
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut split = Vec::new();
+    for (i, c) in params.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                split.push(&params[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(&params[start..]);
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::CallTrace;
+    use ethers::{abi::RawLog, utils::keccak256};
+
+    #[test]
+    fn can_decode_with_signatures_cache() {
+        let mut signatures = SignaturesCache::default();
+        signatures.add_function("transfer(address,uint256)");
+        signatures.add_event("Transfer(address,address,uint256)");
+        let decoder = CallTraceDecoderBuilder::new().with_signatures(signatures).build();
+
+        let to = Address::from_low_u64_be(2);
+        let amount = Token::Uint(100u64.into());
+        let mut calldata = keccak256("transfer(address,uint256)")[..4].to_vec();
+        calldata.extend(ethers::abi::encode(&[Token::Address(to), amount.clone()]));
+
+        let mut arena = CallTraceArena::default();
+        arena.arena[0].trace = CallTrace {
+            success: true,
+            data: RawOrDecodedCall::Raw(calldata),
+            ..Default::default()
+        };
+        arena.arena[0].logs.push(RawOrDecodedLog::Raw(RawLog {
+            topics: vec![
+                H256::from(keccak256("Transfer(address,address,uint256)")),
+                H256::from(Address::from_low_u64_be(1)),
+                H256::from(to),
+            ],
+            data: ethers::abi::encode(&[amount]),
+        }));
+        decoder.decode(&mut arena);
+
+        match &arena.arena[0].trace.data {
+            RawOrDecodedCall::Decoded(name, params) => {
+                assert_eq!(name, "transfer");
+                assert_eq!(params[1], "100");
+            }
+            RawOrDecodedCall::Raw(_) => panic!("call was not decoded"),
+        }
+        match &arena.arena[0].logs[0] {
+            RawOrDecodedLog::Decoded(name, params) => {
+                assert_eq!(name, "Transfer");
+                assert_eq!(params.len(), 3);
+                assert_eq!(params[2].1, "100");
+            }
+            RawOrDecodedLog::Raw(_) => panic!("event was not decoded"),
+        }
+    }
+
+    #[test]
+    fn can_decode_chain_system_contracts() {
+        let arb_sys = Address::from_low_u64_be(0x64);
+        let mut arena = CallTraceArena::default();
+        arena.arena[0].trace = CallTrace {
+            success: true,
+            address: arb_sys,
+            data: RawOrDecodedCall::Raw(keccak256("arbBlockNumber()")[..4].to_vec()),
+            ..Default::default()
+        };
+
+        let mainnet = CallTraceDecoderBuilder::new().with_chain_id(1).build();
+        let mut mainnet_arena = arena.clone();
+        mainnet.decode(&mut mainnet_arena);
+        assert!(matches!(mainnet_arena.arena[0].trace.data, RawOrDecodedCall::Raw(_)));
+
+        let arbitrum = CallTraceDecoderBuilder::new().with_chain_id(42161).build();
+        arbitrum.decode(&mut arena);
+        assert_eq!(arena.arena[0].trace.label.as_deref(), Some("ArbSys"));
+        assert!(matches!(
+            &arena.arena[0].trace.data,
+            RawOrDecodedCall::Decoded(name, _) if name == "arbBlockNumber"
+        ));
+    }
+
+    #[test]
+    fn can_parse_event_signatures() {
+        let event = event_from_signature("Swap(address,(uint256,uint256),bool)", 1).unwrap();
+        assert_eq!(event.inputs.len(), 3);
+        assert!(event.inputs[0].indexed && !event.inputs[1].indexed);
+
+        let event = event_from_signature("Transfer(address,address indexed,uint256)", 1).unwrap();
+        assert!(!event.inputs[0].indexed && event.inputs[1].indexed);
+        assert!(event_from_signature("Transfer(address,address indexed,uint256)", 2).is_none());
+    }
+}
@@ -0,0 +1,459 @@
+use super::{
+    CallTrace, CallTraceArena, LogCallOrder, RawOrDecodedCall, RawOrDecodedLog,
+    RawOrDecodedReturnData, StorageChange, TraceKind,
+};
+use crate::CallKind;
+use ethers::{
+    abi::Address,
+    types::{H256, U256},
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt, path::Path, str::FromStr};
+
+/// The formats traces can be exported in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// A tree of calls with their decoded inputs, outputs, gas usage and logs
+    Json,
+    /// The [trace event format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU)
+    /// understood by `chrome://tracing` and Perfetto, with gas as the time axis
+    Chrome,
+}
+
+impl fmt::Display for TraceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceFormat::Json => f.write_str("json"),
+            TraceFormat::Chrome => f.write_str("chrome"),
+        }
+    }
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(TraceFormat::Json),
+            "chrome" => Ok(TraceFormat::Chrome),
+            _ => Err(format!("Unknown trace format: {s}, expected one of: json, chrome")),
+        }
+    }
+}
+
+/// The traces of several runs to export, grouped by e.g. the test contract they belong to.
+#[derive(Debug, Default)]
+pub struct TraceExport<'a> {
+    runs: BTreeMap<String, BTreeMap<String, &'a [(TraceKind, CallTraceArena)]>>,
+}
+
+impl<'a> TraceExport<'a> {
+    /// Adds the traces of the run `name` of the given group
+    pub fn add(&mut self, group: &str, name: &str, traces: &'a [(TraceKind, CallTraceArena)]) {
+        self.runs.entry(group.to_string()).or_default().insert(name.to_string(), traces);
+    }
+
+    /// Serializes the traces in the given format.
+    ///
+    /// In the Chrome format, every group is a process and every run is a thread, whose traces
+    /// follow each other on the gas axis.
+    pub fn to_json(&self, format: TraceFormat) -> serde_json::Result<serde_json::Value> {
+        match format {
+            TraceFormat::Json => {
+                let runs = self
+                    .runs
+                    .iter()
+                    .map(|(group, runs)| {
+                        let runs = runs
+                            .iter()
+                            .map(|(name, traces)| {
+                                let traces = traces
+                                    .iter()
+                                    .map(|(kind, arena)| ExportedTrace {
+                                        kind: kind.clone(),
+                                        trace: arena.export(),
+                                    })
+                                    .collect::<Vec<_>>();
+                                (name, traces)
+                            })
+                            .collect::<BTreeMap<_, _>>();
+                        (group, runs)
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                serde_json::to_value(runs)
+            }
+            TraceFormat::Chrome => {
+                let mut chrome = ChromeTrace::default();
+                for (pid, (group, runs)) in self.runs.iter().enumerate() {
+                    for (tid, (name, traces)) in runs.iter().enumerate() {
+                        chrome.add_thread(pid + 1, tid + 1, group, name);
+                        traces.iter().fold(0, |start, (_, arena)| {
+                            chrome.add_trace(pid + 1, tid + 1, start, arena)
+                        });
+                    }
+                }
+                serde_json::to_value(chrome)
+            }
+        }
+    }
+
+    /// Writes the traces to a file in the given format
+    pub fn write(&self, path: impl AsRef<Path>, format: TraceFormat) -> eyre::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(&self.to_json(format)?)?)?;
+        Ok(())
+    }
+}
+
+/// A trace of a run along with its kind
+#[derive(Debug, Serialize)]
+struct ExportedTrace {
+    kind: TraceKind,
+    trace: ExportedCall,
+}
+
+/// A call of a trace with its logs and subcalls
+#[derive(Debug, Serialize)]
+pub struct ExportedCall {
+    pub kind: CallKind,
+    pub address: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract: Option<String>,
+    /// The name of the called function, if the calldata could be decoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    /// The decoded inputs of the call, if the calldata could be decoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<Vec<String>>,
+    /// The raw calldata, if it could not be decoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calldata: Option<String>,
+    /// The decoded or hex encoded return data, or the runtime code of created contracts
+    pub output: String,
+    pub success: bool,
+    pub gas: u64,
+    pub value: U256,
+    pub logs: Vec<ExportedLog>,
+    /// The storage slots written in the call, if storage changes were recorded
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub storage: Vec<StorageChange>,
+    pub calls: Vec<ExportedCall>,
+}
+
+/// A log emitted in a call
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ExportedLog {
+    Decoded { event: String, params: Vec<ExportedParam> },
+    Raw { topics: Vec<H256>, data: String },
+}
+
+/// A decoded parameter of a log
+#[derive(Debug, Serialize)]
+pub struct ExportedParam {
+    pub name: String,
+    pub value: String,
+}
+
+impl CallTraceArena {
+    /// Returns the trace as a tree of calls, for exporting it as JSON
+    pub fn export(&self) -> ExportedCall {
+        self.export_node(0)
+    }
+
+    fn export_node(&self, idx: usize) -> ExportedCall {
+        let node = &self.arena[idx];
+        let trace = &node.trace;
+        let (function, inputs, calldata) = match &trace.data {
+            RawOrDecodedCall::Decoded(function, inputs) => {
+                (Some(function.clone()), Some(inputs.clone()), None)
+            }
+            RawOrDecodedCall::Raw(bytes) => (None, None, Some(format!("0x{}", hex::encode(bytes)))),
+        };
+
+        ExportedCall {
+            kind: trace.kind,
+            address: trace.address,
+            label: trace.label.clone(),
+            contract: trace.contract.clone(),
+            function,
+            inputs,
+            calldata,
+            output: output_string(&trace.output),
+            success: trace.success,
+            gas: trace.gas_cost,
+            value: trace.value,
+            logs: node
+                .logs
+                .iter()
+                .map(|log| match log {
+                    RawOrDecodedLog::Decoded(event, params) => ExportedLog::Decoded {
+                        event: event.clone(),
+                        params: params
+                            .iter()
+                            .map(|(name, value)| ExportedParam {
+                                name: name.clone(),
+                                value: value.clone(),
+                            })
+                            .collect(),
+                    },
+                    RawOrDecodedLog::Raw(log) => ExportedLog::Raw {
+                        topics: log.topics.clone(),
+                        data: format!("0x{}", hex::encode(&log.data)),
+                    },
+                })
+                .collect(),
+            storage: node.storage_changes.clone(),
+            calls: node.children.iter().map(|child| self.export_node(*child)).collect(),
+        }
+    }
+}
+
+/// Returns the return data as a string, hex encoded if it could not be decoded
+fn output_string(output: &RawOrDecodedReturnData) -> String {
+    match output {
+        RawOrDecodedReturnData::Raw(bytes) => format!("0x{}", hex::encode(bytes)),
+        RawOrDecodedReturnData::Decoded(decoded) => decoded.clone(),
+    }
+}
+
+/// Returns the log as a string without the colors of its `Display` implementation
+fn log_string(log: &RawOrDecodedLog) -> String {
+    match log {
+        RawOrDecodedLog::Decoded(event, params) => {
+            let params = params
+                .iter()
+                .map(|(name, value)| format!("{name}: {value}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{event}({params})")
+        }
+        RawOrDecodedLog::Raw(log) => {
+            let topics = log.topics.iter().map(|topic| format!("{topic:?}")).collect::<Vec<_>>();
+            format!("topics: [{}], data: 0x{}", topics.join(", "), hex::encode(&log.data))
+        }
+    }
+}
+
+/// Traces in the Chrome trace event format, with gas as the time axis
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChromeTrace {
+    pub trace_events: Vec<ChromeTraceEvent>,
+}
+
+/// A single event of a [`ChromeTrace`]
+#[derive(Debug, Serialize)]
+pub struct ChromeTraceEvent {
+    pub name: String,
+    /// The phase of the event: `X` for calls, `i` for logs and `M` for metadata
+    pub ph: &'static str,
+    /// The gas offset at which the event starts
+    pub ts: u64,
+    /// The gas used by a call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dur: Option<u64>,
+    pub pid: usize,
+    pub tid: usize,
+    /// The scope of instant events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub s: Option<&'static str>,
+    pub args: BTreeMap<String, String>,
+}
+
+impl ChromeTrace {
+    /// Names the process and thread that the traces of a run are added to
+    pub fn add_thread(&mut self, pid: usize, tid: usize, process: &str, thread: &str) {
+        for (name, value) in [("process_name", process), ("thread_name", thread)] {
+            self.trace_events.push(ChromeTraceEvent {
+                name: name.to_string(),
+                ph: "M",
+                ts: 0,
+                dur: None,
+                pid,
+                tid,
+                s: None,
+                args: BTreeMap::from([("name".to_string(), value.to_string())]),
+            });
+        }
+    }
+
+    /// Adds the calls of the trace as complete events and its logs as instant events, starting at
+    /// the gas offset `start`.
+    ///
+    /// Subcalls are laid out one after another from the start of their parent, since only the gas
+    /// used by every call is known. Returns the gas offset at which the trace ends.
+    pub fn add_trace(&mut self, pid: usize, tid: usize, start: u64, arena: &CallTraceArena) -> u64 {
+        self.add_node(pid, tid, start, arena.arena[0].trace.gas_cost, arena, 0)
+    }
+
+    fn add_node(
+        &mut self,
+        pid: usize,
+        tid: usize,
+        start: u64,
+        gas: u64,
+        arena: &CallTraceArena,
+        idx: usize,
+    ) -> u64 {
+        let node = &arena.arena[idx];
+        let end = start + gas;
+
+        let mut args = BTreeMap::from([
+            ("address".to_string(), format!("{:?}", node.trace.address)),
+            ("gas".to_string(), node.trace.gas_cost.to_string()),
+            ("success".to_string(), node.trace.success.to_string()),
+        ]);
+        if let RawOrDecodedCall::Decoded(_, inputs) = &node.trace.data {
+            args.insert("inputs".to_string(), inputs.join(", "));
+        }
+        if !node.trace.created() {
+            args.insert("output".to_string(), output_string(&node.trace.output));
+        }
+        self.trace_events.push(ChromeTraceEvent {
+            name: call_name(&node.trace),
+            ph: "X",
+            ts: start,
+            dur: Some(gas),
+            pid,
+            tid,
+            s: None,
+            args,
+        });
+
+        let mut cursor = start;
+        for child in &node.ordering {
+            match child {
+                LogCallOrder::Log(index) => {
+                    let name = match &node.logs[*index] {
+                        RawOrDecodedLog::Decoded(event, _) => format!("emit {event}"),
+                        RawOrDecodedLog::Raw(_) => "emit".to_string(),
+                    };
+                    self.trace_events.push(ChromeTraceEvent {
+                        name,
+                        ph: "i",
+                        ts: cursor,
+                        dur: None,
+                        pid,
+                        tid,
+                        s: Some("t"),
+                        args: BTreeMap::from([("log".to_string(), log_string(&node.logs[*index]))]),
+                    });
+                }
+                // storage changes are part of the JSON export only
+                LogCallOrder::Storage(_) => {}
+                LogCallOrder::Call(index) => {
+                    let child = node.children[*index];
+                    // keep subcalls within their parent, so the events nest properly
+                    let gas = arena.arena[child].trace.gas_cost.min(end - cursor);
+                    cursor = self.add_node(pid, tid, cursor, gas, arena, child);
+                }
+            }
+        }
+
+        end
+    }
+}
+
+/// Returns the name of a call in the format `<contract>::<function>`, or `new <contract>` for
+/// contract creations
+fn call_name(trace: &CallTrace) -> String {
+    let contract = trace.label.clone().unwrap_or_else(|| format!("{:?}", trace.address));
+    if trace.created() {
+        return format!("new {contract}")
+    }
+    match &trace.data {
+        RawOrDecodedCall::Decoded(function, _) => format!("{contract}::{function}"),
+        RawOrDecodedCall::Raw(bytes) if bytes.len() >= 4 => {
+            format!("{contract}::0x{}", hex::encode(&bytes[..4]))
+        }
+        RawOrDecodedCall::Raw(_) => format!("{contract}::fallback"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::node::CallTraceNode;
+
+    fn arena() -> CallTraceArena {
+        let call = |depth: usize, function: &str, gas_cost: u64| CallTrace {
+            depth,
+            success: true,
+            label: Some("Counter".to_string()),
+            data: RawOrDecodedCall::Decoded(function.to_string(), vec!["1".to_string()]),
+            output: RawOrDecodedReturnData::Decoded("2".to_string()),
+            gas_cost,
+            ..Default::default()
+        };
+        CallTraceArena {
+            arena: vec![
+                CallTraceNode {
+                    children: vec![1],
+                    trace: call(0, "testIncrement", 1000),
+                    logs: vec![RawOrDecodedLog::Decoded(
+                        "Incremented".to_string(),
+                        vec![("by".to_string(), "1".to_string())],
+                    )],
+                    ordering: vec![LogCallOrder::Call(0), LogCallOrder::Log(0)],
+                    ..Default::default()
+                },
+                CallTraceNode {
+                    parent: Some(0),
+                    idx: 1,
+                    trace: call(1, "increment", 400),
+                    ..Default::default()
+                },
+            ],
+            preimages: Default::default(),
+        }
+    }
+
+    #[test]
+    fn can_export_json() {
+        let traces = vec![(TraceKind::Execution, arena())];
+        let mut export = TraceExport::default();
+        export.add("src/Counter.t.sol:CounterTest", "testIncrement()", &traces);
+
+        let json = export.to_json(TraceFormat::Json).unwrap();
+        let trace = &json["src/Counter.t.sol:CounterTest"]["testIncrement()"][0];
+        assert_eq!(trace["kind"], "Execution");
+        assert_eq!(trace["trace"]["function"], "testIncrement");
+        assert_eq!(trace["trace"]["gas"], 1000);
+        assert_eq!(trace["trace"]["logs"][0]["event"], "Incremented");
+        assert_eq!(trace["trace"]["logs"][0]["params"][0]["value"], "1");
+        assert_eq!(trace["trace"]["calls"][0]["function"], "increment");
+        assert_eq!(trace["trace"]["calls"][0]["output"], "2");
+    }
+
+    #[test]
+    fn can_export_chrome() {
+        let traces = vec![(TraceKind::Setup, arena()), (TraceKind::Execution, arena())];
+        let mut export = TraceExport::default();
+        export.add("src/Counter.t.sol:CounterTest", "testIncrement()", &traces);
+
+        let json = export.to_json(TraceFormat::Chrome).unwrap();
+        let events = json["traceEvents"].as_array().unwrap();
+        let calls = events.iter().filter(|event| event["ph"] == "X").collect::<Vec<_>>();
+        assert_eq!(calls.len(), 4);
+
+        // subcalls start with their parent, traces of a run follow each other
+        assert_eq!(calls[0]["name"], "Counter::testIncrement");
+        assert_eq!((calls[0]["ts"].as_u64(), calls[0]["dur"].as_u64()), (Some(0), Some(1000)));
+        assert_eq!(calls[1]["name"], "Counter::increment");
+        assert_eq!((calls[1]["ts"].as_u64(), calls[1]["dur"].as_u64()), (Some(0), Some(400)));
+        assert_eq!(calls[2]["ts"].as_u64(), Some(1000));
+
+        // the log is emitted after the subcall
+        let log = events.iter().find(|event| event["ph"] == "i").unwrap();
+        assert_eq!(
+            (log["name"].as_str(), log["ts"].as_u64()),
+            (Some("emit Incremented"), Some(400))
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use super::{AddressIdentity, TraceIdentifier};
+use ethers::abi::Address;
+use std::collections::HashSet;
+
+/// A trace identifier that chains several identifiers.
+///
+/// The identifiers are queried in the order they were added, and every identifier is only asked
+/// about the addresses that the identifiers before it could not identify.
+#[derive(Default)]
+pub struct CompositeIdentifier<'a> {
+    identifiers: Vec<&'a dyn TraceIdentifier>,
+}
+
+impl<'a> CompositeIdentifier<'a> {
+    /// Adds an identifier with a lower priority than the identifiers added so far
+    #[must_use]
+    pub fn with(mut self, identifier: &'a dyn TraceIdentifier) -> Self {
+        self.identifiers.push(identifier);
+        self
+    }
+}
+
+impl<'a> TraceIdentifier for CompositeIdentifier<'a> {
+    fn identify_addresses(
+        &self,
+        mut addresses: Vec<(&Address, Option<&Vec<u8>>)>,
+    ) -> Vec<AddressIdentity> {
+        let mut identities = Vec::new();
+        for identifier in &self.identifiers {
+            if addresses.is_empty() {
+                break
+            }
+            let identified = identifier.identify_addresses(addresses.clone());
+            // an address only counts as identified once we know what contract it is
+            let known: HashSet<Address> = identified
+                .iter()
+                .filter(|identity| identity.abi.is_some() || identity.contract.is_some())
+                .map(|identity| identity.address)
+                .collect();
+            addresses.retain(|(address, _)| !known.contains(address));
+            identities.extend(identified);
+        }
+        identities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Identifies a fixed set of addresses as the given contract
+    struct Fixed(&'static str, Vec<Address>);
+
+    impl TraceIdentifier for Fixed {
+        fn identify_addresses(
+            &self,
+            addresses: Vec<(&Address, Option<&Vec<u8>>)>,
+        ) -> Vec<AddressIdentity> {
+            addresses
+                .into_iter()
+                .filter(|(address, _)| self.1.contains(address))
+                .map(|(address, _)| AddressIdentity {
+                    address: *address,
+                    label: Some(self.0.to_string()),
+                    contract: Some(self.0.to_string()),
+                    abi: None,
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn identifies_by_priority() {
+        let (a, b, c) = (Address::from_low_u64_be(1), Address::from_low_u64_be(2), Address::zero());
+        let first = Fixed("First", vec![a]);
+        let second = Fixed("Second", vec![a, b]);
+        let identifier = CompositeIdentifier::default().with(&first).with(&second);
+
+        let mut identities = identifier
+            .identify_addresses(vec![(&a, None), (&b, None), (&c, None)])
+            .into_iter()
+            .map(|identity| (identity.address, identity.contract.unwrap()))
+            .collect::<Vec<_>>();
+        identities.sort();
+        assert_eq!(identities, vec![(a, "First".to_string()), (b, "Second".to_string())]);
+    }
+}
@@ -2,29 +2,105 @@ use super::{AddressIdentity, TraceIdentifier};
 use ethers::{
     abi::{Abi, Address, Event},
     prelude::ArtifactId,
+    solc::artifacts::Offsets,
+    utils::keccak256,
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap},
 };
-use std::{borrow::Cow, collections::BTreeMap};
 
 /// A trace identifier that tries to identify addresses using local contracts.
+///
+/// Contracts are identified by the hash of their normalized runtime code, that is without the CBOR
+/// encoded metadata and with the values of immutables masked out, so the same contract is
+/// identified regardless of its constructor arguments or the path it was compiled from.
 pub struct LocalTraceIdentifier {
-    local_contracts: BTreeMap<Vec<u8>, (String, Abi)>,
+    /// The local contracts, by their name
+    local_contracts: Vec<LocalContract>,
+    /// The indices of the local contracts, by the hash of their normalized runtime code
+    index: HashMap<[u8; 32], Vec<usize>>,
+    /// The distinct immutable offsets of the local contracts, the code of an address is normalized
+    /// with each of them when looking it up in the index
+    masks: BTreeSet<Vec<(usize, usize)>>,
+}
+
+struct LocalContract {
+    id: ArtifactId,
+    name: String,
+    abi: Abi,
+    runtime_code: Vec<u8>,
+    /// The offsets and lengths of the immutables in the runtime code
+    immutables: Vec<(usize, usize)>,
 }
 
 impl LocalTraceIdentifier {
     pub fn new(known_contracts: &BTreeMap<ArtifactId, (Abi, Vec<u8>)>) -> Self {
-        Self {
-            local_contracts: known_contracts
-                .iter()
-                .map(|(id, (abi, runtime_code))| {
-                    (runtime_code.clone(), (id.name.clone(), abi.clone()))
-                })
-                .collect(),
+        let local_contracts = known_contracts
+            .iter()
+            .map(|(id, (abi, runtime_code))| LocalContract {
+                id: id.clone(),
+                name: id.name.clone(),
+                abi: abi.clone(),
+                runtime_code: runtime_code.clone(),
+                immutables: Vec::new(),
+            })
+            .collect();
+        let mut identifier =
+            Self { local_contracts, index: Default::default(), masks: Default::default() };
+        identifier.reindex();
+        identifier
+    }
+
+    /// Sets the immutable references of the contracts' runtime code, as found in the
+    /// `deployedBytecode.immutableReferences` of their artifacts.
+    ///
+    /// Without them, only contracts without immutables are identified exactly.
+    #[must_use]
+    pub fn with_immutable_references(
+        mut self,
+        references: &BTreeMap<ArtifactId, BTreeMap<String, Vec<Offsets>>>,
+    ) -> Self {
+        for contract in &mut self.local_contracts {
+            if let Some(references) = references.get(&contract.id) {
+                let mut immutables = references
+                    .values()
+                    .flatten()
+                    .map(|offsets| (offsets.start as usize, offsets.length as usize))
+                    .collect::<Vec<_>>();
+                immutables.sort_unstable();
+                contract.immutables = immutables;
+            }
         }
+        self.reindex();
+        self
     }
 
     /// Get all the events of the local contracts.
     pub fn events(&self) -> Vec<Event> {
-        self.local_contracts.iter().flat_map(|(_, (_, abi))| abi.events().cloned()).collect()
+        self.local_contracts.iter().flat_map(|contract| contract.abi.events().cloned()).collect()
+    }
+
+    fn reindex(&mut self) {
+        self.index.clear();
+        self.masks.clear();
+        for (idx, contract) in self.local_contracts.iter().enumerate() {
+            let hash = keccak256(normalize(&contract.runtime_code, &contract.immutables));
+            self.index.entry(hash).or_default().push(idx);
+            self.masks.insert(contract.immutables.clone());
+        }
+    }
+
+    /// Finds the local contract with the same normalized code
+    fn find_exact(&self, code: &[u8]) -> Option<&LocalContract> {
+        self.masks.iter().find_map(|mask| {
+            let hash = keccak256(normalize(code, mask));
+            self.index
+                .get(&hash)?
+                .iter()
+                .map(|idx| &self.local_contracts[*idx])
+                .find(|contract| &contract.immutables == mask)
+        })
     }
 }
 
@@ -37,22 +113,52 @@ impl TraceIdentifier for LocalTraceIdentifier {
             .into_iter()
             .filter_map(|(address, code)| {
                 let code = code?;
-                let (_, (name, abi)) = self
-                    .local_contracts
-                    .iter()
-                    .find(|(known_code, _)| diff_score(known_code, code) < 0.1)?;
+                // Fall back to fuzzy matching for code that was modified after compilation, e.g. by
+                // the constructor
+                let contract = self.find_exact(code).or_else(|| {
+                    self.local_contracts
+                        .iter()
+                        .find(|contract| diff_score(&contract.runtime_code, code) < 0.1)
+                })?;
 
                 Some(AddressIdentity {
                     address: *address,
-                    contract: Some(name.clone()),
-                    label: Some(name.clone()),
-                    abi: Some(Cow::Borrowed(abi)),
+                    contract: Some(contract.name.clone()),
+                    label: Some(contract.name.clone()),
+                    abi: Some(Cow::Borrowed(&contract.abi)),
                 })
             })
             .collect()
     }
 }
 
+/// Returns the code without its trailing CBOR encoded metadata and with the given ranges, the
+/// immutables, zeroed
+fn normalize(code: &[u8], immutables: &[(usize, usize)]) -> Vec<u8> {
+    let mut code = strip_metadata(code).to_vec();
+    for (start, length) in immutables {
+        if let Some(immutable) = code.get_mut(*start..start + length) {
+            immutable.iter_mut().for_each(|byte| *byte = 0);
+        }
+    }
+    code
+}
+
+/// Strips the CBOR encoded metadata solc appends to the runtime code.
+///
+/// The last two bytes of the code are the length of the metadata, which is a CBOR map.
+fn strip_metadata(code: &[u8]) -> &[u8] {
+    if code.len() < 2 {
+        return code
+    }
+    let len = u16::from_be_bytes([code[code.len() - 2], code[code.len() - 1]]) as usize;
+    match code.len().checked_sub(len + 2) {
+        // a CBOR map with at most 23 entries
+        Some(start) if len > 0 && (0xa0..=0xb7).contains(&code[start]) => &code[..start],
+        _ => code,
+    }
+}
+
 /// Very simple fuzzy matching of contract bytecode.
 ///
 /// Will fail for small contracts that are essentially all immutable variables.
@@ -72,3 +178,57 @@ fn diff_score(a: &[u8], b: &[u8]) -> f64 {
     }
     diff_chars as f64 / cutoff_len as f64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn artifact_id(name: &str) -> ArtifactId {
+        ArtifactId {
+            path: PathBuf::from(format!("out/{name}.sol/{name}.json")),
+            name: name.to_string(),
+            source: PathBuf::from(format!("src/{name}.sol")),
+            version: "0.8.13".parse().unwrap(),
+        }
+    }
+
+    /// Runtime code with an immutable at offset 2 and metadata with the given hash byte
+    fn code(immutable: u8, metadata: u8) -> Vec<u8> {
+        let mut code = vec![0x60, 0x80, immutable, immutable, 0x56, 0x00];
+        // {"ipfs": <hash>} followed by the length of the map
+        code.extend([0xa1, 0x64, b'i', b'p', b'f', b's', 0x41, metadata, 0x00, 0x08]);
+        code
+    }
+
+    #[test]
+    fn strips_metadata() {
+        assert_eq!(strip_metadata(&code(0, 1)), &[0x60, 0x80, 0, 0, 0x56, 0x00]);
+        assert_eq!(strip_metadata(&[0x60, 0x80]), &[0x60, 0x80]);
+        assert_eq!(strip_metadata(&[]), &[] as &[u8]);
+    }
+
+    #[test]
+    fn identifies_contracts_with_immutables() {
+        let known_contracts =
+            [(artifact_id("Token"), (Abi::default(), code(0, 1)))].into_iter().collect();
+        let references = [(
+            artifact_id("Token"),
+            [("5".to_string(), vec![Offsets { start: 2, length: 2 }])].into_iter().collect(),
+        )]
+        .into_iter()
+        .collect();
+
+        // the immutables and metadata differ too much for fuzzy matching to identify the contract
+        let deployed = code(0x12, 2);
+        let identifier = LocalTraceIdentifier::new(&known_contracts);
+        assert!(identifier
+            .identify_addresses(vec![(&Address::zero(), Some(&deployed))])
+            .is_empty());
+
+        let identifier = identifier.with_immutable_references(&references);
+        let identities = identifier.identify_addresses(vec![(&Address::zero(), Some(&deployed))]);
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].contract.as_deref(), Some("Token"));
+    }
+}
@@ -4,6 +4,12 @@ pub use local::LocalTraceIdentifier;
 mod etherscan;
 pub use etherscan::EtherscanIdentifier;
 
+mod sourcify;
+pub use sourcify::{SourcifyIdentifier, SourcifyRepository, SOURCIFY_URL};
+
+mod composite;
+pub use composite::CompositeIdentifier;
+
 use ethers::abi::{Abi, Address};
 use std::borrow::Cow;
 
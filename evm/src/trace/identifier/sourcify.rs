@@ -0,0 +1,233 @@
+use super::{AddressIdentity, TraceIdentifier};
+use ethers::{
+    abi::{Abi, Address},
+    types::Chain,
+    utils::to_checksum,
+};
+use futures::future::join_all;
+use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tracing::{trace, warn};
+
+/// The public Sourcify repository
+pub const SOURCIFY_URL: &str = "https://repo.sourcify.dev";
+
+/// The kinds of matches in a Sourcify repository, in order of preference
+const MATCH_KINDS: [&str; 2] = ["full_match", "partial_match"];
+
+/// Where the contracts verified on Sourcify are looked up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourcifyRepository {
+    /// A local mirror of the Sourcify repository
+    Local(PathBuf),
+    /// A Sourcify repository served over HTTP, e.g. [`SOURCIFY_URL`]
+    Http(String),
+}
+
+impl FromStr for SourcifyRepository {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("empty Sourcify repository".to_string())
+        }
+        if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(SourcifyRepository::Http(s.trim_end_matches('/').to_string()))
+        } else {
+            Ok(SourcifyRepository::Local(PathBuf::from(s)))
+        }
+    }
+}
+
+/// The parts of the metadata of a verified contract we care about
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    output: MetadataOutput,
+    settings: MetadataSettings,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataOutput {
+    abi: Abi,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataSettings {
+    /// The source file and the name of the verified contract
+    #[serde(rename = "compilationTarget")]
+    compilation_target: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    fn contract_name(&self) -> Option<&String> {
+        self.settings.compilation_target.values().next()
+    }
+}
+
+/// A trace identifier that tries to identify addresses using contracts verified on Sourcify.
+///
+/// Contracts are looked up by chain and address in either a local mirror of the Sourcify
+/// repository or a repository served over HTTP.
+pub struct SourcifyIdentifier {
+    chain_id: Option<u64>,
+    repository: Option<SourcifyRepository>,
+    client: reqwest::Client,
+}
+
+impl SourcifyIdentifier {
+    /// Creates a new Sourcify identifier.
+    ///
+    /// The identifier is a noop if either `chain` or `repository` are `None`.
+    pub fn new(chain: Option<impl Into<Chain>>, repository: Option<SourcifyRepository>) -> Self {
+        Self {
+            chain_id: chain.map(|chain| u64::from(chain.into())),
+            repository,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Returns the paths of the metadata files of the contract at `address`, relative to the root
+    /// of the repository
+    fn metadata_paths(chain_id: u64, address: &Address) -> Vec<String> {
+        let address = to_checksum(address, None);
+        MATCH_KINDS
+            .iter()
+            .map(|kind| format!("contracts/{kind}/{chain_id}/{address}/metadata.json"))
+            .collect()
+    }
+
+    /// Reads the metadata of the contract at `address` from a local mirror
+    fn read_metadata(root: &Path, chain_id: u64, address: &Address) -> Option<Metadata> {
+        Self::metadata_paths(chain_id, address).into_iter().find_map(|path| {
+            let content = std::fs::read_to_string(root.join(path)).ok()?;
+            serde_json::from_str(&content)
+                .map_err(|err| {
+                    warn!(target: "sourcifyidentifier", "invalid metadata for {:?}: {}", address, err)
+                })
+                .ok()
+        })
+    }
+
+    /// Fetches the metadata of the contract at `address` from a repository served over HTTP
+    async fn fetch_metadata(&self, url: &str, chain_id: u64, address: Address) -> Option<Metadata> {
+        for path in Self::metadata_paths(chain_id, &address) {
+            let res = match self.client.get(format!("{url}/{path}")).send().await {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!(target: "sourcifyidentifier", "could not fetch metadata for {:?}: {}", address, err);
+                    return None
+                }
+            };
+            if !res.status().is_success() {
+                continue
+            }
+            match res.json::<Metadata>().await {
+                Ok(metadata) => return Some(metadata),
+                Err(err) => {
+                    warn!(target: "sourcifyidentifier", "invalid metadata for {:?}: {}", address, err)
+                }
+            }
+        }
+        None
+    }
+}
+
+impl TraceIdentifier for SourcifyIdentifier {
+    fn identify_addresses(
+        &self,
+        addresses: Vec<(&Address, Option<&Vec<u8>>)>,
+    ) -> Vec<AddressIdentity> {
+        let (chain_id, repository) = match (self.chain_id, self.repository.as_ref()) {
+            (Some(chain_id), Some(repository)) => (chain_id, repository),
+            _ => return Vec::new(),
+        };
+        trace!(target: "sourcifyidentifier", "identifying {} addresses", addresses.len());
+
+        let metadata: Vec<(Address, Option<Metadata>)> = match repository {
+            SourcifyRepository::Local(root) => addresses
+                .into_iter()
+                .map(|(address, _)| (*address, Self::read_metadata(root, chain_id, address)))
+                .collect(),
+            SourcifyRepository::Http(url) => {
+                let fut = join_all(addresses.into_iter().map(|(address, _)| async move {
+                    (*address, self.fetch_metadata(url, chain_id, *address).await)
+                }));
+                foundry_utils::RuntimeOrHandle::new().block_on(fut)
+            }
+        };
+
+        metadata
+            .into_iter()
+            .filter_map(|(address, metadata)| {
+                let metadata = metadata?;
+                let name = metadata.contract_name()?.clone();
+                Some(AddressIdentity {
+                    address,
+                    label: Some(name.clone()),
+                    contract: Some(name),
+                    abi: Some(Cow::Owned(metadata.output.abi)),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_repository() {
+        assert_eq!(
+            "https://repo.sourcify.dev/".parse::<SourcifyRepository>().unwrap(),
+            SourcifyRepository::Http(SOURCIFY_URL.to_string())
+        );
+        assert_eq!(
+            "./sourcify".parse::<SourcifyRepository>().unwrap(),
+            SourcifyRepository::Local(PathBuf::from("./sourcify"))
+        );
+    }
+
+    #[test]
+    fn can_identify_from_local_mirror() {
+        let root = tempfile::tempdir().unwrap();
+        let address: Address = "0x6b175474e89094c44da98b954eedeac495271d0f".parse().unwrap();
+        let dir = root.path().join("contracts/partial_match/1").join(to_checksum(&address, None));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("metadata.json"),
+            r#"{
+                "compiler": { "version": "0.5.12+commit.7709ece9" },
+                "output": {
+                    "abi": [{
+                        "type": "function",
+                        "name": "totalSupply",
+                        "inputs": [],
+                        "outputs": [{ "name": "", "type": "uint256" }],
+                        "stateMutability": "view"
+                    }]
+                },
+                "settings": { "compilationTarget": { "Dai.sol": "Dai" } }
+            }"#,
+        )
+        .unwrap();
+
+        let repository = Some(SourcifyRepository::Local(root.path().to_path_buf()));
+        let identifier = SourcifyIdentifier::new(Some(Chain::Mainnet), repository.clone());
+        let identities =
+            identifier.identify_addresses(vec![(&address, None), (&Address::zero(), None)]);
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].address, address);
+        assert_eq!(identities[0].contract.as_deref(), Some("Dai"));
+        assert!(identities[0].abi.as_ref().unwrap().function("totalSupply").is_ok());
+
+        // a noop without a chain
+        let identifier = SourcifyIdentifier::new(None::<Chain>, repository);
+        assert!(identifier.identify_addresses(vec![(&address, None)]).is_empty());
+    }
+}
@@ -5,20 +5,28 @@ pub mod identifier;
 
 mod decoder;
 mod node;
+mod storage;
 mod utils;
 
+/// Exporting traces as JSON or in the Chrome trace event format
+pub mod export;
+
+/// Precompiles and system contracts of chains
+pub mod precompiles;
+
 pub use decoder::{CallTraceDecoder, CallTraceDecoderBuilder};
+pub use storage::StorageChange;
 
 use crate::{abi::CHEATCODE_ADDRESS, CallKind};
 use ansi_term::Colour;
 use ethers::{
     abi::{Address, RawLog},
-    types::U256,
+    types::{H256, U256},
 };
 use node::CallTraceNode;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     fmt::{self, Write},
 };
 
@@ -27,11 +35,16 @@ use std::{
 pub struct CallTraceArena {
     /// The arena of nodes
     pub arena: Vec<CallTraceNode>,
+    /// The preimages of the keccak256 hashes computed during execution, by their hash.
+    ///
+    /// Only recorded along with storage changes, to decode the keys of mapping slots.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub preimages: BTreeMap<H256, Vec<u8>>,
 }
 
 impl Default for CallTraceArena {
     fn default() -> Self {
-        CallTraceArena { arena: vec![Default::default()] }
+        CallTraceArena { arena: vec![Default::default()], preimages: Default::default() }
     }
 }
 
@@ -102,29 +115,31 @@ impl fmt::Display for CallTraceArena {
             // Display trace header
             writeln!(writer, "{}{}", left, node.trace)?;
 
-            // Display logs and subcalls
+            // Display logs, storage changes and subcalls
             let left_prefix = format!("{child}{BRANCH}");
             let right_prefix = format!("{child}{PIPE}");
             for child in &node.ordering {
+                let mut entry = String::new();
                 match child {
-                    LogCallOrder::Log(index) => {
-                        let mut log = String::new();
-                        write!(log, "{}", node.logs[*index])?;
-
-                        // Prepend our tree structure symbols to each line of the displayed log
-                        log.lines().enumerate().try_for_each(|(i, line)| {
-                            writeln!(
-                                writer,
-                                "{}{}",
-                                if i == 0 { &left_prefix } else { &right_prefix },
-                                line
-                            )
-                        })?;
+                    LogCallOrder::Log(index) => write!(entry, "{}", node.logs[*index])?,
+                    LogCallOrder::Storage(index) => {
+                        write!(entry, "{}", node.storage_changes[*index])?
                     }
                     LogCallOrder::Call(index) => {
                         inner(arena, writer, node.children[*index], &left_prefix, &right_prefix)?;
+                        continue
                     }
                 }
+
+                // Prepend our tree structure symbols to each line of the displayed entry
+                entry.lines().enumerate().try_for_each(|(i, line)| {
+                    writeln!(
+                        writer,
+                        "{}{}",
+                        if i == 0 { &left_prefix } else { &right_prefix },
+                        line
+                    )
+                })?;
             }
 
             // Display trace return data
@@ -192,7 +207,7 @@ impl fmt::Display for RawOrDecodedLog {
     }
 }
 
-/// Ordering enum for calls and logs
+/// Ordering enum for calls, logs and storage changes
 ///
 /// i.e. if Call 0 occurs before Log 0, it will be pushed into the `CallTraceNode`'s ordering before
 /// the log.
@@ -200,6 +215,7 @@ impl fmt::Display for RawOrDecodedLog {
 pub enum LogCallOrder {
     Log(usize),
     Call(usize),
+    Storage(usize),
 }
 
 // TODO: Maybe unify with output
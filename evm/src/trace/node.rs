@@ -2,6 +2,7 @@ use crate::{
     executor::CHEATCODE_ADDRESS,
     trace::{
         utils, CallTrace, LogCallOrder, RawOrDecodedCall, RawOrDecodedLog, RawOrDecodedReturnData,
+        StorageChange,
     },
 };
 use ethers::{
@@ -25,7 +26,10 @@ pub struct CallTraceNode {
     /// Logs
     #[serde(skip)]
     pub logs: Vec<RawOrDecodedLog>,
-    /// Ordering of child calls and logs
+    /// The storage slots written in the call, if storage changes are recorded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub storage_changes: Vec<StorageChange>,
+    /// Ordering of child calls, logs and storage changes
     pub ordering: Vec<LogCallOrder>,
 }
 
@@ -0,0 +1,290 @@
+//! Tables of the precompiles and system contracts of chains, used to decode calls to them
+
+use ethers::abi::{parse_abi, Abi, Address, Function, Param, ParamType};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+/// Returns the precompiles every EVM chain has, which take raw inputs rather than ABI-encoded calls
+pub fn ethereum() -> HashMap<Address, Function> {
+    [
+        precompile(
+            1,
+            "ecrecover",
+            [
+                ParamType::FixedBytes(32),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+            ],
+            [ParamType::Address],
+        ),
+        precompile(2, "keccak", [ParamType::Bytes], [ParamType::FixedBytes(32)]),
+        precompile(3, "ripemd", [ParamType::Bytes], [ParamType::FixedBytes(32)]),
+        precompile(4, "identity", [ParamType::Bytes], [ParamType::Bytes]),
+        precompile(
+            5,
+            "modexp",
+            [ParamType::Uint(256), ParamType::Uint(256), ParamType::Uint(256), ParamType::Bytes],
+            [ParamType::Bytes],
+        ),
+        precompile(
+            6,
+            "ecadd",
+            [
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+            ],
+            [ParamType::Uint(256), ParamType::Uint(256)],
+        ),
+        precompile(
+            7,
+            "ecmul",
+            [ParamType::Uint(256), ParamType::Uint(256), ParamType::Uint(256)],
+            [ParamType::Uint(256), ParamType::Uint(256)],
+        ),
+        precompile(
+            8,
+            "ecpairing",
+            [
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+            ],
+            [ParamType::Uint(256)],
+        ),
+        precompile(
+            9,
+            "blake2f",
+            [
+                ParamType::Uint(4),
+                ParamType::FixedBytes(64),
+                ParamType::FixedBytes(128),
+                ParamType::FixedBytes(16),
+                ParamType::FixedBytes(1),
+            ],
+            [ParamType::FixedBytes(64)],
+        ),
+    ]
+    .into()
+}
+
+/// Returns the system contracts of the chain that live at fixed addresses, e.g. Arbitrum's ArbSys
+/// precompile or Optimism's predeploys, along with their names and ABIs
+pub fn system_contracts(chain_id: u64) -> Vec<(Address, &'static str, &'static Abi)> {
+    match chain_id {
+        // Arbitrum One, Arbitrum Nova, Arbitrum Rinkeby and Arbitrum Goerli
+        42161 | 42170 | 421611 | 421613 => vec![
+            (address(0x64), "ArbSys", &*ARB_SYS_ABI),
+            (address(0x6c), "ArbGasInfo", &*ARB_GAS_INFO_ABI),
+            (address(0x6e), "ArbRetryableTx", &*ARB_RETRYABLE_TX_ABI),
+        ],
+        // Optimism, Optimism Kovan and Optimism Goerli
+        10 | 69 | 420 => vec![
+            (predeploy(0x00), "LegacyMessagePasser", &*OP_LEGACY_MESSAGE_PASSER_ABI),
+            (predeploy(0x06), "WETH9", &*WETH_ABI),
+            (predeploy(0x07), "L2CrossDomainMessenger", &*OP_L2_CROSS_DOMAIN_MESSENGER_ABI),
+            (predeploy(0x0f), "GasPriceOracle", &*OP_GAS_PRICE_ORACLE_ABI),
+            (predeploy(0x10), "L2StandardBridge", &*OP_L2_STANDARD_BRIDGE_ABI),
+            (predeploy(0x15), "L1Block", &*OP_L1_BLOCK_ABI),
+            (predeploy(0x16), "L2ToL1MessagePasser", &*OP_L2_TO_L1_MESSAGE_PASSER_ABI),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+static ARB_SYS_ABI: Lazy<Abi> = Lazy::new(|| {
+    parse_abi(&[
+        "function arbBlockNumber() external view returns (uint256)",
+        "function arbBlockHash(uint256 arbBlockNum) external view returns (bytes32)",
+        "function arbChainID() external view returns (uint256)",
+        "function arbOSVersion() external view returns (uint256)",
+        "function getStorageGasAvailable() external view returns (uint256)",
+        "function isTopLevelCall() external view returns (bool)",
+        "function mapL1SenderContractAddressToL2Alias(address sender, address unused) external pure returns (address)",
+        "function wasMyCallersAddressAliased() external view returns (bool)",
+        "function myCallersAddressWithoutAliasing() external view returns (address)",
+        "function sendTxToL1(address destination, bytes data) external payable returns (uint256)",
+        "function sendMerkleTreeState() external view returns (uint256 size, bytes32 root, bytes32[] partials)",
+        "function withdrawEth(address destination) external payable returns (uint256)",
+        "event L2ToL1Tx(address caller, address indexed destination, uint256 indexed hash, uint256 indexed position, uint256 arbBlockNum, uint256 ethBlockNum, uint256 timestamp, uint256 callvalue, bytes data)",
+    ])
+    .expect("invalid ArbSys ABI")
+});
+
+static ARB_GAS_INFO_ABI: Lazy<Abi> = Lazy::new(|| {
+    parse_abi(&[
+        "function getPricesInWei() external view returns (uint256, uint256, uint256, uint256, uint256, uint256)",
+        "function getPricesInArbGas() external view returns (uint256, uint256, uint256)",
+        "function getL1BaseFeeEstimate() external view returns (uint256)",
+        "function getCurrentTxL1GasFees() external view returns (uint256)",
+        "function getGasAccountingParams() external view returns (uint256, uint256, uint256)",
+    ])
+    .expect("invalid ArbGasInfo ABI")
+});
+
+static ARB_RETRYABLE_TX_ABI: Lazy<Abi> = Lazy::new(|| {
+    parse_abi(&[
+        "function redeem(bytes32 ticketId) external returns (bytes32)",
+        "function getLifetime() external view returns (uint256)",
+        "function getTimeout(bytes32 ticketId) external view returns (uint256)",
+        "function keepalive(bytes32 ticketId) external returns (uint256)",
+        "function getBeneficiary(bytes32 ticketId) external view returns (address)",
+        "function cancel(bytes32 ticketId) external",
+        "function getCurrentRedeemer() external view returns (address)",
+    ])
+    .expect("invalid ArbRetryableTx ABI")
+});
+
+static WETH_ABI: Lazy<Abi> = Lazy::new(|| {
+    parse_abi(&[
+        "function deposit() external payable",
+        "function withdraw(uint256 wad) external",
+        "function totalSupply() external view returns (uint256)",
+        "function balanceOf(address owner) external view returns (uint256)",
+        "function allowance(address owner, address spender) external view returns (uint256)",
+        "function approve(address spender, uint256 wad) external returns (bool)",
+        "function transfer(address to, uint256 wad) external returns (bool)",
+        "function transferFrom(address from, address to, uint256 wad) external returns (bool)",
+        "event Approval(address indexed src, address indexed guy, uint256 wad)",
+        "event Transfer(address indexed src, address indexed dst, uint256 wad)",
+        "event Deposit(address indexed dst, uint256 wad)",
+        "event Withdrawal(address indexed src, uint256 wad)",
+    ])
+    .expect("invalid WETH9 ABI")
+});
+
+static OP_LEGACY_MESSAGE_PASSER_ABI: Lazy<Abi> = Lazy::new(|| {
+    parse_abi(&[
+        "function passMessageToL1(bytes message) external",
+        "function sentMessages(bytes32 messageHash) external view returns (bool)",
+    ])
+    .expect("invalid LegacyMessagePasser ABI")
+});
+
+static OP_L2_CROSS_DOMAIN_MESSENGER_ABI: Lazy<Abi> = Lazy::new(|| {
+    parse_abi(&[
+        "function sendMessage(address target, bytes message, uint32 gasLimit) external",
+        "function relayMessage(address target, address sender, bytes message, uint256 messageNonce) external",
+        "function xDomainMessageSender() external view returns (address)",
+        "function messageNonce() external view returns (uint256)",
+        "event SentMessage(address indexed target, address sender, bytes message, uint256 messageNonce, uint256 gasLimit)",
+        "event RelayedMessage(bytes32 indexed msgHash)",
+        "event FailedRelayedMessage(bytes32 indexed msgHash)",
+    ])
+    .expect("invalid L2CrossDomainMessenger ABI")
+});
+
+static OP_GAS_PRICE_ORACLE_ABI: Lazy<Abi> = Lazy::new(|| {
+    parse_abi(&[
+        "function gasPrice() external view returns (uint256)",
+        "function baseFee() external view returns (uint256)",
+        "function l1BaseFee() external view returns (uint256)",
+        "function overhead() external view returns (uint256)",
+        "function scalar() external view returns (uint256)",
+        "function decimals() external view returns (uint256)",
+        "function getL1Fee(bytes data) external view returns (uint256)",
+        "function getL1GasUsed(bytes data) external view returns (uint256)",
+    ])
+    .expect("invalid GasPriceOracle ABI")
+});
+
+static OP_L2_STANDARD_BRIDGE_ABI: Lazy<Abi> = Lazy::new(|| {
+    parse_abi(&[
+        "function withdraw(address l2Token, uint256 amount, uint32 l1Gas, bytes data) external",
+        "function withdrawTo(address l2Token, address to, uint256 amount, uint32 l1Gas, bytes data) external",
+        "function finalizeDeposit(address l1Token, address l2Token, address from, address to, uint256 amount, bytes data) external",
+        "event WithdrawalInitiated(address indexed l1Token, address indexed l2Token, address indexed from, address to, uint256 amount, bytes data)",
+        "event DepositFinalized(address indexed l1Token, address indexed l2Token, address indexed from, address to, uint256 amount, bytes data)",
+        "event DepositFailed(address indexed l1Token, address indexed l2Token, address indexed from, address to, uint256 amount, bytes data)",
+    ])
+    .expect("invalid L2StandardBridge ABI")
+});
+
+static OP_L1_BLOCK_ABI: Lazy<Abi> = Lazy::new(|| {
+    parse_abi(&[
+        "function number() external view returns (uint64)",
+        "function timestamp() external view returns (uint64)",
+        "function basefee() external view returns (uint256)",
+        "function hash() external view returns (bytes32)",
+        "function sequenceNumber() external view returns (uint64)",
+        "function batcherHash() external view returns (bytes32)",
+        "function l1FeeOverhead() external view returns (uint256)",
+        "function l1FeeScalar() external view returns (uint256)",
+    ])
+    .expect("invalid L1Block ABI")
+});
+
+static OP_L2_TO_L1_MESSAGE_PASSER_ABI: Lazy<Abi> = Lazy::new(|| {
+    parse_abi(&[
+        "function initiateWithdrawal(address target, uint256 gasLimit, bytes data) external payable",
+        "function messageNonce() external view returns (uint256)",
+        "function sentMessages(bytes32 withdrawalHash) external view returns (bool)",
+        "event MessagePassed(uint256 indexed nonce, address indexed sender, address indexed target, uint256 value, uint256 gasLimit, bytes data, bytes32 withdrawalHash)",
+    ])
+    .expect("invalid L2ToL1MessagePasser ABI")
+});
+
+/// Returns the address `0x00..<number>`
+fn address(number: u64) -> Address {
+    Address::from_low_u64_be(number)
+}
+
+/// Returns the address of an Optimism predeploy, `0x4200..<number>`
+fn predeploy(number: u8) -> Address {
+    let mut address = Address::zero();
+    address.0[0] = 0x42;
+    address.0[19] = number;
+    address
+}
+
+fn precompile<I, O>(number: u8, name: impl ToString, inputs: I, outputs: O) -> (Address, Function)
+where
+    I: IntoIterator<Item = ParamType>,
+    O: IntoIterator<Item = ParamType>,
+{
+    (
+        Address::from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, number]),
+        #[allow(deprecated)]
+        Function {
+            name: name.to_string(),
+            inputs: inputs
+                .into_iter()
+                .map(|kind| Param { name: "".to_string(), kind, internal_type: None })
+                .collect(),
+            outputs: outputs
+                .into_iter()
+                .map(|kind| Param { name: "".to_string(), kind, internal_type: None })
+                .collect(),
+            constant: None,
+            state_mutability: ethers::abi::StateMutability::Pure,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_select_chain_contracts() {
+        // the ABIs are parsed lazily, so make sure all of them are valid
+        for chain_id in [1, 10, 42161] {
+            for (_, _, abi) in system_contracts(chain_id) {
+                assert!(abi.functions().count() > 0);
+            }
+        }
+        assert!(system_contracts(1).is_empty());
+
+        let arbitrum = system_contracts(42161);
+        assert_eq!(arbitrum[0].0, "0x0000000000000000000000000000000000000064".parse().unwrap());
+        let optimism = system_contracts(10);
+        let (address, name, _) = optimism[2];
+        assert_eq!(address, "0x4200000000000000000000000000000000000007".parse().unwrap());
+        assert_eq!(name, "L2CrossDomainMessenger");
+    }
+}
@@ -0,0 +1,549 @@
+use super::utils;
+use ansi_term::Colour;
+use ethers::{
+    abi::{param_type::Reader, Address, ParamType},
+    solc::artifacts::{Storage, StorageLayout},
+    types::{H256, U256},
+    utils::keccak256,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+/// The number of slots after the hash of a dynamic array's base slot that are considered to hold
+/// its elements, since the length of the array is not known
+const MAX_ARRAY_SLOTS: u64 = 1 << 16;
+
+/// A storage slot written in a call
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageChange {
+    /// The written slot
+    pub slot: U256,
+    /// The value of the slot before the write
+    pub previous: U256,
+    /// The value written to the slot
+    pub value: U256,
+    /// The variables stored in the slot, if it could be decoded.
+    ///
+    /// The elements of the tuples are the name of the variable, e.g. `balances[0x..]`, its
+    /// previous value and its new value.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub decoded: Vec<(String, String, String)>,
+}
+
+impl fmt::Display for StorageChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.decoded.is_empty() {
+            return write!(
+                f,
+                "{} @ 0x{:x}: 0x{:x} → 0x{:x}",
+                Colour::Purple.paint("storage"),
+                self.slot,
+                self.previous,
+                self.value
+            )
+        }
+
+        let lines = self
+            .decoded
+            .iter()
+            .map(|(name, previous, value)| {
+                format!("{} {}: {} → {}", Colour::Purple.paint("storage"), name, previous, value)
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// A variable stored in a slot
+struct Variable {
+    /// The name of the variable, including the keys and indices it is stored under
+    name: String,
+    /// The label of its type, e.g. `uint256` or `contract IERC20`
+    kind: String,
+    /// The offset of the variable in the slot in bytes, from the right
+    offset: usize,
+    /// The size of the variable in bytes
+    size: usize,
+}
+
+/// Decodes written storage slots to the variables stored in them.
+///
+/// Slots of mappings and dynamic arrays are derived from keccak256 hashes, so they are decoded
+/// using the preimages of the hashes that were computed during execution.
+pub(crate) struct StorageDecoder<'a> {
+    layout: Option<&'a StorageLayout>,
+    preimages: &'a BTreeMap<H256, Vec<u8>>,
+    labels: &'a HashMap<Address, String>,
+}
+
+impl<'a> StorageDecoder<'a> {
+    pub(crate) fn new(
+        layout: Option<&'a StorageLayout>,
+        preimages: &'a BTreeMap<H256, Vec<u8>>,
+        labels: &'a HashMap<Address, String>,
+    ) -> Self {
+        Self { layout, preimages, labels }
+    }
+
+    /// Decodes the variables of the written slot.
+    ///
+    /// Without a storage layout, or if the layout does not know the slot, only the mapping keys
+    /// and array indices the slot is derived from are decoded.
+    pub(crate) fn decode(&self, change: &StorageChange) -> Vec<(String, String, String)> {
+        let mut variables = Vec::new();
+        if let Some(layout) = self.layout {
+            for storage in &layout.storage {
+                if let Ok(slot) = U256::from_dec_str(&storage.slot) {
+                    self.find(
+                        change.slot,
+                        slot,
+                        storage.label.clone(),
+                        &storage.storage_type,
+                        storage.offset as usize,
+                        &mut variables,
+                    );
+                }
+            }
+        }
+
+        if variables.is_empty() {
+            return self
+                .derived_slot_name(change.slot)
+                .map(|name| {
+                    vec![(
+                        name,
+                        format!("0x{:x}", change.previous),
+                        format!("0x{:x}", change.value),
+                    )]
+                })
+                .unwrap_or_default()
+        }
+
+        let decoded = variables
+            .into_iter()
+            .map(|variable| {
+                let previous = self.format_value(change.previous, &variable);
+                let value = self.format_value(change.value, &variable);
+                (variable.name, previous, value)
+            })
+            .collect::<Vec<_>>();
+        // Only show the variables of a packed slot that were changed, unless none were
+        if decoded.iter().any(|(_, previous, value)| previous != value) {
+            decoded.into_iter().filter(|(_, previous, value)| previous != value).collect()
+        } else {
+            decoded
+        }
+    }
+
+    /// Finds the variables stored in `slot` that are part of the variable `name` of type `ty`,
+    /// which is stored at `base` and `offset`
+    fn find(
+        &self,
+        slot: U256,
+        base: U256,
+        name: String,
+        ty: &str,
+        offset: usize,
+        variables: &mut Vec<Variable>,
+    ) {
+        let layout = match self.layout {
+            Some(layout) => layout,
+            None => return,
+        };
+        let storage_type = match layout.types.get(ty) {
+            Some(storage_type) => storage_type,
+            None => return,
+        };
+
+        match storage_type.encoding.as_str() {
+            "inplace" => {
+                let size = self.size_of(ty);
+                let slots = U256::from((size + 31) / 32).max(U256::one());
+                if slot < base || slot - base >= slots {
+                    return
+                }
+
+                if let Some(members) = storage_type.other.get("members").and_then(|members| {
+                    serde_json::from_value::<Vec<Storage>>(members.clone()).ok()
+                }) {
+                    // A struct
+                    for member in members {
+                        if let Ok(member_slot) = U256::from_dec_str(&member.slot) {
+                            self.find(
+                                slot,
+                                base + member_slot,
+                                format!("{name}.{}", member.label),
+                                &member.storage_type,
+                                member.offset as usize,
+                                variables,
+                            );
+                        }
+                    }
+                } else if let Some(element) =
+                    storage_type.other.get("base").and_then(|base| base.as_str())
+                {
+                    // A static array
+                    self.find_element(slot, base, &name, element, variables);
+                } else {
+                    variables.push(Variable {
+                        name,
+                        kind: storage_type.label.clone(),
+                        offset,
+                        size: size.min(32),
+                    });
+                }
+            }
+            "mapping" => {
+                let (key, value) = match (&storage_type.key, &storage_type.value) {
+                    (Some(key), Some(value)) => (key, value),
+                    _ => return,
+                };
+                let base_word = H256::from_uint(&base);
+                for (hash, preimage) in self.preimages.iter().filter(|(_, preimage)| {
+                    preimage.len() > 32 && &preimage[preimage.len() - 32..] == base_word.as_bytes()
+                }) {
+                    let key = self.format_key(&preimage[..preimage.len() - 32], key);
+                    self.find(
+                        slot,
+                        hash.into_uint(),
+                        format!("{name}[{key}]"),
+                        value,
+                        0,
+                        variables,
+                    );
+                }
+            }
+            "dynamic_array" => {
+                if slot == base {
+                    variables.push(Variable {
+                        name: format!("{name}.length"),
+                        kind: "uint256".to_string(),
+                        offset: 0,
+                        size: 32,
+                    });
+                } else if let Some(element) =
+                    storage_type.other.get("base").and_then(|base| base.as_str())
+                {
+                    let data = U256::from(keccak256(H256::from_uint(&base)));
+                    if slot >= data && slot - data < U256::from(MAX_ARRAY_SLOTS) {
+                        self.find_element(slot, data, &name, element, variables);
+                    }
+                }
+            }
+            "bytes" if slot == base => variables.push(Variable {
+                name,
+                kind: storage_type.label.clone(),
+                offset: 0,
+                size: 32,
+            }),
+            _ => {}
+        }
+    }
+
+    /// Finds the element of the array `name` stored in `slot`, whose elements start at `start`
+    fn find_element(
+        &self,
+        slot: U256,
+        start: U256,
+        name: &str,
+        element: &str,
+        variables: &mut Vec<Variable>,
+    ) {
+        let size = self.size_of(element);
+        if size >= 32 {
+            let slots = U256::from((size + 31) / 32);
+            let index = (slot - start) / slots;
+            self.find(
+                slot,
+                start + index * slots,
+                format!("{name}[{index}]"),
+                element,
+                0,
+                variables,
+            );
+        } else {
+            // Elements smaller than a slot are packed
+            let per_slot = 32 / size.max(1);
+            let first = (slot - start) * per_slot;
+            for i in 0..per_slot {
+                self.find(
+                    slot,
+                    slot,
+                    format!("{name}[{}]", first + i),
+                    element,
+                    i * size,
+                    variables,
+                );
+            }
+        }
+    }
+
+    /// Returns the size of a type in bytes
+    fn size_of(&self, ty: &str) -> usize {
+        self.layout
+            .and_then(|layout| layout.types.get(ty))
+            .and_then(|storage_type| storage_type.number_of_bytes.parse().ok())
+            .unwrap_or(32)
+    }
+
+    /// Returns the number of slots the values of the mapping stored at `base` span, which is a
+    /// single slot unless the storage layout knows the mapping
+    fn mapping_value_slots(&self, base: U256) -> U256 {
+        self.layout
+            .and_then(|layout| {
+                let storage = layout
+                    .storage
+                    .iter()
+                    .find(|storage| U256::from_dec_str(&storage.slot).ok() == Some(base))?;
+                layout.types.get(&storage.storage_type)?.value.as_ref()
+            })
+            .map_or(U256::one(), |value| {
+                U256::from((self.size_of(value) + 31) / 32).max(U256::one())
+            })
+    }
+
+    /// Names a slot that is derived from a hash by the slot and keys or indices it is derived
+    /// from, e.g. `3[0x..]` for the value of key `0x..` of the mapping in slot 3
+    fn derived_slot_name(&self, slot: U256) -> Option<String> {
+        let (hash, preimage) = self.preimages.range(..=H256::from_uint(&slot)).next_back()?;
+        let offset = slot - hash.into_uint();
+        let name = match preimage.len() {
+            // The elements of a dynamic array
+            32 if offset < U256::from(MAX_ARRAY_SLOTS) => {
+                format!("{}[{offset}]", self.slot_name(U256::from(&preimage[..])))
+            }
+            len if len > 32 => {
+                let base = U256::from(&preimage[len - 32..]);
+                if offset >= self.mapping_value_slots(base) {
+                    return None
+                }
+                let name =
+                    format!("{}[0x{}]", self.slot_name(base), hex::encode(&preimage[..len - 32]));
+                if offset.is_zero() {
+                    name
+                } else {
+                    format!("{name} + {offset}")
+                }
+            }
+            _ => return None,
+        };
+        Some(name)
+    }
+
+    fn slot_name(&self, slot: U256) -> String {
+        self.derived_slot_name(slot).unwrap_or_else(|| slot.to_string())
+    }
+
+    /// Formats a mapping key of the given type
+    fn format_key(&self, key: &[u8], ty: &str) -> String {
+        let label = self
+            .layout
+            .and_then(|layout| layout.types.get(ty))
+            .map_or("", |storage_type| storage_type.label.as_str());
+        match label {
+            "string" => format!("{:?}", String::from_utf8_lossy(key)),
+            _ => match self.param_type(label, key.len()) {
+                Some(kind) if key.len() == 32 => ethers::abi::decode(&[kind], key)
+                    .ok()
+                    .and_then(|tokens| tokens.into_iter().next())
+                    .map(|token| utils::label(&token, self.labels))
+                    .unwrap_or_else(|| format!("0x{}", hex::encode(key))),
+                _ => format!("0x{}", hex::encode(key)),
+            },
+        }
+    }
+
+    /// Formats the value of the variable in the slot
+    fn format_value(&self, slot_value: U256, variable: &Variable) -> String {
+        let mut slot = [0u8; 32];
+        slot_value.to_big_endian(&mut slot);
+        if variable.offset + variable.size > 32 {
+            return format!("0x{slot_value:x}")
+        }
+        let bytes = &slot[32 - variable.offset - variable.size..32 - variable.offset];
+
+        if variable.kind == "string" || variable.kind == "bytes" {
+            // Short strings are stored in place, with twice their length in the lowest byte
+            let len = bytes[31] as usize;
+            return if len % 2 == 0 && len / 2 < 32 {
+                let data = &bytes[..len / 2];
+                if variable.kind == "string" {
+                    format!("{:?}", String::from_utf8_lossy(data))
+                } else {
+                    format!("0x{}", hex::encode(data))
+                }
+            } else {
+                format!("<{} bytes>", (slot_value - 1) / 2)
+            }
+        }
+
+        // Pad the value to a word as in the ABI encoding to decode it
+        let kind = match self.param_type(&variable.kind, variable.size) {
+            Some(kind) => kind,
+            None => return format!("0x{}", hex::encode(bytes)),
+        };
+        let mut word = [0u8; 32];
+        match kind {
+            ParamType::FixedBytes(_) => word[..bytes.len()].copy_from_slice(bytes),
+            ParamType::Int(_) => {
+                if bytes[0] & 0x80 != 0 {
+                    word = [0xff; 32];
+                }
+                word[32 - bytes.len()..].copy_from_slice(bytes);
+            }
+            _ => word[32 - bytes.len()..].copy_from_slice(bytes),
+        }
+        ethers::abi::decode(&[kind], &word)
+            .ok()
+            .and_then(|tokens| tokens.into_iter().next())
+            .map(|token| utils::label(&token, self.labels))
+            .unwrap_or_else(|| format!("0x{}", hex::encode(bytes)))
+    }
+
+    /// Returns the ABI type of a value type of the storage layout
+    fn param_type(&self, label: &str, size: usize) -> Option<ParamType> {
+        if label.starts_with("contract ") || label == "address payable" {
+            Some(ParamType::Address)
+        } else if label.starts_with("enum ") {
+            Some(ParamType::Uint(size * 8))
+        } else {
+            match Reader::read(label).ok()? {
+                kind @ (ParamType::Address |
+                ParamType::Bool |
+                ParamType::Uint(_) |
+                ParamType::Int(_) |
+                ParamType::FixedBytes(_)) => Some(kind),
+                _ => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The storage layout of
+    ///
+    /// ```solidity
+    /// contract Token {
+    ///     uint128 totalSupply;
+    ///     bool paused;
+    ///     mapping(address => uint256) balances;
+    ///     address[] holders;
+    /// }
+    /// ```
+    fn layout() -> StorageLayout {
+        serde_json::from_str(
+            r#"{
+                "storage": [
+                    {"astId": 3, "contract": "src/Token.sol:Token", "label": "totalSupply", "offset": 0, "slot": "0", "type": "t_uint128"},
+                    {"astId": 5, "contract": "src/Token.sol:Token", "label": "paused", "offset": 16, "slot": "0", "type": "t_bool"},
+                    {"astId": 9, "contract": "src/Token.sol:Token", "label": "balances", "offset": 0, "slot": "1", "type": "t_mapping(t_address,t_uint256)"},
+                    {"astId": 12, "contract": "src/Token.sol:Token", "label": "holders", "offset": 0, "slot": "2", "type": "t_array(t_address)dyn_storage"}
+                ],
+                "types": {
+                    "t_address": {"encoding": "inplace", "label": "address", "numberOfBytes": "20"},
+                    "t_array(t_address)dyn_storage": {"base": "t_address", "encoding": "dynamic_array", "label": "address[]", "numberOfBytes": "32"},
+                    "t_bool": {"encoding": "inplace", "label": "bool", "numberOfBytes": "1"},
+                    "t_mapping(t_address,t_uint256)": {"encoding": "mapping", "key": "t_address", "label": "mapping(address => uint256)", "numberOfBytes": "32", "value": "t_uint256"},
+                    "t_uint128": {"encoding": "inplace", "label": "uint128", "numberOfBytes": "16"},
+                    "t_uint256": {"encoding": "inplace", "label": "uint256", "numberOfBytes": "32"}
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn change(slot: U256, previous: U256, value: U256) -> StorageChange {
+        StorageChange { slot, previous, value, decoded: Vec::new() }
+    }
+
+    #[test]
+    fn can_decode_storage_changes() {
+        let layout = layout();
+        let holder = Address::from_low_u64_be(0xbeef);
+
+        // balances[holder] is stored at keccak256(holder . 1)
+        let mut preimage = H256::from(holder).as_bytes().to_vec();
+        preimage.extend(H256::from_low_u64_be(1).as_bytes());
+        let preimages: BTreeMap<H256, Vec<u8>> =
+            [(H256::from(keccak256(&preimage)), preimage.clone())].into_iter().collect();
+        let labels = [(holder, "alice".to_string())].into_iter().collect();
+        let decoder = StorageDecoder::new(Some(&layout), &preimages, &labels);
+
+        // only the packed variables that changed are decoded
+        let paused = U256::one() << 128;
+        assert_eq!(
+            decoder.decode(&change(U256::zero(), 100.into(), paused + 100)),
+            vec![("paused".to_string(), "false".to_string(), "true".to_string())]
+        );
+
+        let balance = U256::from(keccak256(&preimage));
+        assert_eq!(
+            decoder.decode(&change(balance, 0.into(), 5.into())),
+            vec![(format!("balances[alice: [{holder:?}]]"), "0".to_string(), "5".to_string())]
+        );
+
+        let holders = U256::from(keccak256(H256::from_low_u64_be(2)));
+        assert_eq!(
+            decoder.decode(&change(holders + 1, 0.into(), U256::from(holder.as_bytes()))),
+            vec![(
+                "holders[1]".to_string(),
+                format!("{:?}", Address::zero()),
+                format!("alice: [{holder:?}]")
+            )]
+        );
+        assert_eq!(decoder.decode(&change(2.into(), 1.into(), 2.into()))[0].0, "holders.length");
+
+        // without a layout, only the keys of the slot are decoded
+        let decoder = StorageDecoder::new(None, &preimages, &labels);
+        assert_eq!(
+            decoder.decode(&change(balance, 0.into(), 5.into())),
+            vec![(
+                format!("1[0x{}]", hex::encode(H256::from(holder))),
+                "0x0".to_string(),
+                "0x5".to_string()
+            )]
+        );
+        assert!(decoder.decode(&change(U256::zero(), 0.into(), 1.into())).is_empty());
+    }
+
+    #[test]
+    fn ignores_slots_near_unrelated_hashes() {
+        let layout = layout();
+        let holder = Address::from_low_u64_be(0xbeef);
+
+        let mut preimage = H256::from(holder).as_bytes().to_vec();
+        preimage.extend(H256::from_low_u64_be(1).as_bytes());
+        let word = H256::from_low_u64_be(0x1234).as_bytes().to_vec();
+        let preimages: BTreeMap<H256, Vec<u8>> = [
+            (H256::from(keccak256(&preimage)), preimage.clone()),
+            (H256::from(keccak256(&word)), word.clone()),
+        ]
+        .into_iter()
+        .collect();
+        let labels = HashMap::new();
+
+        // balances values span a single slot, and unrelated hashes are far from array elements
+        let balance = U256::from(keccak256(&preimage));
+        let unrelated = U256::from(keccak256(&word)) + MAX_ARRAY_SLOTS;
+        for decoder in [
+            StorageDecoder::new(Some(&layout), &preimages, &labels),
+            StorageDecoder::new(None, &preimages, &labels),
+        ] {
+            assert!(decoder.decode(&change(balance + 1, 0.into(), 5.into())).is_empty());
+            assert!(decoder.decode(&change(unrelated, 0.into(), 5.into())).is_empty());
+        }
+
+        // slots close to a 32 byte preimage are still named as array elements
+        let decoder = StorageDecoder::new(None, &preimages, &labels);
+        assert_eq!(
+            decoder.decode(&change(U256::from(keccak256(&word)) + 3, 0.into(), 5.into()))[0].0,
+            "4660[3]"
+        );
+    }
+}
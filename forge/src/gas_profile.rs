@@ -0,0 +1,241 @@
+use crate::trace::{CallTrace, CallTraceArena, RawOrDecodedCall, TraceKind};
+use ethers::utils::keccak256;
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+/// The width of a rendered flamegraph in pixels
+const SVG_WIDTH: f64 = 1200.0;
+/// The height of a single frame of a rendered flamegraph in pixels
+const FRAME_HEIGHT: usize = 16;
+/// The approximate width of a character of a frame label in pixels
+const CHAR_WIDTH: f64 = 7.0;
+
+/// A gas profile of traces that keeps the call hierarchy.
+///
+/// Every call is recorded as a stack of the calls leading up to it, along with the gas spent in
+/// the call itself, i.e. excluding its subcalls. The profile is output in the folded stack format
+/// (`Contract::fn;Callee::fn gas`) understood by flamegraph tools, or rendered as an SVG.
+#[derive(Debug, Default)]
+pub struct GasProfile {
+    /// The gas spent in each call stack, keyed by the folded stack
+    pub stacks: BTreeMap<String, u64>,
+}
+
+impl GasProfile {
+    /// Adds the calls of the traces to the profile, skipping deployments of test contracts
+    pub fn analyze(&mut self, traces: &[(TraceKind, CallTraceArena)]) {
+        traces.iter().filter(|(kind, _)| *kind != TraceKind::Deployment).for_each(|(_, arena)| {
+            self.analyze_node(arena, 0, "");
+        });
+    }
+
+    fn analyze_node(&mut self, arena: &CallTraceArena, idx: usize, parent: &str) {
+        let node = &arena.arena[idx];
+        let frame = frame_name(&node.trace);
+        let stack = if parent.is_empty() { frame } else { format!("{parent};{frame}") };
+
+        let children_gas =
+            node.children.iter().map(|child| arena.arena[*child].trace.gas_cost).sum::<u64>();
+        let self_gas = node.trace.gas_cost.saturating_sub(children_gas);
+        if self_gas > 0 {
+            *self.stacks.entry(stack.clone()).or_default() += self_gas;
+        }
+
+        for child in &node.children {
+            self.analyze_node(arena, *child, &stack);
+        }
+    }
+
+    /// Returns the profile in the folded stack format, one stack per line
+    pub fn folded(&self) -> String {
+        self.stacks.iter().fold(String::new(), |mut folded, (stack, gas)| {
+            let _ = writeln!(folded, "{stack} {gas}");
+            folded
+        })
+    }
+
+    /// Renders the profile as a flamegraph, with the outermost calls at the bottom
+    pub fn svg(&self, title: &str) -> String {
+        let root = Frame::from_stacks(&self.stacks);
+        let depth = root.depth();
+        let height = (depth + 2) * FRAME_HEIGHT;
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SVG_WIDTH}" height="{height}" font-family="monospace" font-size="12">"#
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle" font-size="14">{}</text>"#,
+            SVG_WIDTH / 2.0,
+            FRAME_HEIGHT - 2,
+            escape(title)
+        );
+        if root.gas > 0 {
+            let scale = SVG_WIDTH / root.gas as f64;
+            let mut x = 0.0;
+            for (name, frame) in &root.children {
+                frame.render(&mut svg, name, x, 0, height, scale);
+                x += frame.gas as f64 * scale;
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Writes the profile to a file, as an SVG if the file has the `svg` extension and in the
+    /// folded stack format otherwise
+    pub fn write(&self, path: impl AsRef<Path>, title: &str) -> eyre::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = if path.extension().map_or(false, |ext| ext == "svg") {
+            self.svg(title)
+        } else {
+            self.folded()
+        };
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// A call in the tree of calls that the folded stacks describe
+#[derive(Debug, Default)]
+struct Frame {
+    /// The gas spent in the call, including its subcalls
+    gas: u64,
+    children: BTreeMap<String, Frame>,
+}
+
+impl Frame {
+    fn from_stacks(stacks: &BTreeMap<String, u64>) -> Self {
+        let mut root = Frame::default();
+        for (stack, gas) in stacks {
+            let mut frame = &mut root;
+            frame.gas += gas;
+            for name in stack.split(';') {
+                frame = frame.children.entry(name.to_string()).or_default();
+                frame.gas += gas;
+            }
+        }
+        root
+    }
+
+    fn depth(&self) -> usize {
+        self.children.values().map(|child| child.depth() + 1).max().unwrap_or(0)
+    }
+
+    fn render(
+        &self,
+        svg: &mut String,
+        name: &str,
+        x: f64,
+        level: usize,
+        height: usize,
+        scale: f64,
+    ) {
+        let width = self.gas as f64 * scale;
+        let y = height - (level + 1) * FRAME_HEIGHT;
+
+        // a stable color per frame, in the red to yellow range of flamegraphs
+        let hash = keccak256(name.as_bytes());
+        let (green, blue) = (100 + hash[0] as usize % 130, hash[1] as usize % 60);
+        let label = truncate(name, (width / CHAR_WIDTH) as usize);
+
+        let _ = writeln!(
+            svg,
+            r#"<g><title>{} ({} gas)</title><rect x="{x:.2}" y="{y}" width="{width:.2}" height="{}" fill="rgb(230,{green},{blue})" stroke="white" stroke-width="0.5"/><text x="{:.2}" y="{}">{}</text></g>"#,
+            escape(name),
+            self.gas,
+            FRAME_HEIGHT - 1,
+            x + 3.0,
+            y + FRAME_HEIGHT - 4,
+            escape(&label)
+        );
+
+        let mut child_x = x;
+        for (name, child) in &self.children {
+            child.render(svg, name, child_x, level + 1, height, scale);
+            child_x += child.gas as f64 * scale;
+        }
+    }
+}
+
+/// Returns the name of a call in a folded stack, `<contract>::<function>` or `new <contract>` for
+/// contract creations
+fn frame_name(trace: &CallTrace) -> String {
+    let contract = trace
+        .label
+        .clone()
+        .or_else(|| {
+            trace.contract.as_ref().map(|id| id.rsplit(':').next().unwrap_or(id).to_string())
+        })
+        .unwrap_or_else(|| format!("{:?}", trace.address));
+    let name = if trace.created() {
+        format!("new {contract}")
+    } else {
+        match &trace.data {
+            RawOrDecodedCall::Decoded(function, _) => format!("{contract}::{function}"),
+            RawOrDecodedCall::Raw(bytes) if bytes.len() >= 4 => {
+                format!("{contract}::0x{}", hex::encode(&bytes[..4]))
+            }
+            RawOrDecodedCall::Raw(_) => format!("{contract}::fallback"),
+        }
+    };
+    // `;` separates the frames of a folded stack
+    name.replace(';', ":")
+}
+
+/// Truncates a frame label to fit in the given number of characters
+fn truncate(name: &str, chars: usize) -> String {
+    if name.chars().count() <= chars {
+        name.to_string()
+    } else if chars > 2 {
+        format!("{}..", name.chars().take(chars - 2).collect::<String>())
+    } else {
+        String::new()
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_fold_stacks() {
+        let mut arena = CallTraceArena::default();
+        let call = |depth: usize, label: &str, function: &str, gas_cost: u64| CallTrace {
+            depth,
+            label: Some(label.to_string()),
+            data: RawOrDecodedCall::Decoded(function.to_string(), vec![]),
+            gas_cost,
+            ..Default::default()
+        };
+        arena.arena[0].trace = call(0, "CounterTest", "testIncrement", 1000);
+        arena.push_trace(0, call(1, "Counter", "increment", 400));
+        arena.push_trace(1, call(2, "Math", "add", 100));
+        arena.push_trace(0, call(1, "Counter", "number", 200));
+
+        let mut profile = GasProfile::default();
+        profile.analyze(&[(TraceKind::Execution, arena.clone())]);
+        profile.analyze(&[(TraceKind::Deployment, arena)]);
+
+        assert_eq!(
+            profile.folded(),
+            "CounterTest::testIncrement 400\n\
+             CounterTest::testIncrement;Counter::increment 300\n\
+             CounterTest::testIncrement;Counter::increment;Math::add 100\n\
+             CounterTest::testIncrement;Counter::number 200\n"
+        );
+
+        let svg = profile.svg("testIncrement()");
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("<title>Counter::increment (400 gas)</title>"));
+        assert!(svg.contains("<title>CounterTest::testIncrement (1000 gas)</title>"));
+    }
+}
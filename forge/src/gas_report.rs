@@ -1,16 +1,24 @@
 use crate::{
     executor::{CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS},
-    trace::{CallTraceArena, RawOrDecodedCall, TraceKind},
+    trace::{CallTraceArena, RawOrDecodedCall, RawOrDecodedReturnData, TraceKind},
+    CallKind,
 };
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
-use ethers::types::U256;
+use ethers::types::{Address, U256};
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, fmt::Display};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{Display, Write},
+    path::Path,
+};
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct GasReport {
     pub report_for: Vec<String>,
     pub contracts: BTreeMap<String, ContractInfo>,
+    /// Whether to break down the gas of every function by the test contracts that called it
+    #[serde(skip)]
+    pub breakdown: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -27,6 +35,34 @@ pub struct GasInfo {
     pub mean: U256,
     pub median: U256,
     pub max: U256,
+    /// The gas of the calls made by each test contract, by the identifier of its artifact
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub by_test: BTreeMap<String, GasInfo>,
+}
+
+impl GasInfo {
+    fn push(&mut self, test_contract: &str, gas: U256) {
+        self.calls.push(gas);
+        self.by_test.entry(test_contract.to_string()).or_default().calls.push(gas);
+    }
+
+    fn finalize(&mut self) {
+        self.calls.sort();
+        self.min = self.calls.first().cloned().unwrap_or_default();
+        self.max = self.calls.last().cloned().unwrap_or_default();
+
+        let len = self.calls.len();
+        if len > 0 {
+            self.mean = self.calls.iter().fold(U256::zero(), |acc, x| acc + x) / len;
+            self.median = if len % 2 == 0 {
+                (self.calls[len / 2 - 1] + self.calls[len / 2]) / 2
+            } else {
+                self.calls[len / 2]
+            };
+        }
+
+        self.by_test.values_mut().for_each(GasInfo::finalize);
+    }
 }
 
 impl GasReport {
@@ -34,18 +70,44 @@ impl GasReport {
         Self { report_for, ..Default::default() }
     }
 
-    pub fn analyze(&mut self, traces: &[(TraceKind, CallTraceArena)]) {
+    /// Breaks down the gas of every function by the test contracts that called it
+    #[must_use]
+    pub fn with_breakdown(mut self, breakdown: bool) -> Self {
+        self.breakdown = breakdown;
+        self
+    }
+
+    /// Adds the calls of the traces of a test of the given test contract to the report.
+    ///
+    /// The test contract is the contract at the root of the setup and execution traces, since the
+    /// test runner calls into it, and of its own deployment trace. Calls to it, including its
+    /// deployment, are not reported. The libraries the test contract is linked against are
+    /// deployed in deployment traces of their own, and are reported.
+    pub fn analyze(&mut self, test_contract: &str, traces: &[(TraceKind, CallTraceArena)]) {
         let report_for_all = self.report_for.is_empty() || self.report_for.iter().any(|s| s == "*");
+        let test_name = test_contract.rsplit(':').next().unwrap_or(test_contract);
+        let test_addresses = traces
+            .iter()
+            .map(|(kind, trace)| (kind, &trace.arena[0].trace))
+            .filter(|(kind, root)| {
+                !matches!(kind, TraceKind::Deployment) ||
+                    root.contract.as_deref() == Some(test_name)
+            })
+            .map(|(_, root)| root.address)
+            .collect::<HashSet<_>>();
         traces.iter().for_each(|(_, trace)| {
-            self.analyze_trace(trace, report_for_all);
+            self.analyze_node(0, trace, test_contract, &test_addresses, report_for_all);
         });
     }
 
-    fn analyze_trace(&mut self, trace: &CallTraceArena, report_for_all: bool) {
-        self.analyze_node(0, trace, report_for_all);
-    }
-
-    fn analyze_node(&mut self, node_index: usize, arena: &CallTraceArena, report_for_all: bool) {
+    fn analyze_node(
+        &mut self,
+        node_index: usize,
+        arena: &CallTraceArena,
+        test_contract: &str,
+        test_addresses: &HashSet<Address>,
+        report_for_all: bool,
+    ) {
         let node = &arena.arena[node_index];
         let trace = &node.trace;
 
@@ -53,63 +115,277 @@ impl GasReport {
             return
         }
 
-        if let Some(name) = &trace.contract {
+        // A proxy that forwards the call to its implementation: the call is attributed to the
+        // implementation, including the overhead of the proxy
+        let implementation = node.children.iter().copied().find(|child| {
+            let child = &arena.arena[*child].trace;
+            child.kind == CallKind::DelegateCall && same_calldata(&child.data, &trace.data)
+        });
+        let contract = match implementation {
+            Some(idx) => &arena.arena[idx].trace.contract,
+            None => &trace.contract,
+        };
+
+        if let Some(name) = contract {
             let report_for = self.report_for.iter().any(|s| s == name);
-            if report_for || report_for_all {
+            if (report_for || report_for_all) && !test_addresses.contains(&trace.address) {
                 let mut contract_report =
                     self.contracts.entry(name.to_string()).or_insert_with(Default::default);
 
-                match &trace.data {
-                    RawOrDecodedCall::Raw(bytes) if trace.created() => {
+                match (&trace.data, &trace.output) {
+                    // the init code includes the constructor arguments, so the size of the
+                    // deployed runtime code is reported instead
+                    (_, RawOrDecodedReturnData::Raw(code)) if trace.created() => {
                         contract_report.gas = trace.gas_cost.into();
-                        contract_report.size = bytes.len().into();
+                        contract_report.size = code.len().into();
                     }
-                    // TODO: More robust test contract filtering
-                    RawOrDecodedCall::Decoded(func, _)
-                        if !func.starts_with("test") && func != "setUp" =>
-                    {
-                        let function_report = contract_report
+                    (RawOrDecodedCall::Decoded(func, _), _) => {
+                        contract_report
                             .functions
                             .entry(func.clone())
-                            .or_insert_with(Default::default);
-                        function_report.calls.push(trace.gas_cost.into());
+                            .or_insert_with(Default::default)
+                            .push(test_contract, trace.gas_cost.into());
                     }
                     _ => (),
                 }
             }
         }
 
-        node.children.iter().for_each(|index| {
-            self.analyze_node(*index, arena, report_for_all);
+        // the calls of the implementation are made on behalf of the proxy call
+        let children = node.children.iter().flat_map(|child| match implementation {
+            Some(idx) if idx == *child => arena.arena[idx].children.as_slice(),
+            _ => std::slice::from_ref(child),
+        });
+        children.for_each(|index| {
+            self.analyze_node(*index, arena, test_contract, test_addresses, report_for_all);
         });
     }
 
     #[must_use]
     pub fn finalize(mut self) -> Self {
         self.contracts.iter_mut().for_each(|(_, contract)| {
-            contract.functions.iter_mut().for_each(|(_, func)| {
-                func.calls.sort();
-                func.min = func.calls.first().cloned().unwrap_or_default();
-                func.max = func.calls.last().cloned().unwrap_or_default();
-                func.mean =
-                    func.calls.iter().fold(U256::zero(), |acc, x| acc + x) / func.calls.len();
-
-                let len = func.calls.len();
-                func.median = if len > 0 {
-                    if len % 2 == 0 {
-                        (func.calls[len / 2 - 1] + func.calls[len / 2]) / 2
-                    } else {
-                        func.calls[len / 2]
-                    }
-                } else {
-                    0.into()
-                };
-            });
+            contract.functions.values_mut().for_each(GasInfo::finalize);
         });
         self
     }
 }
 
+/// Returns whether a delegatecall forwards the calldata of its caller
+fn same_calldata(a: &RawOrDecodedCall, b: &RawOrDecodedCall) -> bool {
+    match (a, b) {
+        (RawOrDecodedCall::Raw(a), RawOrDecodedCall::Raw(b)) => a == b,
+        (
+            RawOrDecodedCall::Decoded(a_func, a_inputs),
+            RawOrDecodedCall::Decoded(b_func, b_inputs),
+        ) => a_func == b_func && a_inputs == b_inputs,
+        _ => false,
+    }
+}
+
+impl GasReport {
+    /// Reads a gas report that was written as JSON, e.g. to diff against it
+    pub fn read(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Writes the report to a file, as Markdown if the file has the `md` extension and as JSON
+    /// otherwise.
+    ///
+    /// The changes relative to a baseline are appended to Markdown reports.
+    pub fn write(&self, path: impl AsRef<Path>, diff: Option<&GasReportDiff>) -> eyre::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = if path.extension().map_or(false, |ext| ext == "md") {
+            let mut markdown = self.to_markdown();
+            if let Some(diff) = diff {
+                markdown.push('\n');
+                markdown.push_str(&diff.to_markdown());
+            }
+            markdown
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Returns the report as Markdown tables, one per contract
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        for (name, contract) in self.contracts.iter() {
+            if contract.functions.is_empty() {
+                continue
+            }
+
+            let _ = writeln!(markdown, "### {name}\n");
+            let _ = writeln!(markdown, "| Deployment Cost | Deployment Size |");
+            let _ = writeln!(markdown, "|---|---|");
+            let _ = writeln!(markdown, "| {} | {} |\n", contract.gas, contract.size);
+            let _ = writeln!(markdown, "| Function Name | min | avg | median | max | # calls |");
+            let _ = writeln!(markdown, "|---|---|---|---|---|---|");
+            for (fname, function) in contract.functions.iter() {
+                let _ = writeln!(
+                    markdown,
+                    "| {} | {} | {} | {} | {} | {} |",
+                    fname,
+                    function.min,
+                    function.mean,
+                    function.median,
+                    function.max,
+                    function.calls.len()
+                );
+                if self.breakdown {
+                    for (test_contract, test) in function.by_test.iter() {
+                        let _ = writeln!(
+                            markdown,
+                            "| ↳ {} | {} | {} | {} | {} | {} |",
+                            test_contract,
+                            test.min,
+                            test.mean,
+                            test.median,
+                            test.max,
+                            test.calls.len()
+                        );
+                    }
+                }
+            }
+            markdown.push('\n');
+        }
+        markdown
+    }
+
+    /// Compares the deployment costs and the min, mean, median and max gas of every function to
+    /// those of a baseline report.
+    ///
+    /// Only contracts and functions that are part of both reports are compared.
+    pub fn diff(&self, baseline: &GasReport) -> GasReportDiff {
+        let mut changes = Vec::new();
+        for (name, contract) in self.contracts.iter() {
+            let before = match baseline.contracts.get(name) {
+                Some(before) => before,
+                None => continue,
+            };
+            let mut push = |function: Option<&String>, metric: &str, before: U256, after: U256| {
+                if before != after && !before.is_zero() {
+                    changes.push(GasChange {
+                        contract: name.clone(),
+                        function: function.cloned(),
+                        metric: metric.to_string(),
+                        before,
+                        after,
+                    });
+                }
+            };
+
+            push(None, "deployment", before.gas, contract.gas);
+            for (fname, function) in contract.functions.iter() {
+                if let Some(prev) = before.functions.get(fname) {
+                    push(Some(fname), "min", prev.min, function.min);
+                    push(Some(fname), "avg", prev.mean, function.mean);
+                    push(Some(fname), "median", prev.median, function.median);
+                    push(Some(fname), "max", prev.max, function.max);
+                }
+            }
+        }
+        GasReportDiff { changes }
+    }
+}
+
+/// The changes of a gas report relative to a baseline
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GasReportDiff {
+    pub changes: Vec<GasChange>,
+}
+
+/// A change of a single gas metric of a contract
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GasChange {
+    pub contract: String,
+    /// The function, or `None` for the deployment cost
+    pub function: Option<String>,
+    /// The changed metric: `deployment`, `min`, `avg`, `median` or `max`
+    pub metric: String,
+    pub before: U256,
+    pub after: U256,
+}
+
+impl GasChange {
+    /// The change in percent of the baseline value
+    pub fn percent(&self) -> f64 {
+        let before = self.before.as_u128() as f64;
+        (self.after.as_u128() as f64 - before) / before * 100.0
+    }
+
+    fn name(&self) -> String {
+        match &self.function {
+            Some(function) => format!("{}::{}", self.contract, function),
+            None => self.contract.clone(),
+        }
+    }
+}
+
+impl GasReportDiff {
+    /// Returns the increases that exceed the threshold, in percent
+    pub fn regressions(&self, threshold: f64) -> Vec<&GasChange> {
+        self.changes.iter().filter(|change| change.percent() > threshold).collect()
+    }
+
+    /// Returns the changes as a Markdown table
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::from("### Gas changes\n\n");
+        if self.changes.is_empty() {
+            markdown.push_str("No changes\n");
+            return markdown
+        }
+        let _ = writeln!(markdown, "| Name | Metric | Before | After | Change |");
+        let _ = writeln!(markdown, "|---|---|---|---|---|");
+        for change in self.changes.iter() {
+            let _ = writeln!(
+                markdown,
+                "| {} | {} | {} | {} | {:+.2}% |",
+                change.name(),
+                change.metric,
+                change.before,
+                change.after,
+                change.percent()
+            );
+        }
+        markdown
+    }
+}
+
+impl Display for GasReportDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        if self.changes.is_empty() {
+            return writeln!(f, "No gas changes relative to the baseline")
+        }
+
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
+        table.set_header(vec![
+            Cell::new("Name").add_attribute(Attribute::Bold).fg(Color::Magenta),
+            Cell::new("Metric").add_attribute(Attribute::Bold),
+            Cell::new("Before").add_attribute(Attribute::Bold),
+            Cell::new("After").add_attribute(Attribute::Bold),
+            Cell::new("Change").add_attribute(Attribute::Bold),
+        ]);
+        for change in self.changes.iter() {
+            let color = if change.after > change.before { Color::Red } else { Color::Green };
+            table.add_row(vec![
+                Cell::new(change.name()).add_attribute(Attribute::Bold),
+                Cell::new(&change.metric),
+                Cell::new(change.before.to_string()),
+                Cell::new(change.after.to_string()),
+                Cell::new(format!("{:+.2}%", change.percent())).fg(color),
+            ]);
+        }
+        writeln!(f, "{}", table)
+    }
+}
+
 impl Display for GasReport {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         for (name, contract) in self.contracts.iter() {
@@ -145,9 +421,133 @@ impl Display for GasReport {
                     Cell::new(function.max.to_string()).fg(Color::Red),
                     Cell::new(function.calls.len().to_string()),
                 ]);
+                if self.breakdown {
+                    function.by_test.iter().for_each(|(test_contract, test)| {
+                        table.add_row(vec![
+                            Cell::new(format!("  ↳ {test_contract}")),
+                            Cell::new(test.min.to_string()).fg(Color::Green),
+                            Cell::new(test.mean.to_string()).fg(Color::Yellow),
+                            Cell::new(test.median.to_string()).fg(Color::Yellow),
+                            Cell::new(test.max.to_string()).fg(Color::Red),
+                            Cell::new(test.calls.len().to_string()),
+                        ]);
+                    });
+                }
             });
             writeln!(f, "{}", table)?
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::CallTrace;
+
+    fn report(deployment: u64, calls: &[u64]) -> GasReport {
+        let mut report = GasReport::default();
+        let contract = report.contracts.entry("Counter".to_string()).or_default();
+        contract.gas = deployment.into();
+        contract.functions.insert(
+            "increment".to_string(),
+            GasInfo {
+                calls: calls.iter().map(|gas| (*gas).into()).collect(),
+                ..Default::default()
+            },
+        );
+        report.finalize()
+    }
+
+    #[test]
+    fn can_attribute_calls() {
+        let call =
+            |depth: usize, address: u64, contract: &str, function: &str, gas_cost: u64| CallTrace {
+                depth,
+                address: Address::from_low_u64_be(address),
+                contract: Some(contract.to_string()),
+                data: RawOrDecodedCall::Decoded(function.to_string(), vec![]),
+                gas_cost,
+                ..Default::default()
+            };
+        let mut execution = CallTraceArena::default();
+        execution.arena[0].trace = call(0, 1, "CounterTest", "testIncrement", 10000);
+        execution.push_trace(
+            0,
+            CallTrace {
+                kind: CallKind::Create,
+                data: RawOrDecodedCall::Raw(vec![0; 20]),
+                output: RawOrDecodedReturnData::Raw(vec![0; 10]),
+                ..call(1, 2, "Counter", "", 5000)
+            },
+        );
+        // a call through a proxy that delegates to its implementation
+        execution.push_trace(0, call(1, 3, "Proxy", "increment", 3000));
+        execution.push_trace(
+            2,
+            CallTrace { kind: CallKind::DelegateCall, ..call(2, 2, "Counter", "increment", 2500) },
+        );
+        execution.push_trace(3, call(3, 4, "Math", "add", 500));
+
+        let mut deployment = CallTraceArena::default();
+        deployment.arena[0].trace = CallTrace {
+            kind: CallKind::Create,
+            output: RawOrDecodedReturnData::Raw(vec![0; 100]),
+            ..call(0, 1, "CounterTest", "", 50000)
+        };
+
+        // a library the test contract is linked against
+        let mut library = CallTraceArena::default();
+        library.arena[0].trace = CallTrace {
+            kind: CallKind::Create,
+            output: RawOrDecodedReturnData::Raw(vec![0; 30]),
+            ..call(0, 5, "Lib", "", 20000)
+        };
+
+        let mut report = GasReport::new(vec![]);
+        report.analyze(
+            "src/Counter.t.sol:CounterTest",
+            &[
+                (TraceKind::Deployment, library),
+                (TraceKind::Deployment, deployment),
+                (TraceKind::Execution, execution),
+            ],
+        );
+        let report = report.finalize();
+
+        // the test contract and the proxy are not reported
+        assert_eq!(report.contracts.keys().collect::<Vec<_>>(), ["Counter", "Lib", "Math"]);
+        let lib = &report.contracts["Lib"];
+        assert_eq!((lib.gas, lib.size), (20000.into(), 30.into()));
+        let counter = &report.contracts["Counter"];
+        assert_eq!((counter.gas, counter.size), (5000.into(), 10.into()));
+        let increment = &counter.functions["increment"];
+        assert_eq!(increment.calls, [U256::from(3000)]);
+        assert_eq!(increment.by_test["src/Counter.t.sol:CounterTest"].max, 3000.into());
+        assert_eq!(report.contracts["Math"].functions["add"].calls, [U256::from(500)]);
+    }
+
+    #[test]
+    fn can_diff_against_baseline() {
+        let baseline = report(1000, &[100, 200]);
+        let current = report(1000, &[100, 230]);
+        let diff = current.diff(&baseline);
+
+        // only the changed metrics are reported
+        let changes = diff
+            .changes
+            .iter()
+            .map(|change| (change.metric.as_str(), change.percent()))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, [("avg", 10.0), ("median", 10.0), ("max", 15.0)]);
+
+        assert_eq!(diff.regressions(12.0).len(), 1);
+        assert!(diff.regressions(15.0).is_empty());
+        assert!(diff.to_markdown().contains("| Counter::increment | max | 200 | 230 | +15.00% |"));
+
+        // reports survive a JSON round trip, so they can be used as baselines
+        let json = serde_json::to_string(&current).unwrap();
+        let read: GasReport = serde_json::from_str(&json).unwrap();
+        assert!(read.diff(&current).changes.is_empty());
+    }
+}
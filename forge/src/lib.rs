@@ -1,10 +1,25 @@
 /// Gas reports
 pub mod gas_report;
 
+/// Gas profiles that keep the call hierarchy, for flamegraphs
+pub mod gas_profile;
+
 /// The Forge test runner
 mod runner;
 pub use runner::{ContractRunner, SuiteResult, TestKind, TestKindGas, TestResult};
 
+/// Custom NatSpec tags of test functions
+pub mod natspec;
+
+/// Caching of passing test results between runs
+pub mod result_cache;
+
+/// Machine readable test reports
+pub mod reporter;
+
+/// Splitting test suites across machines
+pub mod shard;
+
 /// Forge test runners for multiple contracts
 mod multi_runner;
 pub use multi_runner::{MultiContractRunner, MultiContractRunnerBuilder};
@@ -13,6 +28,14 @@ pub trait TestFilter {
     fn matches_test(&self, test_name: impl AsRef<str>) -> bool;
     fn matches_contract(&self, contract_name: impl AsRef<str>) -> bool;
     fn matches_path(&self, path: impl AsRef<str>) -> bool;
+
+    /// Returns whether the test `test_name` of the contract with the given artifact identifier
+    /// matches, for filters that select the tests of specific contracts.
+    ///
+    /// Defaults to [`TestFilter::matches_test`].
+    fn matches_contract_test(&self, _artifact_id: &str, test_name: impl AsRef<str>) -> bool {
+        self.matches_test(test_name)
+    }
 }
 
 /// The Forge EVM backend
@@ -1,18 +1,35 @@
-use crate::{ContractRunner, SuiteResult, TestFilter};
+use crate::{
+    natspec::{self, FunctionTags},
+    result_cache::{artifacts_hash, cache_key, reads_files, TestResultCache},
+    runner::setup_variants,
+    shard::Shard,
+    ContractRunner, SuiteResult, TestFilter, TestResult,
+};
 use ethers::{
     abi::Abi,
-    prelude::{artifacts::CompactContractBytecode, ArtifactId, ArtifactOutput},
+    prelude::{
+        artifacts::{CompactContractBytecode, Offsets},
+        ArtifactId, ArtifactOutput,
+    },
     solc::{Artifact, ProjectCompileOutput},
-    types::{Address, Bytes, U256},
+    types::{Address, Bytes, H256, U256},
 };
 use eyre::Result;
 use foundry_evm::executor::{
-    builder::Backend, opts::EvmOpts, DatabaseRef, Executor, ExecutorBuilder, Fork, SpecId,
+    builder::Backend,
+    opts::{EvmOpts, DEFAULT_CHAIN_ID},
+    DatabaseRef, Executor, ExecutorBuilder, Fork, SpecId,
 };
 use foundry_utils::{PostLinkInput, RuntimeOrHandle};
 use proptest::test_runner::TestRunner;
 use rayon::prelude::*;
-use std::{collections::BTreeMap, marker::Sync, path::Path, sync::mpsc::Sender};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    marker::Sync,
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+    time::Duration,
+};
 
 /// Builder used for instantiating the multi-contract runner
 #[derive(Debug, Default)]
@@ -28,6 +45,18 @@ pub struct MultiContractRunnerBuilder {
     pub evm_spec: Option<SpecId>,
     /// The fork config
     pub fork: Option<Fork>,
+    /// Whether fuzz tests are guided by the `JUMPI` edges their inputs reach
+    pub coverage_guided: bool,
+    /// The directory passing test results are cached in
+    pub result_cache: Option<PathBuf>,
+    /// The wall-clock time after which a test call is halted
+    pub test_timeout: Option<Duration>,
+    /// The wall-clock time after which a fuzz campaign is stopped
+    pub fuzz_timeout: Option<Duration>,
+    /// The seed of every fuzz campaign, if it is fixed
+    pub fuzz_seed: Option<H256>,
+    /// The share of the test contracts to run
+    pub shard: Option<Shard>,
 }
 
 pub type DeployableContracts = BTreeMap<ArtifactId, (Abi, Bytes, Vec<Bytes>)>;
@@ -44,6 +73,8 @@ impl MultiContractRunnerBuilder {
     where
         A: ArtifactOutput,
     {
+        let root = root.as_ref();
+
         // This is just the contracts compiled, but we need to merge this with the read cached
         // artifacts
         let contracts = output
@@ -53,6 +84,13 @@ impl MultiContractRunnerBuilder {
             .collect::<Vec<(ArtifactId, CompactContractBytecode)>>();
 
         let mut known_contracts: BTreeMap<ArtifactId, (Abi, Vec<u8>)> = Default::default();
+        let immutable_references = contracts
+            .iter()
+            .filter_map(|(id, contract)| {
+                let references = &contract.deployed_bytecode.as_ref()?.immutable_references;
+                (!references.is_empty()).then(|| (id.clone(), references.clone()))
+            })
+            .collect();
         let source_paths = contracts
             .iter()
             .map(|(i, _)| (i.identifier(), i.source.to_string_lossy().into()))
@@ -102,10 +140,18 @@ impl MultiContractRunnerBuilder {
             },
         )?;
 
+        let natspec = collect_natspec(
+            root,
+            source_paths.values().collect::<BTreeSet<_>>(),
+            &deployable_contracts,
+        );
+
         let execution_info = foundry_utils::flatten_known_contracts(&known_contracts);
         Ok(MultiContractRunner {
             contracts: deployable_contracts,
+            natspec,
             known_contracts,
+            immutable_references,
             evm_opts,
             evm_spec: self.evm_spec.unwrap_or(SpecId::LONDON),
             sender: self.sender,
@@ -113,6 +159,13 @@ impl MultiContractRunnerBuilder {
             errors: Some(execution_info.2),
             source_paths,
             fork: self.fork,
+            coverage_guided: self.coverage_guided,
+            result_cache: self.result_cache,
+            test_timeout: self.test_timeout,
+            fuzz_timeout: self.fuzz_timeout,
+            fuzz_seed: self.fuzz_seed,
+            shard: self.shard,
+            root: root.to_path_buf(),
         })
     }
 
@@ -145,6 +198,77 @@ impl MultiContractRunnerBuilder {
         self.fork = fork;
         self
     }
+
+    #[must_use]
+    pub fn coverage_guided(mut self, enable: bool) -> Self {
+        self.coverage_guided = enable;
+        self
+    }
+
+    #[must_use]
+    pub fn with_result_cache(mut self, cache_dir: Option<PathBuf>) -> Self {
+        self.result_cache = cache_dir;
+        self
+    }
+
+    #[must_use]
+    pub fn test_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.test_timeout = timeout;
+        self
+    }
+
+    #[must_use]
+    pub fn fuzz_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.fuzz_timeout = timeout;
+        self
+    }
+
+    /// Runs every fuzz campaign with the given seed, instead of a seed drawn from the fuzzer
+    #[must_use]
+    pub fn fuzz_seed(mut self, seed: Option<U256>) -> Self {
+        self.fuzz_seed = seed.map(|seed| {
+            let mut bytes = [0u8; 32];
+            seed.to_big_endian(&mut bytes);
+            H256(bytes)
+        });
+        self
+    }
+
+    #[must_use]
+    pub fn shard(mut self, shard: Option<Shard>) -> Self {
+        self.shard = shard;
+        self
+    }
+}
+
+/// Reads the custom NatSpec tags of the test functions of all deployable contracts from their
+/// sources.
+///
+/// Tags of inherited functions are looked up in the base contracts, which may be declared in any
+/// of the `sources` of the project.
+fn collect_natspec<'a>(
+    root: &Path,
+    sources: impl IntoIterator<Item = &'a String>,
+    contracts: &DeployableContracts,
+) -> BTreeMap<ArtifactId, FunctionTags> {
+    let sources = sources
+        .into_iter()
+        .map(|source| {
+            let tags = std::fs::read_to_string(root.join(source))
+                .map(|source| natspec::parse_custom_tags(&source))
+                .unwrap_or_default();
+            (source.clone(), tags)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    contracts
+        .iter()
+        .filter_map(|(id, (abi, _, _))| {
+            let source = id.source.to_string_lossy();
+            let tags = natspec::resolve_tags(&sources, &source, &id.name, abi);
+            (!tags.is_empty()).then(|| (id.clone(), tags))
+        })
+        .collect()
 }
 
 /// A multi contract runner receives a set of contracts deployed in an EVM instance and proceeds
@@ -155,6 +279,8 @@ pub struct MultiContractRunner {
     pub contracts: DeployableContracts,
     /// Compiled contracts by name that have an Abi and runtime bytecode
     pub known_contracts: BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
+    /// The offsets of the immutables in the runtime code of the known contracts
+    pub immutable_references: BTreeMap<ArtifactId, BTreeMap<String, Vec<Offsets>>>,
     /// The EVM instance used in the test runner
     pub evm_opts: EvmOpts,
     /// The EVM spec
@@ -169,20 +295,91 @@ pub struct MultiContractRunner {
     pub source_paths: BTreeMap<String, String>,
     /// The fork config
     pub fork: Option<Fork>,
+    /// Whether fuzz tests are guided by the `JUMPI` edges their inputs reach
+    coverage_guided: bool,
+    /// The directory passing test results are cached in, if caching is enabled
+    result_cache: Option<PathBuf>,
+    /// The wall-clock time after which a test call is halted
+    test_timeout: Option<Duration>,
+    /// The wall-clock time after which a fuzz campaign is stopped
+    fuzz_timeout: Option<Duration>,
+    /// The seed of every fuzz campaign, if it is fixed
+    fuzz_seed: Option<H256>,
+    /// The share of the test contracts to run, if the tests are split across machines
+    shard: Option<Shard>,
+    /// The custom NatSpec tags of the test functions of each contract
+    pub natspec: BTreeMap<ArtifactId, FunctionTags>,
+    /// The root of the project
+    root: PathBuf,
 }
 
 impl MultiContractRunner {
+    /// Returns the chain id of the environment the tests run in
+    pub fn chain_id(&self) -> u64 {
+        match &self.fork {
+            Some(fork) => fork.chain_id,
+            None => self.evm_opts.env.chain_id.unwrap_or(DEFAULT_CHAIN_ID),
+        }
+    }
+
     pub fn count_filtered_tests(&self, filter: &(impl TestFilter + Send + Sync)) -> usize {
-        self.contracts
+        self.list(filter, true).values().flat_map(BTreeMap::values).map(Vec::len).sum()
+    }
+
+    /// Returns the signatures of the tests that match the filter without running them, grouped by
+    /// source file and contract name
+    pub fn list(
+        &self,
+        filter: &(impl TestFilter + Send + Sync),
+        include_fuzz_tests: bool,
+    ) -> BTreeMap<String, BTreeMap<String, Vec<String>>> {
+        let shard = self.shard_contracts(filter);
+        let mut tests = BTreeMap::<_, BTreeMap<_, _>>::new();
+        for (id, (abi, _, _)) in self.contracts.iter().filter(|(id, _)| {
+            filter.matches_path(id.source.to_string_lossy()) &&
+                filter.matches_contract(&id.name) &&
+                shard.as_ref().map_or(true, |shard| shard.contains(&id.identifier()))
+        }) {
+            let identifier = id.identifier();
+            let signatures = abi
+                .functions()
+                .filter(|func| {
+                    func.name.starts_with("test") &&
+                        (include_fuzz_tests || func.inputs.is_empty()) &&
+                        filter.matches_contract_test(&identifier, func.signature())
+                })
+                .map(|func| func.signature())
+                .collect::<Vec<_>>();
+            if !signatures.is_empty() {
+                tests
+                    .entry(id.source.to_string_lossy().into_owned())
+                    .or_default()
+                    .insert(id.name.clone(), signatures);
+            }
+        }
+        tests
+    }
+
+    /// Returns the identifiers of the test contracts that match the filter and belong to the
+    /// runner's shard, or `None` if the tests are not sharded.
+    ///
+    /// The shards are computed from the same set of contracts on every machine, as long as the
+    /// filters are the same.
+    fn shard_contracts(
+        &self,
+        filter: &(impl TestFilter + Send + Sync),
+    ) -> Option<BTreeSet<String>> {
+        let shard = self.shard.as_ref()?;
+        let identifiers = self
+            .contracts
             .iter()
-            .filter(|(id, _)| {
+            .filter(|(id, (abi, _, _))| {
                 filter.matches_path(id.source.to_string_lossy()) &&
-                    filter.matches_contract(&id.name)
+                    filter.matches_contract(&id.name) &&
+                    abi.functions().any(|func| filter.matches_test(&func.name))
             })
-            .flat_map(|(_, (abi, _, _))| {
-                abi.functions().filter(|func| filter.matches_test(func.signature()))
-            })
-            .count()
+            .map(|(id, _)| id.identifier());
+        Some(shard.select(identifiers))
     }
 
     pub fn test(
@@ -196,47 +393,138 @@ impl MultiContractRunner {
 
         // the db backend that serves all the data
         let db = runtime.block_on(Backend::new(self.fork.take(), &env));
+        let no_natspec = FunctionTags::default();
+
+        let mut cache = self.read_result_cache();
+        let cache_context = self.cache_context();
+        let shard = self.shard_contracts(filter);
 
         let results = self
             .contracts
             .par_iter()
             .filter(|(id, _)| {
                 filter.matches_path(id.source.to_string_lossy()) &&
-                    filter.matches_contract(&id.name)
+                    filter.matches_contract(&id.name) &&
+                    shard.as_ref().map_or(true, |shard| shard.contains(&id.identifier()))
             })
             .filter(|(_, (abi, _, _))| abi.functions().any(|func| filter.matches_test(&func.name)))
             .map(|(id, (abi, deploy_code, libs))| {
-                let mut builder = ExecutorBuilder::new()
-                    .with_cheatcodes(self.evm_opts.ffi)
-                    .with_config(env.clone())
-                    .with_spec(self.evm_spec)
-                    .with_gas_limit(self.evm_opts.gas_limit());
-
-                if self.evm_opts.verbosity >= 3 {
-                    builder = builder.with_tracing();
+                let identifier = id.identifier();
+                let natspec = self.natspec.get(id).unwrap_or(&no_natspec);
+
+                // Look up the results of tests that can be skipped. The results of tests run once
+                // for every `setUp` variant are not cached, since they are not reported under
+                // their signature
+                let cacheable = !reads_files(deploy_code) &&
+                    !libs.iter().any(|lib| reads_files(lib)) &&
+                    setup_variants(abi).is_empty();
+                let key = cache.as_ref().filter(|_| cacheable).map(|_| {
+                    let natspec = serde_json::to_vec(natspec).unwrap_or_default();
+                    cache_key(deploy_code, libs, &[cache_context.as_slice(), &natspec].concat())
+                });
+                let mut cached = match (&cache, key) {
+                    (Some(cache), Some(key)) => cache.get(&identifier, key),
+                    _ => BTreeMap::new(),
+                };
+                cached.retain(|sig, _| filter.matches_contract_test(&identifier, sig));
+
+                let all_cached = abi
+                    .functions()
+                    .filter(|func| {
+                        func.name.starts_with("test") &&
+                            filter.matches_contract_test(&identifier, func.signature()) &&
+                            (include_fuzz_tests || func.inputs.is_empty())
+                    })
+                    .all(|func| cached.contains_key(&func.signature()));
+                if all_cached && !cached.is_empty() {
+                    return Ok((identifier, key, SuiteResult::new(Duration::ZERO, cached, vec![])))
                 }
 
-                let executor = builder.build(db.clone());
-                let result = self.run_tests(
-                    &id.identifier(),
+                let new_executor = || {
+                    let mut builder = ExecutorBuilder::new()
+                        .with_cheatcodes(self.evm_opts.ffi)
+                        .with_config(env.clone())
+                        .with_spec(self.evm_spec)
+                        .with_gas_limit(self.evm_opts.gas_limit())
+                        .with_timeout(self.test_timeout);
+
+                    if self.evm_opts.verbosity >= 3 {
+                        builder = builder.with_tracing();
+                    }
+                    if self.evm_opts.verbosity >= 6 {
+                        builder = builder.with_storage_tracing();
+                    }
+                    if self.coverage_guided {
+                        builder = builder.with_coverage();
+                    }
+
+                    builder.build(db.clone())
+                };
+                let mut result = self.run_tests(
+                    &identifier,
                     abi,
-                    executor,
+                    new_executor,
                     deploy_code.clone(),
                     libs,
-                    (filter, include_fuzz_tests),
+                    natspec,
+                    (
+                        &ContractFilter { filter, artifact_id: &identifier, cached: &cached },
+                        include_fuzz_tests,
+                    ),
                 )?;
-                Ok((id.identifier(), result))
+                result.test_results.extend(cached);
+                Ok((identifier, key, result))
             })
             .filter_map(Result::<_>::ok)
-            .filter(|(_, results)| !results.is_empty())
-            .map_with(stream_result, |stream_result, (name, result)| {
+            .filter(|(_, _, results)| !results.is_empty())
+            .map_with(stream_result, |stream_result, (name, key, result)| {
                 if let Some(stream_result) = stream_result.as_ref() {
                     stream_result.send((name.clone(), result.clone())).unwrap();
                 }
-                (name, result)
+                (name, key, result)
             })
-            .collect::<BTreeMap<_, _>>();
-        Ok(results)
+            .collect::<Vec<_>>();
+
+        if let Some(cache) = cache.as_mut() {
+            for (name, key, result) in results.iter() {
+                if let Some(key) = key {
+                    cache.insert(name.clone(), *key, result);
+                }
+            }
+            if let Err(err) = cache.write() {
+                tracing::warn!(?err, "failed to write test result cache");
+            }
+        }
+
+        Ok(results.into_iter().map(|(name, _, result)| (name, result)).collect())
+    }
+
+    /// Reads the cached test results, if caching is enabled.
+    ///
+    /// Tests against a fork that is not pinned to a block are never cached, since the state they
+    /// run against can change between runs, and neither are tests that can call `ffi`.
+    fn read_result_cache(&self) -> Option<TestResultCache> {
+        if self.evm_opts.ffi ||
+            (self.evm_opts.fork_url.is_some() && self.evm_opts.fork_block_number.is_none())
+        {
+            return None
+        }
+        self.result_cache.as_ref().map(TestResultCache::read)
+    }
+
+    /// Returns the settings that can affect the outcome of a test, for the test result cache key
+    fn cache_context(&self) -> Vec<u8> {
+        // verbosity only affects what is collected during execution, not the outcome
+        let evm_opts = EvmOpts { verbosity: 0, ..self.evm_opts.clone() };
+        let mut context = serde_json::to_vec(&evm_opts).unwrap_or_default();
+        context.push(self.evm_spec as u8);
+        let artifacts = self
+            .known_contracts
+            .iter()
+            .map(|(id, (_, code))| (id, code.as_slice()))
+            .chain(self.contracts.iter().map(|(id, (_, code, _))| (id, code.as_ref())));
+        context.extend_from_slice(artifacts_hash(artifacts).as_bytes());
+        context
     }
 
     // The _name field is unused because we only want it for tracing
@@ -250,21 +538,75 @@ impl MultiContractRunner {
         &self,
         _name: &str,
         contract: &Abi,
-        executor: Executor<DB>,
+        new_executor: impl Fn() -> Executor<DB>,
         deploy_code: Bytes,
         libs: &[Bytes],
+        natspec: &FunctionTags,
         (filter, include_fuzz_tests): (&impl TestFilter, bool),
     ) -> Result<SuiteResult> {
-        let mut runner = ContractRunner::new(
-            executor,
-            contract,
-            deploy_code,
-            self.evm_opts.initial_balance,
-            self.sender,
-            self.errors.as_ref(),
-            libs,
-        );
-        runner.run_tests(filter, self.fuzzer.clone(), include_fuzz_tests)
+        let run = |setup_variant: Option<&str>| {
+            let mut runner = ContractRunner::new(
+                new_executor(),
+                contract,
+                deploy_code.clone(),
+                self.evm_opts.initial_balance,
+                self.sender,
+                self.errors.as_ref(),
+                libs,
+                natspec,
+            );
+            runner.fuzz_timeout = self.fuzz_timeout;
+            runner.fuzz_seed = self.fuzz_seed;
+            runner.setup_variant = setup_variant.map(str::to_string);
+            runner.root = Some(&self.root);
+            runner.run_tests(filter, self.fuzzer.clone(), include_fuzz_tests)
+        };
+
+        // The tests of a contract with `setUp_<name>()` variants run once for every variant
+        let variants = setup_variants(contract);
+        if variants.is_empty() {
+            return run(None)
+        }
+        let mut suite = SuiteResult::new(Duration::ZERO, BTreeMap::new(), vec![]);
+        for variant in variants {
+            let result = run(Some(variant))?;
+            suite.duration += result.duration;
+            suite.test_results.extend(
+                result
+                    .test_results
+                    .into_iter()
+                    .map(|(sig, result)| (format!("{sig} [{variant}]"), result)),
+            );
+            for warning in result.warnings {
+                if !suite.warnings.contains(&warning) {
+                    suite.warnings.push(warning);
+                }
+            }
+        }
+        Ok(suite)
+    }
+}
+
+/// The [`TestFilter`] for the tests of a single contract, which skips the tests with cached
+/// results
+struct ContractFilter<'a, F> {
+    filter: &'a F,
+    artifact_id: &'a str,
+    cached: &'a BTreeMap<String, TestResult>,
+}
+
+impl<'a, F: TestFilter> TestFilter for ContractFilter<'a, F> {
+    fn matches_test(&self, test_name: impl AsRef<str>) -> bool {
+        !self.cached.contains_key(test_name.as_ref()) &&
+            self.filter.matches_contract_test(self.artifact_id, test_name)
+    }
+
+    fn matches_contract(&self, contract_name: impl AsRef<str>) -> bool {
+        self.filter.matches_contract(contract_name)
+    }
+
+    fn matches_path(&self, path: impl AsRef<str>) -> bool {
+        self.filter.matches_path(path)
     }
 }
 
@@ -449,6 +791,77 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_deterministic_across_thread_counts() {
+        let run = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            pool.install(|| {
+                runner()
+                    .test(&Filter::new(".*", ".*", ".*core|.*logs"), None, false)
+                    .unwrap()
+                    .into_iter()
+                    .flat_map(|(contract, SuiteResult { test_results, .. })| {
+                        test_results.into_iter().map(move |(test, result)| {
+                            (
+                                format!("{contract}::{test}"),
+                                (result.success, result.reason, result.kind.gas_used()),
+                            )
+                        })
+                    })
+                    .collect::<BTreeMap<_, _>>()
+            })
+        };
+
+        assert_eq!(run(1), run(4));
+    }
+
+    #[test]
+    fn test_result_cache() {
+        let cache_dir = std::env::temp_dir().join("forge-multi-runner-result-cache");
+        let _ = std::fs::remove_dir_all(&cache_dir);
+        let run = |path: &str, ffi: bool| {
+            base_runner()
+                .with_result_cache(Some(cache_dir.clone()))
+                .build(
+                    &(*PROJECT).paths.root,
+                    (*COMPILED).clone(),
+                    EvmOpts { ffi, ..EVM_OPTS.clone() },
+                )
+                .unwrap()
+                .test(&Filter::new(".*", ".*", path), None, true)
+                .unwrap()
+        };
+        let any_cached = |results: &BTreeMap<String, SuiteResult>| {
+            results.values().flat_map(|suite| suite.test_results.values()).any(|r| r.cached)
+        };
+
+        let first = run(".*core", false);
+        assert!(!any_cached(&first));
+
+        // nothing is cached while `ffi` is enabled
+        assert!(!any_cached(&run(".*core", true)));
+
+        // contracts that read files with `getCode` are never cached
+        run(".*cheats/GetCode", false);
+        assert!(!any_cached(&run(".*cheats/GetCode", false)));
+
+        let second = run(".*core", false);
+        for (contract, suite) in second.iter() {
+            for (test, result) in suite.test_results.iter() {
+                let previous = &first[contract].test_results[test];
+                assert_eq!(result.success, previous.success, "{contract}::{test}");
+                assert_eq!(result.kind.gas_used(), previous.kind.gas_used(), "{contract}::{test}");
+                // only passing unit tests are served from the cache
+                assert_eq!(
+                    result.cached,
+                    result.success && !result.is_fuzz(),
+                    "{contract}::{test}"
+                );
+            }
+        }
+        let _ = std::fs::remove_dir_all(cache_dir);
+    }
+
     #[test]
     fn test_logs() {
         let mut runner = runner();
@@ -553,19 +966,285 @@ mod tests {
     fn test_fuzz() {
         let mut runner = runner();
         let suite_result = runner.test(&Filter::new(".*", ".*", ".*fuzz"), None, true).unwrap();
+        assert_fuzz_results(suite_result);
+    }
 
-        for (_, SuiteResult { test_results, .. }) in suite_result {
+    #[test]
+    fn test_fuzz_coverage_guided() {
+        let mut runner = base_runner()
+            .coverage_guided(true)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let suite_result = runner.test(&Filter::new(".*", ".*", ".*fuzz"), None, true).unwrap();
+        assert_fuzz_results(suite_result);
+    }
+
+    #[test]
+    fn test_fuzz_stats() {
+        let run = |seed: Option<U256>| {
+            let mut runner = base_runner()
+                .fuzz_seed(seed)
+                .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+                .unwrap();
+            let mut results = runner
+                .test(&Filter::new("testAssume", ".*", ".*cheats/Assume"), None, true)
+                .unwrap();
+            results.remove("cheats/Assume.t.sol:AssumeTest").unwrap().test_results
+                ["testAssume(uint8)"]
+                .clone()
+        };
+
+        let result = run(None);
+        let stats = result.fuzz_stats.clone().expect("no fuzz stats");
+        // half of all `uint8` inputs are rejected by `vm.assume`
+        assert!(stats.rejects > 0 && stats.rejects < stats.runs, "{stats:?}");
+        assert!(stats.dictionary_values > 0, "{stats:?}");
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["fuzz_stats"]["runs"], stats.runs);
+        assert_eq!(json["fuzz_stats"]["rejects"], stats.rejects);
+        assert_eq!(json["fuzz_stats"]["dictionary_values"], stats.dictionary_values);
+        assert_eq!(json["fuzz_stats"]["duration"], stats.duration.as_secs_f64());
+        assert_eq!(json["fuzz_stats"]["seed"], format!("{:?}", stats.seed));
+
+        // the reported seed reproduces the run
+        let replay = run(Some(U256::from_big_endian(stats.seed.as_bytes())));
+        let replay_stats = replay.fuzz_stats.clone().expect("no fuzz stats");
+        assert_eq!(replay_stats.seed, stats.seed);
+        assert_eq!(replay_stats.runs, stats.runs);
+        assert_eq!(replay_stats.rejects, stats.rejects);
+        assert_eq!(replay.kind.gas_used(), result.kind.gas_used());
+    }
+
+    #[test]
+    fn test_limits() {
+        let timeout = Duration::from_millis(500);
+        let mut runner = base_runner()
+            .test_timeout(Some(timeout))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*limits"), None, true).unwrap();
+
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "limits/Limits.t.sol:LimitsTest",
+                vec![
+                    (
+                        "testTimeout()",
+                        false,
+                        Some(format!("Test timed out after {timeout:?}")),
+                        None,
+                        None,
+                    ),
+                    ("testFailGasLimit()", true, None, None, None),
+                    ("testGasLimit()", true, None, None, None),
+                    (
+                        "testInvalidGasLimit()",
+                        false,
+                        Some("Invalid gas limit: `lots` is not a valid gas limit".to_string()),
+                        None,
+                        None,
+                    ),
+                ],
+            )]),
+        );
+    }
+
+    #[test]
+    fn test_list() {
+        let runner = runner();
+        let filter = Filter::new(".*", ".*", ".*table");
+
+        let tests = runner.list(&filter, true);
+        assert_eq!(
+            tests,
+            BTreeMap::from([(
+                "table/Table.t.sol".to_string(),
+                BTreeMap::from([(
+                    "TableTest".to_string(),
+                    vec![
+                        "testDouble(uint256,uint256)".to_string(),
+                        "testFuzzWithFixture(uint256,uint256)".to_string(),
+                        "testMismatchedFixtures(uint256,address)".to_string(),
+                    ]
+                )])
+            )])
+        );
+        assert_eq!(runner.count_filtered_tests(&filter), 3);
+
+        // tests with parameters are only listed along with fuzz tests
+        assert!(runner.list(&filter, false).is_empty());
+    }
+
+    #[test]
+    fn test_shard() {
+        let filter = Filter::new(".*", ".*", ".*core");
+        let flatten = |tests: BTreeMap<String, BTreeMap<String, Vec<String>>>| {
+            tests
+                .into_iter()
+                .flat_map(|(file, contracts)| {
+                    contracts
+                        .into_iter()
+                        .map(move |(contract, tests)| (format!("{file}:{contract}"), tests))
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        let all = flatten(runner().list(&filter, true));
+
+        // every test contract belongs to exactly one shard, which only runs its own contracts
+        let mut sharded = BTreeMap::new();
+        for index in 1..=3 {
+            let mut runner = base_runner()
+                .shard(Some(Shard::new(index, 3)))
+                .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+                .unwrap();
+            let listed = flatten(runner.list(&filter, true));
+            let results = runner.test(&filter, None, true).unwrap();
+            assert!(results.keys().all(|id| listed.contains_key(id)));
+
+            for (id, tests) in listed {
+                assert!(sharded.insert(id, tests).is_none());
+            }
+        }
+        assert_eq!(sharded, all);
+    }
+
+    #[test]
+    fn test_table() {
+        let cfg = proptest::test_runner::Config { failure_persistence: None, ..Default::default() };
+        let mut runner = base_runner()
+            .fuzzer(TestRunner::new(cfg))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*table/Table"), None, true).unwrap();
+
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "table/Table.t.sol:TableTest",
+                vec![
+                    ("testDouble(uint256,uint256) #0", true, None, None, None),
+                    ("testDouble(uint256,uint256) #1", true, None, None, None),
+                    ("testDouble(uint256,uint256) #2", false, None, None, None),
+                    (
+                        "testMismatchedFixtures(uint256,address)",
+                        false,
+                        Some(
+                            "Invalid fixture: the fixtures of `amount` and `owner` have different \
+                             lengths"
+                                .to_string(),
+                        ),
+                        None,
+                        None,
+                    ),
+                    (
+                        "testFuzzWithFixture(uint256,uint256)",
+                        false,
+                        Some("found fixture".to_string()),
+                        None,
+                        None,
+                    ),
+                ],
+            )]),
+        );
+
+        let case =
+            &results["table/Table.t.sol:TableTest"].test_results["testDouble(uint256,uint256) #2"];
+        assert_eq!(case.inputs, Some(vec!["100".to_string(), "201".to_string()]));
+    }
+
+    #[test]
+    fn test_setup_variants() {
+        let mut runner = runner();
+        let results =
+            runner.test(&Filter::new(".*", ".*", ".*table/SetupVariants"), None, true).unwrap();
+
+        assert_multiple(
+            &results,
+            BTreeMap::from([
+                (
+                    "table/SetupVariants.t.sol:SetupVariantsTest",
+                    vec![
+                        ("testBase() [large]", true, None, None, None),
+                        ("testBase() [small]", true, None, None, None),
+                        ("testSmall() [large]", false, None, None, None),
+                        ("testSmall() [small]", true, None, None, None),
+                    ],
+                ),
+                (
+                    "table/SetupVariants.t.sol:SetupVariantFailTest",
+                    vec![
+                        (
+                            "setUp() [broken]",
+                            false,
+                            Some("Setup failed: broken variant".to_string()),
+                            None,
+                            None,
+                        ),
+                        ("testVariant() [working]", true, None, None, None),
+                    ],
+                ),
+            ]),
+        );
+    }
+
+    /// Asserts that only the fuzz tests that are expected to pass did pass
+    fn assert_fuzz_results(suite_result: BTreeMap<String, SuiteResult>) {
+        for (contract, SuiteResult { test_results, .. }) in suite_result {
             for (test_name, result) in test_results {
                 let logs = decode_console_logs(&result.logs);
 
                 match test_name.as_ref() {
-                    "testPositive(uint256)" | "testSuccessfulFuzz(uint128,uint128)" => assert!(
+                    "testPositive(uint256)" |
+                    "testSuccessfulFuzz(uint128,uint128)" |
+                    "testBoundedRange(uint256)" |
+                    "testNotZeroAddress(address)" |
+                    "testBoundedArray(uint64[])" |
+                    "testInheritedRange(uint256)" |
+                    "testDifferentialAdd(uint64,uint64)" |
+                    "testDifferentialFfi(uint256)" => assert!(
                         result.success,
                         "Test {} did not pass as expected.\nReason: {:?}\nLogs:\n{}",
                         test_name,
                         result.reason,
                         logs.join("\n")
                     ),
+                    // the constraints of a test only apply to the contract that declares it
+                    "testSameName(uint256)" => assert_eq!(
+                        result.success,
+                        contract.ends_with(":FuzzTaggedTest"),
+                        "Test {}::{} did not use the expected constraints.\nReason: {:?}",
+                        contract,
+                        test_name,
+                        result.reason,
+                    ),
+                    "testDifferentialMismatch(uint64,uint64)" => assert!(
+                        !result.success &&
+                            result.reason.as_deref().map_or(false, |reason| {
+                                reason.starts_with(
+                                    "Differential mismatch: testDifferentialMismatch returned 0x",
+                                ) && reason.contains(", but buggyAdd returned 0x")
+                            }),
+                        "Test {} did not fail with a differential mismatch.\nReason: {:?}",
+                        test_name,
+                        result.reason
+                    ),
+                    "testFailDifferential(uint64,uint64)" => assert_eq!(
+                        result.reason.as_deref(),
+                        Some(
+                            "Invalid differential target: `testFailDifferential` is expected to \
+                             fail and can not be differential"
+                        ),
+                        "Test {} did not fail with the expected reason",
+                        test_name
+                    ),
+                    "testInvalidConstraint(uint256)" => assert_eq!(
+                        result.reason.as_deref(),
+                        Some("Invalid fuzz constraint: unknown parameter `missing`"),
+                        "Test {} did not fail with the expected reason",
+                        test_name
+                    ),
                     _ => assert!(
                         !result.success,
                         "Test {} did not fail as expected.\nReason: {:?}\nLogs:\n{}",
@@ -574,6 +1253,11 @@ mod tests {
                         logs.join("\n")
                     ),
                 }
+
+                if result.success {
+                    let stats = result.fuzz_stats.expect("no fuzz stats");
+                    assert!(stats.runs > 0, "Test {} did not record its fuzz runs", test_name);
+                }
             }
         }
     }
@@ -0,0 +1,428 @@
+use ethers::abi::Abi;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A `@custom:<name> <value>` NatSpec tag
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomTag {
+    /// The name of the tag, without the `custom:` prefix
+    pub name: String,
+    /// The value of the tag
+    pub value: String,
+}
+
+/// The custom NatSpec tags of the functions of a contract, keyed by function signature
+pub type FunctionTags = BTreeMap<String, Vec<CustomTag>>;
+
+/// The custom NatSpec tags declared in the source of a contract
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContractTags {
+    /// The names of the contracts it inherits from, in declaration order
+    pub bases: Vec<String>,
+    /// The tags of the functions declared in the contract itself, keyed by signature.
+    ///
+    /// The signatures are built from the parameter types as written in the source, so they only
+    /// match the ABI signature for elementary types.
+    pub functions: FunctionTags,
+}
+
+/// Extracts the custom NatSpec tags of all functions in a Solidity source file, keyed by contract
+/// name.
+///
+/// This is a line based scan of the source and only understands doc comments (`///` and
+/// `/** */`) that directly precede a `function` declaration.
+pub fn parse_custom_tags(source: &str) -> BTreeMap<String, ContractTags> {
+    let mut contracts: BTreeMap<String, ContractTags> = BTreeMap::new();
+    let mut contract = String::new();
+    let mut doc: Vec<String> = Vec::new();
+    let mut in_block = false;
+    // A contract or function declaration that may span multiple lines, along with its doc
+    let mut declaration: Option<(String, Vec<String>)> = None;
+
+    for line in source.lines() {
+        let line = line.trim();
+
+        if let Some((header, _)) = declaration.as_mut() {
+            header.push(' ');
+            header.push_str(line);
+        } else if in_block {
+            match line.split_once("*/") {
+                Some((comment, _)) => {
+                    doc.push(comment.trim_start_matches('*').trim().to_string());
+                    in_block = false;
+                }
+                None => doc.push(line.trim_start_matches('*').trim().to_string()),
+            }
+            continue
+        } else if let Some(comment) = line.strip_prefix("///") {
+            doc.push(comment.trim().to_string());
+            continue
+        } else if let Some(comment) = line.strip_prefix("/**") {
+            match comment.split_once("*/") {
+                Some((comment, _)) => doc.push(comment.trim().to_string()),
+                None => {
+                    doc.push(comment.trim().to_string());
+                    in_block = true;
+                }
+            }
+            continue
+        } else if line.is_empty() || line.starts_with("//") {
+            // Blank lines and regular comments do not detach doc comments
+            continue
+        } else if declared_contract(line).is_some() || line.starts_with("function ") {
+            declaration = Some((line.to_string(), std::mem::take(&mut doc)));
+        } else {
+            doc.clear();
+            continue
+        }
+
+        // Handle the declaration once its header is complete
+        let (header, header_doc) = declaration.as_ref().expect("declaration was started");
+        if let Some(name) = declared_contract(header) {
+            if let Some((header, _)) = header.split_once('{') {
+                contract = name.to_string();
+                contracts.entry(contract.clone()).or_default().bases = inherited_contracts(header);
+                declaration = None;
+            }
+        } else if let Some(signature) = function_signature(header) {
+            let tags = parse_doc(header_doc);
+            if !tags.is_empty() {
+                contracts
+                    .entry(contract.clone())
+                    .or_default()
+                    .functions
+                    .entry(signature)
+                    .or_default()
+                    .extend(tags);
+            }
+            declaration = None;
+        }
+    }
+
+    contracts
+}
+
+/// Resolves the custom NatSpec tags of all functions of a contract, including the ones it
+/// inherits, keyed by the signatures in its ABI.
+///
+/// `sources` are the parsed tags of every source file of the project. Base contracts are looked
+/// up in the file of the contract first and in all other files after that. Functions of more
+/// derived contracts take precedence, following the order of the inheritance list.
+pub fn resolve_tags(
+    sources: &BTreeMap<String, BTreeMap<String, ContractTags>>,
+    source: &str,
+    name: &str,
+    abi: &Abi,
+) -> FunctionTags {
+    let mut tags = FunctionTags::new();
+    let mut visited = BTreeSet::new();
+    collect_inherited(sources, source, name, &mut visited, &mut tags);
+
+    abi.functions()
+        .filter_map(|func| {
+            let signature = func.signature();
+            let func_tags = tags.get(&signature).or_else(|| {
+                // Signatures with structs, enums or contracts only match by name and arity
+                let mut candidates = tags.iter().filter(|(candidate, _)| {
+                    candidate.split('(').next() == Some(&*func.name) &&
+                        parameter_count(candidate) == func.inputs.len()
+                });
+                match (candidates.next(), candidates.next()) {
+                    (Some((_, func_tags)), None) => Some(func_tags),
+                    _ => None,
+                }
+            })?;
+            Some((signature, func_tags.clone()))
+        })
+        .collect()
+}
+
+/// Adds the tags of the functions of the contract and its bases that are not overridden yet
+fn collect_inherited(
+    sources: &BTreeMap<String, BTreeMap<String, ContractTags>>,
+    source: &str,
+    name: &str,
+    visited: &mut BTreeSet<(String, String)>,
+    tags: &mut FunctionTags,
+) {
+    let found = sources
+        .get(source)
+        .and_then(|contracts| Some((source, contracts.get(name)?)))
+        .or_else(|| {
+            sources
+                .iter()
+                .find_map(|(source, contracts)| Some((source.as_str(), contracts.get(name)?)))
+        });
+    let (source, contract) = match found {
+        Some(found) => found,
+        None => return,
+    };
+    if !visited.insert((source.to_string(), name.to_string())) {
+        return
+    }
+
+    for (signature, func_tags) in &contract.functions {
+        tags.entry(signature.clone()).or_insert_with(|| func_tags.clone());
+    }
+    for base in contract.bases.iter().rev() {
+        collect_inherited(sources, source, base, visited, tags);
+    }
+}
+
+/// Returns the name of the contract, library or interface declared on this line, if any
+fn declared_contract(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    let mut word = words.next()?;
+    if word == "abstract" {
+        word = words.next()?;
+    }
+    match word {
+        "contract" | "library" | "interface" => {
+            words.next().map(|name| name.split('{').next().unwrap_or(name))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the names of the contracts listed after `is` in a contract declaration
+fn inherited_contracts(header: &str) -> Vec<String> {
+    let header = without_parentheses(header);
+    match header.split_once(" is ") {
+        Some((_, bases)) => bases
+            .split(',')
+            .map(|base| base.trim().to_string())
+            .filter(|base| !base.is_empty())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Returns the signature of the function declared in the header, once its parameter list is
+/// complete
+fn function_signature(header: &str) -> Option<String> {
+    let header = header.strip_prefix("function ")?;
+    let (name, rest) = header.split_once('(')?;
+
+    // Find the parenthesis that closes the parameter list
+    let mut depth = 1;
+    let end = rest.char_indices().find_map(|(i, c)| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        (depth == 0).then(|| i)
+    })?;
+
+    let params = split_top_level(&rest[..end])
+        .into_iter()
+        .filter_map(|param| param.split_whitespace().next().map(canonical_type))
+        .collect::<Vec<_>>();
+    Some(format!("{}({})", name.trim(), params.join(",")))
+}
+
+/// Returns the canonical ABI name of an elementary type, e.g. `uint256[]` for `uint[]`
+fn canonical_type(ty: &str) -> String {
+    let (base, dimensions) = ty.split_at(ty.find('[').unwrap_or(ty.len()));
+    let base = match base {
+        "uint" => "uint256",
+        "int" => "int256",
+        "byte" => "bytes1",
+        "fixed" => "fixed128x18",
+        "ufixed" => "ufixed128x18",
+        base => base,
+    };
+    format!("{base}{dimensions}")
+}
+
+/// Returns the number of parameters in a function signature
+fn parameter_count(signature: &str) -> usize {
+    signature
+        .split_once('(')
+        .map(|(_, params)| {
+            let params = params.strip_suffix(')').unwrap_or(params);
+            split_top_level(params).len()
+        })
+        .unwrap_or_default()
+}
+
+/// Splits a comma separated list, ignoring the commas within parentheses
+fn split_top_level(list: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                items.push(list[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(list[start..].trim());
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+/// Removes everything within parentheses, e.g. the constructor arguments of base contracts
+fn without_parentheses(s: &str) -> String {
+    let mut depth = 0usize;
+    s.chars()
+        .filter(|c| {
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth = depth.saturating_sub(1);
+                    return false
+                }
+                _ => {}
+            }
+            depth == 0
+        })
+        .collect()
+}
+
+/// Parses the `@custom:` tags out of the lines of a doc comment.
+///
+/// Tag values may continue on the following lines until the next tag starts.
+fn parse_doc(doc: &[String]) -> Vec<CustomTag> {
+    let mut tags: Vec<CustomTag> = Vec::new();
+    let mut in_custom_tag = false;
+
+    for line in doc {
+        if let Some(tag) = line.strip_prefix("@custom:") {
+            let (name, value) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+            tags.push(CustomTag { name: name.to_string(), value: value.trim().to_string() });
+            in_custom_tag = true;
+        } else if line.starts_with('@') {
+            in_custom_tag = false;
+        } else if in_custom_tag && !line.is_empty() {
+            let tag = tags.last_mut().expect("custom tag was started");
+            if !tag.value.is_empty() {
+                tag.value.push(' ');
+            }
+            tag.value.push_str(line);
+        }
+    }
+
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_custom_tags() {
+        let source = r#"
+contract Base {
+    /// @custom:fuzz amount range 1..100
+    function testBase(uint256 amount) public {}
+}
+
+abstract contract FuzzTest is Base {
+    /// @notice bounded inputs
+    /// @custom:fuzz amount range 1..1e24
+    /// @custom:fuzz addr notzero
+    function testBounds(uint256 amount, address addr) public {}
+
+    /**
+     * @custom:fuzz x
+     *     notzero
+     * @param x some value
+     */
+    function testBlock(int8 x) public {}
+
+    /// @custom:fuzz detached notzero
+    uint256 value;
+
+    function testNoTags(uint256 detached) public {}
+}
+"#;
+        let tags = parse_custom_tags(source);
+
+        let tag = |name: &str, value: &str| CustomTag { name: name.into(), value: value.into() };
+        assert!(tags["Base"].bases.is_empty());
+        assert_eq!(
+            tags["Base"].functions["testBase(uint256)"],
+            vec![tag("fuzz", "amount range 1..100")]
+        );
+        assert_eq!(tags["FuzzTest"].bases, vec!["Base".to_string()]);
+        assert_eq!(
+            tags["FuzzTest"].functions["testBounds(uint256,address)"],
+            vec![tag("fuzz", "amount range 1..1e24"), tag("fuzz", "addr notzero")]
+        );
+        assert_eq!(tags["FuzzTest"].functions["testBlock(int8)"], vec![tag("fuzz", "x notzero")]);
+        assert!(!tags["FuzzTest"].functions.contains_key("testNoTags(uint256)"));
+    }
+
+    #[test]
+    fn can_resolve_inherited_tags() {
+        let base = r#"
+abstract contract Base {
+    /// @custom:fuzz amount range 1..100
+    function testInherited(uint256 amount) public {}
+
+    /// @custom:fuzz amount range 1..100
+    function testOverridden(uint256 amount) public virtual {}
+}
+"#;
+        let tests = r#"
+struct Order { uint256 amount; }
+
+contract Test is
+    Base(1, 2)
+{
+    /// @custom:fuzz amount range 5..10
+    function testOverridden(uint256 amount) public override {}
+
+    /// @custom:fuzz amount notzero
+    function testOverload(uint amount) public {}
+
+    /// @custom:fuzz owner notzero
+    function testOverload(
+        uint256 amount,
+        address owner
+    ) public {}
+
+    /// @custom:fuzz order.amount notzero
+    function testStruct(Order memory order) public {}
+}
+
+contract Other {
+    /// @custom:fuzz amount range 1..2
+    function testUntagged(uint256 amount) public {}
+}
+"#;
+        let sources = BTreeMap::from([
+            ("src/Base.sol".to_string(), parse_custom_tags(base)),
+            ("src/Test.t.sol".to_string(), parse_custom_tags(tests)),
+        ]);
+        assert_eq!(sources["src/Test.t.sol"]["Test"].bases, vec!["Base".to_string()]);
+
+        let abi = ethers::abi::parse_abi(&[
+            "function testInherited(uint256)",
+            "function testOverridden(uint256)",
+            "function testOverload(uint256)",
+            "function testOverload(uint256,address)",
+            "function testStruct((uint256))",
+            "function testUntagged(uint256)",
+        ])
+        .unwrap();
+        let tags = resolve_tags(&sources, "src/Test.t.sol", "Test", &abi);
+
+        let values = |signature: &str| {
+            tags[signature].iter().map(|tag| tag.value.as_str()).collect::<Vec<_>>()
+        };
+        assert_eq!(values("testInherited(uint256)"), vec!["amount range 1..100"]);
+        assert_eq!(values("testOverridden(uint256)"), vec!["amount range 5..10"]);
+        assert_eq!(values("testOverload(uint256)"), vec!["amount notzero"]);
+        assert_eq!(values("testOverload(uint256,address)"), vec!["owner notzero"]);
+        assert_eq!(values("testStruct((uint256))"), vec!["order.amount notzero"]);
+        // tags of other contracts in the same file do not apply
+        assert!(!tags.contains_key("testUntagged(uint256)"));
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{decode::decode_console_logs, SuiteResult, TestResult};
+use std::io::{self, Write};
+
+/// Reports the results of a test run in a machine readable format.
+///
+/// The results of each test contract are reported as soon as they are streamed from the
+/// [`MultiContractRunner`](crate::MultiContractRunner).
+pub trait Reporter {
+    /// Reports the results of the test contract with the given identifier
+    fn report_suite(&mut self, name: &str, suite: &SuiteResult) -> io::Result<()>;
+
+    /// Completes the report after all test contracts were reported
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Returns the failure message of a test, consisting of the revert reason and the counterexample
+fn failure_message(result: &TestResult) -> String {
+    match (&result.reason, &result.counterexample) {
+        (Some(reason), Some(counterexample)) => {
+            format!("{reason}. Counterexample: {counterexample}")
+        }
+        (None, Some(counterexample)) => format!("Counterexample: {counterexample}"),
+        (Some(reason), None) => reason.clone(),
+        (None, None) => "Test failed".to_string(),
+    }
+}
+
+/// Reports test results as JUnit XML.
+///
+/// Every test contract is reported as a `testsuite`, every test as a `testcase` of it.
+pub struct JunitReporter<W> {
+    out: W,
+    started: bool,
+}
+
+impl<W: Write> JunitReporter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, started: false }
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            writeln!(self.out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+            writeln!(self.out, "<testsuites>")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Reporter for JunitReporter<W> {
+    fn report_suite(&mut self, name: &str, suite: &SuiteResult) -> io::Result<()> {
+        self.start()?;
+
+        let failures = suite.test_results.values().filter(|result| !result.success).count();
+        writeln!(
+            self.out,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" time="{:.3}">"#,
+            xml_escape(name),
+            suite.len(),
+            failures,
+            suite.duration.as_secs_f64()
+        )?;
+        for (sig, result) in suite.test_results.iter() {
+            let logs = decode_console_logs(&result.logs);
+            writeln!(
+                self.out,
+                r#"    <testcase name="{}" classname="{}">"#,
+                xml_escape(sig),
+                xml_escape(name)
+            )?;
+            // the logs of failing tests are reported along with the failure
+            if !result.success {
+                writeln!(
+                    self.out,
+                    r#"      <failure message="{}">{}</failure>"#,
+                    xml_escape(&failure_message(result)),
+                    xml_escape(&logs.join("\n"))
+                )?;
+            } else if !logs.is_empty() {
+                writeln!(
+                    self.out,
+                    "      <system-out>{}</system-out>",
+                    xml_escape(&logs.join("\n"))
+                )?;
+            }
+            writeln!(self.out, "    </testcase>")?;
+        }
+        writeln!(self.out, "  </testsuite>")?;
+        self.out.flush()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.start()?;
+        writeln!(self.out, "</testsuites>")?;
+        self.out.flush()
+    }
+}
+
+/// Escapes the characters with special meaning in XML text and attribute values
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reports test results in the [Test Anything Protocol](https://testanything.org/tap-version-13-specification.html).
+///
+/// Every test is a test point named `<contract>::<test>`, failures come with a YAML block holding
+/// the failure message and the decoded logs. The plan is printed last, since the number of tests
+/// is only known once all test contracts ran.
+pub struct TapReporter<W> {
+    out: W,
+    started: bool,
+    count: usize,
+}
+
+impl<W: Write> TapReporter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, started: false, count: 0 }
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            writeln!(self.out, "TAP version 13")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Reporter for TapReporter<W> {
+    fn report_suite(&mut self, name: &str, suite: &SuiteResult) -> io::Result<()> {
+        self.start()?;
+
+        for (sig, result) in suite.test_results.iter() {
+            self.count += 1;
+            let status = if result.success { "ok" } else { "not ok" };
+            writeln!(self.out, "{} {} - {}::{}", status, self.count, name, sig)?;
+
+            if !result.success {
+                // JSON strings are valid YAML scalars, so we don't need to escape them ourselves
+                writeln!(self.out, "  ---")?;
+                writeln!(self.out, "  message: {}", json_string(&failure_message(result)))?;
+                let logs = decode_console_logs(&result.logs);
+                if !logs.is_empty() {
+                    writeln!(self.out, "  logs:")?;
+                    for log in logs {
+                        writeln!(self.out, "    - {}", json_string(&log))?;
+                    }
+                }
+                writeln!(self.out, "  ...")?;
+            }
+        }
+        self.out.flush()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.start()?;
+        writeln!(self.out, "1..{}", self.count)?;
+        self.out.flush()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    serde_json::to_string(s).expect("strings can always be serialized")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestKind;
+    use std::time::Duration;
+
+    fn suite() -> SuiteResult {
+        let result = |success: bool, reason: Option<&str>| TestResult {
+            success,
+            reason: reason.map(str::to_string),
+            counterexample: None,
+            logs: vec![],
+            kind: TestKind::Standard(0),
+            traces: vec![],
+            labeled_addresses: Default::default(),
+            fuzz_stats: None,
+            inputs: None,
+            cached: false,
+        };
+        SuiteResult::new(
+            Duration::from_millis(5),
+            [
+                ("testPass()".to_string(), result(true, None)),
+                ("testFail()".to_string(), result(false, Some("a < b"))),
+            ]
+            .into(),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn can_report_junit() {
+        let mut out = Vec::new();
+        let mut reporter = JunitReporter::new(&mut out);
+        reporter.report_suite("src/A.t.sol:ATest", &suite()).unwrap();
+        reporter.finish().unwrap();
+
+        let xml = String::from_utf8(out).unwrap();
+        assert_eq!(
+            xml,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="src/A.t.sol:ATest" tests="2" failures="1" time="0.005">
+    <testcase name="testFail()" classname="src/A.t.sol:ATest">
+      <failure message="a &lt; b"></failure>
+    </testcase>
+    <testcase name="testPass()" classname="src/A.t.sol:ATest">
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn can_report_tap() {
+        let mut out = Vec::new();
+        let mut reporter = TapReporter::new(&mut out);
+        reporter.report_suite("src/A.t.sol:ATest", &suite()).unwrap();
+        reporter.finish().unwrap();
+
+        let tap = String::from_utf8(out).unwrap();
+        assert_eq!(
+            tap,
+            r#"TAP version 13
+not ok 1 - src/A.t.sol:ATest::testFail()
+  ---
+  message: "a < b"
+  ...
+ok 2 - src/A.t.sol:ATest::testPass()
+1..2
+"#
+        );
+    }
+}
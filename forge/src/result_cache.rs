@@ -0,0 +1,246 @@
+use crate::{SuiteResult, TestResult};
+use ethers::{
+    prelude::ArtifactId,
+    types::{Bytes, H256},
+    utils::{id, keccak256},
+};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The name of the file the test results are cached in, relative to the cache directory
+pub const TEST_RESULTS_CACHE_FILENAME: &str = "test-results.json";
+
+/// Results of passing tests, cached between runs so tests of unchanged contracts do not need to
+/// be executed again.
+///
+/// Results are cached per test contract, under a key derived from everything that can influence
+/// the outcome of its tests, see [`cache_key`]. Fuzz tests are never cached, since every run
+/// explores different inputs, and neither are contracts that read files, see [`reads_files`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TestResultCache {
+    /// The location of the cache file
+    #[serde(skip)]
+    path: PathBuf,
+    /// `contract identifier -> cached results`
+    contracts: BTreeMap<String, CachedSuite>,
+}
+
+/// The cached results of a single test contract
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedSuite {
+    /// The key the results are valid for
+    key: H256,
+    /// `test signature -> TestResult`
+    results: BTreeMap<String, TestResult>,
+}
+
+impl TestResultCache {
+    /// Reads the cache from the given cache directory.
+    ///
+    /// Returns an empty cache if there is no cache file yet or if it can not be read, warning
+    /// about the latter.
+    pub fn read(cache_dir: impl AsRef<Path>) -> Self {
+        let path = cache_dir.as_ref().join(TEST_RESULTS_CACHE_FILENAME);
+        let mut cache = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str::<Self>(&content).unwrap_or_else(|err| {
+                tracing::warn!(?err, ?path, "ignoring unparsable test result cache");
+                Self::default()
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                tracing::warn!(?err, ?path, "ignoring unreadable test result cache");
+                Self::default()
+            }
+        };
+        cache.path = path;
+        cache
+    }
+
+    /// Writes the cache to the file it was read from.
+    ///
+    /// The cache is written to a temporary file first, which is then moved into place, so an
+    /// interrupted or concurrent run never leaves a partially written file behind.
+    pub fn write(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // a unique name for every write, so concurrent writes don't clobber each other's file
+        static WRITES: AtomicUsize = AtomicUsize::new(0);
+        let tmp = self.path.with_file_name(format!(
+            ".{TEST_RESULTS_CACHE_FILENAME}.{}.{}.tmp",
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(&tmp, &self.path).map_err(|err| {
+            let _ = fs::remove_file(&tmp);
+            err.into()
+        })
+    }
+
+    /// Returns the cached results of the given contract's tests that are still valid for `key`
+    pub fn get(&self, contract: &str, key: H256) -> BTreeMap<String, TestResult> {
+        match self.contracts.get(contract) {
+            Some(suite) if suite.key == key => suite
+                .results
+                .iter()
+                .map(|(sig, result)| (sig.clone(), TestResult { cached: true, ..result.clone() }))
+                .collect(),
+            _ => Default::default(),
+        }
+    }
+
+    /// Caches the passing, non-fuzz test results of the given suite under `key`.
+    ///
+    /// The cases of table-driven tests are not cached either, since they are only skipped if the
+    /// whole test is.
+    ///
+    /// Results cached under a different key are discarded.
+    pub fn insert(&mut self, contract: String, key: H256, suite: &SuiteResult) {
+        let cached = self
+            .contracts
+            .entry(contract)
+            .or_insert_with(|| CachedSuite { key, results: Default::default() });
+        if cached.key != key {
+            *cached = CachedSuite { key, results: Default::default() };
+        }
+
+        for (sig, result) in suite.test_results.iter() {
+            if result.success && !result.is_fuzz() && result.inputs.is_none() {
+                // traces and logs are only displayed for passing tests at higher verbosity levels
+                // in which case the cache is not used, so there's no need to store them
+                let result =
+                    TestResult { traces: vec![], logs: vec![], cached: false, ..result.clone() };
+                cached.results.insert(sig.clone(), result);
+            } else {
+                cached.results.remove(sig);
+            }
+        }
+    }
+}
+
+/// Computes the key the cached test results of a contract are stored under.
+///
+/// The key commits to the contract's creation code, which includes the creation code of all
+/// contracts it deploys, the code of the libraries it is linked against and the `context`, which
+/// should identify the environment the tests are executed in, like the `EvmOpts`, the pinned
+/// fork block and the [`artifacts_hash`] of the project.
+pub fn cache_key(deploy_code: &Bytes, libs: &[Bytes], context: &[u8]) -> H256 {
+    let mut preimage = Vec::with_capacity(deploy_code.len() + context.len() + 32 * libs.len());
+    preimage.extend_from_slice(&keccak256(deploy_code));
+    for lib in libs {
+        preimage.extend_from_slice(&keccak256(lib));
+    }
+    preimage.extend_from_slice(context);
+    H256(keccak256(preimage))
+}
+
+/// Hashes the code of all compiled artifacts.
+///
+/// Tests can read the code of any artifact with `vm.getCode`, so their cached results are
+/// invalidated whenever any contract of the project changes.
+pub fn artifacts_hash<'a>(artifacts: impl IntoIterator<Item = (&'a ArtifactId, &'a [u8])>) -> H256 {
+    let mut preimage = Vec::new();
+    for (id, code) in artifacts {
+        preimage.extend_from_slice(&keccak256(id.identifier()));
+        preimage.extend_from_slice(&keccak256(code));
+    }
+    H256(keccak256(preimage))
+}
+
+/// Whether the code may call the `getCode` cheatcode, which can read arbitrary files that are not
+/// covered by the [`cache_key`].
+///
+/// This checks whether the selector of the cheatcode occurs anywhere in the code, so it can report
+/// false positives, which only cause the results to not be cached.
+pub fn reads_files(code: &[u8]) -> bool {
+    let selector = id("getCode(string)");
+    code.windows(selector.len()).any(|window| window == selector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestKind;
+    use std::time::Duration;
+
+    fn result(success: bool, kind: TestKind) -> TestResult {
+        TestResult {
+            success,
+            reason: None,
+            counterexample: None,
+            logs: vec![],
+            kind,
+            traces: vec![],
+            labeled_addresses: Default::default(),
+            fuzz_stats: None,
+            inputs: None,
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn caches_passing_unit_tests() {
+        let key = cache_key(&Bytes::from(vec![1, 2, 3]), &[], b"ctx");
+        let suite = SuiteResult::new(
+            Duration::ZERO,
+            [
+                ("testPass()".to_string(), result(true, TestKind::Standard(100))),
+                ("testFail()".to_string(), result(false, TestKind::Standard(100))),
+            ]
+            .into(),
+            vec![],
+        );
+
+        let dir = std::env::temp_dir().join("forge-test-result-cache");
+        let mut cache = TestResultCache::read(&dir);
+        cache.insert("Contract".to_string(), key, &suite);
+        cache.write().unwrap();
+
+        let cache = TestResultCache::read(&dir);
+        let cached = cache.get("Contract", key);
+        assert_eq!(cached.keys().collect::<Vec<_>>(), vec!["testPass()"]);
+        assert!(cached["testPass()"].cached);
+
+        // a different key invalidates the cached results
+        let other = cache_key(&Bytes::from(vec![1, 2, 3]), &[Bytes::from(vec![4])], b"ctx");
+        assert!(cache.get("Contract", other).is_empty());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn ignores_unparsable_cache() {
+        let dir = std::env::temp_dir().join("forge-test-result-cache-unparsable");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(TEST_RESULTS_CACHE_FILENAME), "{\"contracts\": {").unwrap();
+
+        let key = cache_key(&Bytes::from(vec![1, 2, 3]), &[], b"ctx");
+        let mut cache = TestResultCache::read(&dir);
+        assert!(cache.get("Contract", key).is_empty());
+
+        let suite = SuiteResult::new(
+            Duration::ZERO,
+            [("testPass()".to_string(), result(true, TestKind::Standard(100)))].into(),
+            vec![],
+        );
+        cache.insert("Contract".to_string(), key, &suite);
+        cache.write().unwrap();
+        assert_eq!(TestResultCache::read(&dir).get("Contract", key).len(), 1);
+        // no temporary files are left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn detects_file_reads() {
+        // PUSH4 getCode(string)
+        assert!(reads_files(&[0x60, 0x00, 0x63, 0x8d, 0x1c, 0xc9, 0x25, 0x00]));
+        assert!(!reads_files(&[0x60, 0x00, 0x63, 0x8d, 0x1c, 0xc9]));
+    }
+}
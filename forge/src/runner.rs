@@ -1,12 +1,15 @@
-use crate::TestFilter;
+use crate::{natspec::FunctionTags, TestFilter};
 use ethers::{
-    abi::{Abi, Function, RawLog},
-    types::{Address, Bytes, U256},
+    abi::{Abi, Function, ParamType, RawLog, Token},
+    types::{Address, Bytes, H256, U256},
 };
 use eyre::Result;
 use foundry_evm::{
     executor::{CallResult, DatabaseRef, DeployResult, EvmError, Executor},
-    fuzz::{CounterExample, FuzzedCases, FuzzedExecutor},
+    fuzz::{
+        CounterExample, DifferentialTarget, FuzzConstraints, FuzzFixtures, FuzzStats, FuzzedCases,
+        FuzzedExecutor,
+    },
     trace::{CallTraceArena, TraceKind},
     CALLER,
 };
@@ -16,11 +19,12 @@ use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
     fmt,
+    path::Path,
     time::{Duration, Instant},
 };
 
 /// Results and duration for a set of tests included in the same test contract
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SuiteResult {
     /// Total duration of the test run for this block of tests
     pub duration: Duration,
@@ -76,6 +80,18 @@ pub struct TestResult {
 
     /// Labeled addresses
     pub labeled_addresses: BTreeMap<Address, String>,
+
+    /// Statistics of the fuzz run, if this was a fuzz test
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzz_stats: Option<FuzzStats>,
+
+    /// The formatted inputs, if this is a case of a table-driven test
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<Vec<String>>,
+
+    /// Whether this result was read from the test result cache instead of executing the test
+    #[serde(default)]
+    pub cached: bool,
 }
 
 impl TestResult {
@@ -155,6 +171,8 @@ pub struct TestSetup {
     pub setup_failed: bool,
     /// The reason the setup failed
     pub reason: Option<String>,
+    /// The arrays returned by the `fixture_<param>()` functions, keyed by parameter name
+    pub fixtures: BTreeMap<String, Result<Vec<Token>, String>>,
 }
 
 pub struct ContractRunner<'a, DB: DatabaseRef> {
@@ -174,6 +192,21 @@ pub struct ContractRunner<'a, DB: DatabaseRef> {
     pub initial_balance: U256,
     /// The address which will be used as the `from` field in all EVM calls
     pub sender: Address,
+
+    /// The custom NatSpec tags of the test functions
+    pub natspec: &'a FunctionTags,
+
+    /// The wall-clock time after which a fuzz campaign is stopped and fails
+    pub fuzz_timeout: Option<Duration>,
+
+    /// The seed of every fuzz campaign, if it is fixed
+    pub fuzz_seed: Option<H256>,
+
+    /// The name of the `setUp_<name>()` variant to call after `setUp`, if any
+    pub setup_variant: Option<String>,
+
+    /// The root of the project, which external differential reference programs run in
+    pub root: Option<&'a Path>,
 }
 
 impl<'a, DB: DatabaseRef> ContractRunner<'a, DB> {
@@ -186,6 +219,7 @@ impl<'a, DB: DatabaseRef> ContractRunner<'a, DB> {
         sender: Option<Address>,
         errors: Option<&'a Abi>,
         predeploy_libs: &'a [Bytes],
+        natspec: &'a FunctionTags,
     ) -> Self {
         Self {
             executor,
@@ -195,6 +229,11 @@ impl<'a, DB: DatabaseRef> ContractRunner<'a, DB> {
             sender: sender.unwrap_or_default(),
             errors,
             predeploy_libs,
+            natspec,
+            fuzz_timeout: None,
+            fuzz_seed: None,
+            setup_variant: None,
+            root: None,
         }
     }
 }
@@ -259,13 +298,56 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             traces.extend(setup_traces.map(|traces| (TraceKind::Setup, traces)).into_iter());
             logs.extend_from_slice(&setup_logs);
 
-            TestSetup { address, logs, traces, labeled_addresses, setup_failed, reason }
+            TestSetup {
+                address,
+                logs,
+                traces,
+                labeled_addresses,
+                setup_failed,
+                reason,
+                fixtures: BTreeMap::new(),
+            }
         } else {
             TestSetup { address, logs, traces, ..Default::default() }
         })
     }
 
+    /// Calls the `setUp_<name>()` variant of the deployed test contract, after `setUp`
+    fn setup_variant(&mut self, name: &str, setup: &mut TestSetup) {
+        tracing::trace!(variant = name, "setting up");
+        let func = format!("setUp_{name}()");
+        let (setup_logs, setup_traces, labeled_addresses) =
+            match self.executor.call_committing::<(), _, _>(
+                *CALLER,
+                setup.address,
+                func.as_str(),
+                (),
+                0.into(),
+                self.errors,
+            ) {
+                Ok(CallResult { traces, labels, logs, .. }) => (logs, traces, labels),
+                Err(EvmError::Execution { traces, labels, logs, reason, .. }) => {
+                    setup.setup_failed = true;
+                    setup.reason = Some(format!("Setup failed: {reason}"));
+                    (logs, traces, labels)
+                }
+                Err(e) => {
+                    setup.setup_failed = true;
+                    setup.reason = Some(format!("Setup failed: {}", &e.to_string()));
+                    (Vec::new(), None, BTreeMap::new())
+                }
+            };
+        setup.traces.extend(setup_traces.map(|traces| (TraceKind::Setup, traces)).into_iter());
+        setup.logs.extend_from_slice(&setup_logs);
+        setup.labeled_addresses.extend(labeled_addresses);
+    }
+
     /// Runs all tests for a contract whose names match the provided regular expression
+    ///
+    /// The contract is deployed and `setUp` is run once, followed by the `setUp_<name>()` variant
+    /// of the runner, if any. The tests then run in parallel, each on its own copy of the
+    /// post-setup state, so the results do not depend on the order in which the tests are
+    /// scheduled or on the number of threads.
     pub fn run_tests(
         &mut self,
         filter: &impl TestFilter,
@@ -305,6 +387,9 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
                         kind: TestKind::Standard(0),
                         traces: vec![],
                         labeled_addresses: BTreeMap::new(),
+                        fuzz_stats: None,
+                        inputs: None,
+                        cached: false,
                     },
                 )]
                 .into(),
@@ -312,7 +397,12 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             ))
         }
 
-        let setup = self.setup(needs_setup)?;
+        let mut setup = self.setup(needs_setup)?;
+        if let Some(variant) = self.setup_variant.clone() {
+            if !setup.setup_failed {
+                self.setup_variant(&variant, &mut setup);
+            }
+        }
         if setup.setup_failed {
             // The setup failed, so we return a single test result for `setUp`
             return Ok(SuiteResult::new(
@@ -327,6 +417,9 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
                         kind: TestKind::Standard(0),
                         traces: setup.traces,
                         labeled_addresses: setup.labeled_addresses,
+                        fuzz_stats: None,
+                        inputs: None,
+                        cached: false,
                     },
                 )]
                 .into(),
@@ -347,20 +440,30 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             .map(|func| (func, func.name.starts_with("testFail")))
             .collect();
 
+        setup.fixtures = self.fixtures(setup.address);
+
+        // Tests never commit to the executor's database, so they can share the post-setup state.
+        // Tests whose parameters all have fixtures are table-driven, all other tests with
+        // parameters are fuzzed
         let test_results = tests
             .par_iter()
             .filter_map(|(func, should_fail)| {
-                let result = if func.inputs.is_empty() {
-                    Some(self.run_test(func, *should_fail, setup.clone()))
+                if func.inputs.is_empty() {
+                    let result = self.run_test(func, *should_fail, setup.clone(), vec![]);
+                    Some(result.map(|result| vec![(func.signature(), result)]))
+                } else if func.inputs.iter().all(|input| setup.fixtures.contains_key(&input.name)) {
+                    Some(self.run_table_test(func, *should_fail, setup.clone()))
                 } else {
                     fuzzer.as_ref().map(|fuzzer| {
                         self.run_fuzz_test(func, *should_fail, fuzzer.clone(), setup.clone())
+                            .map(|result| vec![(func.signature(), result)])
                     })
-                };
-
-                result.map(|result| Ok((func.signature(), result?)))
+                }
             })
-            .collect::<Result<BTreeMap<_, _>>>()?;
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect::<BTreeMap<_, _>>();
 
         let duration = start.elapsed();
         if !test_results.is_empty() {
@@ -381,57 +484,100 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         func: &Function,
         should_fail: bool,
         setup: TestSetup,
+        args: Vec<Token>,
     ) -> Result<TestResult> {
         let TestSetup { address, mut logs, mut traces, mut labeled_addresses, .. } = setup;
 
-        // Run unit test
-        let start = Instant::now();
-        let (reverted, reason, gas, stipend, execution_traces, state_changeset) = match self
-            .executor
-            .call::<(), _, _>(self.sender, address, func.clone(), (), 0.into(), self.errors)
-        {
-            Ok(CallResult {
-                reverted,
-                gas,
-                stipend,
-                logs: execution_logs,
-                traces: execution_trace,
-                labels: new_labels,
-                state_changeset,
-                ..
-            }) => {
-                labeled_addresses.extend(new_labels);
-                logs.extend(execution_logs);
-                (reverted, None, gas, stipend, execution_trace, state_changeset)
-            }
-            Err(EvmError::Execution {
-                reverted,
-                reason,
-                gas,
-                stipend,
-                logs: execution_logs,
-                traces: execution_trace,
-                labels: new_labels,
-                state_changeset,
-                ..
-            }) => {
-                labeled_addresses.extend(new_labels);
-                logs.extend(execution_logs);
-                (reverted, Some(reason), gas, stipend, execution_trace, state_changeset)
-            }
+        let gas_limit = match self.gas_limit(func) {
+            Ok(gas_limit) => gas_limit,
             Err(err) => {
-                tracing::error!(?err);
-                return Err(err.into())
+                return Ok(TestResult {
+                    success: false,
+                    reason: Some(format!("Invalid gas limit: {err}")),
+                    counterexample: None,
+                    logs,
+                    kind: TestKind::Standard(0),
+                    traces,
+                    labeled_addresses,
+                    fuzz_stats: None,
+                    inputs: None,
+                    cached: false,
+                })
             }
         };
+
+        // Run unit test
+        let start = Instant::now();
+        let (reverted, reason, gas, stipend, execution_traces, state_changeset, timed_out) =
+            match self.executor.call_with_gas_limit::<(), _, _>(
+                self.sender,
+                address,
+                func.clone(),
+                Token::Tuple(args),
+                0.into(),
+                self.errors,
+                gas_limit,
+            ) {
+                Ok(CallResult {
+                    reverted,
+                    gas,
+                    stipend,
+                    logs: execution_logs,
+                    traces: execution_trace,
+                    labels: new_labels,
+                    state_changeset,
+                    timed_out,
+                    ..
+                }) => {
+                    labeled_addresses.extend(new_labels);
+                    logs.extend(execution_logs);
+                    (reverted, None, gas, stipend, execution_trace, state_changeset, timed_out)
+                }
+                Err(EvmError::Execution {
+                    reverted,
+                    reason,
+                    gas,
+                    stipend,
+                    logs: execution_logs,
+                    traces: execution_trace,
+                    labels: new_labels,
+                    state_changeset,
+                    timed_out,
+                    ..
+                }) => {
+                    labeled_addresses.extend(new_labels);
+                    logs.extend(execution_logs);
+                    (
+                        reverted,
+                        Some(reason),
+                        gas,
+                        stipend,
+                        execution_trace,
+                        state_changeset,
+                        timed_out,
+                    )
+                }
+                Err(err) => {
+                    tracing::error!(?err);
+                    return Err(err.into())
+                }
+            };
         traces.extend(execution_traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
 
-        let success = self.executor.is_success(
-            setup.address,
-            reverted,
-            state_changeset.expect("we should have a state changeset"),
-            should_fail,
-        );
+        // A call halted by the executor's timeout fails as if it ran out of gas, so we report the
+        // timeout instead
+        let (success, reason) = match self.executor.timeout().filter(|_| timed_out) {
+            Some(timeout) => (false, Some(format!("Test timed out after {timeout:?}"))),
+            None => {
+                let success = self.executor.is_success(
+                    setup.address,
+                    reverted,
+                    state_changeset.expect("we should have a state changeset"),
+                    should_fail,
+                );
+                (success, reason)
+            }
+        };
 
         // Record test execution time
         tracing::debug!(
@@ -448,9 +594,55 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             kind: TestKind::Standard(gas.overflowing_sub(stipend).0),
             traces,
             labeled_addresses,
+            fuzz_stats: None,
+            inputs: None,
+            cached: false,
         })
     }
 
+    /// Runs a table-driven test once for every case of the fixtures of its parameters.
+    ///
+    /// The `i`-th case is called with the `i`-th value of every fixture and is reported as
+    /// `<signature> #<i>`.
+    pub fn run_table_test(
+        &self,
+        func: &Function,
+        should_fail: bool,
+        setup: TestSetup,
+    ) -> Result<Vec<(String, TestResult)>> {
+        let cases = match table_cases(func, &setup.fixtures) {
+            Ok(cases) => cases,
+            Err(err) => {
+                return Ok(vec![(
+                    func.signature(),
+                    TestResult {
+                        success: false,
+                        reason: Some(format!("Invalid fixture: {err}")),
+                        counterexample: None,
+                        logs: setup.logs,
+                        kind: TestKind::Standard(0),
+                        traces: setup.traces,
+                        labeled_addresses: setup.labeled_addresses,
+                        fuzz_stats: None,
+                        inputs: None,
+                        cached: false,
+                    },
+                )])
+            }
+        };
+
+        cases
+            .into_iter()
+            .enumerate()
+            .map(|(i, args)| {
+                let inputs = foundry_utils::format_tokens(&args).collect();
+                let mut result = self.run_test(func, should_fail, setup.clone(), args)?;
+                result.inputs = Some(inputs);
+                Ok((format!("{} #{}", func.signature(), i), result))
+            })
+            .collect()
+    }
+
     #[tracing::instrument(name = "fuzz-test", skip_all, fields(name = %func.signature(), %should_fail))]
     pub fn run_fuzz_test(
         &self,
@@ -461,14 +653,51 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
     ) -> Result<TestResult> {
         let TestSetup { address, mut logs, mut traces, mut labeled_addresses, .. } = setup;
 
+        // Collect the input constraints declared with `@custom:fuzz` along with the fixtures of
+        // the parameters, the reference implementation declared with `@custom:differential` and
+        // the gas limit declared with `@custom:gas-limit`
+        let config = self
+            .fuzz_constraints(func)
+            .map_err(|err| format!("Invalid fuzz constraint: {err}"))
+            .and_then(|constraints| {
+                let fixtures = fuzz_fixtures(func, &setup.fixtures)
+                    .map_err(|err| format!("Invalid fixture: {err}"))?;
+                let differential = self
+                    .differential_target(func, should_fail)
+                    .map_err(|err| format!("Invalid differential target: {err}"))?;
+                let gas_limit =
+                    self.gas_limit(func).map_err(|err| format!("Invalid gas limit: {err}"))?;
+                Ok((constraints, fixtures, differential, gas_limit))
+            });
+        let (constraints, fixtures, differential, gas_limit) = match config {
+            Ok(config) => config,
+            Err(err) => {
+                return Ok(TestResult {
+                    success: false,
+                    reason: Some(err),
+                    counterexample: None,
+                    logs,
+                    kind: TestKind::Fuzz(FuzzedCases::new(vec![])),
+                    traces,
+                    labeled_addresses,
+                    fuzz_stats: None,
+                    inputs: None,
+                    cached: false,
+                })
+            }
+        };
+
         // Run fuzz test
         let start = Instant::now();
-        let mut result = FuzzedExecutor::new(&self.executor, runner, self.sender).fuzz(
-            func,
-            address,
-            should_fail,
-            self.errors,
-        );
+        let mut fuzzer = FuzzedExecutor::new(&self.executor, runner, self.sender)
+            .with_gas_limit(gas_limit)
+            .with_fixtures(fixtures)
+            .with_timeout(self.fuzz_timeout)
+            .with_seed(self.fuzz_seed);
+        if let Some(target) = differential {
+            fuzzer = fuzzer.with_differential(target);
+        }
+        let mut result = fuzzer.fuzz(func, address, should_fail, self.errors, &constraints);
 
         // Record logs, labels and traces
         logs.append(&mut result.logs);
@@ -489,6 +718,212 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             kind: TestKind::Fuzz(result.cases),
             traces,
             labeled_addresses,
+            fuzz_stats: Some(result.stats),
+            inputs: None,
+            cached: false,
         })
     }
+
+    /// Returns the gas limit to call the test function with.
+    ///
+    /// This is the gas limit the function declares with `@custom:gas-limit <gas>`, if any, and the
+    /// executor's otherwise.
+    fn gas_limit(&self, func: &Function) -> Result<U256, String> {
+        let mut tags = self.natspec.get(&func.signature()).into_iter().flatten();
+        let value = match tags.find(|tag| tag.name == "gas-limit") {
+            Some(tag) => &tag.value,
+            None => return Ok(self.executor.gas_limit()),
+        };
+
+        U256::from_dec_str(&value.replace('_', ""))
+            .ok()
+            .filter(|gas_limit| !gas_limit.is_zero() && *gas_limit <= u64::MAX.into())
+            .ok_or_else(|| format!("`{value}` is not a valid gas limit"))
+    }
+
+    /// Calls the `fixture_<param>()` functions of the deployed test contract and collects the
+    /// arrays they return, keyed by parameter name.
+    fn fixtures(&self, address: Address) -> BTreeMap<String, Result<Vec<Token>, String>> {
+        self.contract
+            .functions()
+            .filter_map(|func| {
+                let param = func.name.strip_prefix("fixture_")?;
+                Some((param.to_string(), self.call_fixture(func, address)))
+            })
+            .collect()
+    }
+
+    /// Calls a fixture function, which must not take any parameters and return a single array
+    fn call_fixture(&self, func: &Function, address: Address) -> Result<Vec<Token>, String> {
+        if !func.inputs.is_empty() {
+            return Err(format!("`{}` must not take any parameters", func.signature()))
+        }
+
+        let calldata = func.encode_input(&[]).map_err(|err| err.to_string())?;
+        let call = self
+            .executor
+            .call_raw(self.sender, address, calldata.into(), 0.into())
+            .map_err(|err| err.to_string())?;
+        if call.reverted {
+            let reason = foundry_utils::decode_revert(call.result.as_ref(), self.errors)
+                .unwrap_or_else(|_| "unknown reason".to_string());
+            return Err(format!("`{}` reverted: {reason}", func.signature()))
+        }
+
+        match func.decode_output(&call.result).map_err(|err| err.to_string())?.as_slice() {
+            [Token::Array(values)] | [Token::FixedArray(values)] => Ok(values.clone()),
+            _ => Err(format!("`{}` must return a single array", func.signature())),
+        }
+    }
+
+    /// Parses the `@custom:fuzz` annotations of the function into fuzz input constraints
+    fn fuzz_constraints(&self, func: &Function) -> Result<FuzzConstraints, String> {
+        let mut constraints = FuzzConstraints::default();
+        for tag in self.natspec.get(&func.signature()).into_iter().flatten() {
+            if tag.name == "fuzz" {
+                constraints.add_annotation(&tag.value)?;
+            }
+        }
+        constraints.validate(func)?;
+        Ok(constraints)
+    }
+
+    /// Parses the `@custom:differential` annotation of the function, if any.
+    ///
+    /// The annotation either names another function of the test contract with the same inputs,
+    /// e.g. `@custom:differential mulDivYul`, or an external program, e.g.
+    /// `@custom:differential ffi python3 ref.py`. External programs run in the project root.
+    ///
+    /// Tests that are expected to fail can not be differential, since they pass when both
+    /// implementations behave the same.
+    fn differential_target(
+        &self,
+        func: &Function,
+        should_fail: bool,
+    ) -> Result<Option<DifferentialTarget>, String> {
+        let mut tags = self.natspec.get(&func.signature()).into_iter().flatten();
+        let value = match tags.find(|tag| tag.name == "differential") {
+            Some(tag) => &tag.value,
+            None => return Ok(None),
+        };
+        if should_fail {
+            return Err(format!("`{}` is expected to fail and can not be differential", func.name))
+        }
+
+        let mut args = value.split_whitespace().map(str::to_string);
+        match args.next() {
+            Some(ffi) if ffi == "ffi" => {
+                let args = args.collect::<Vec<_>>();
+                if args.is_empty() {
+                    return Err("missing ffi command".to_string())
+                }
+                if !self.executor.ffi_enabled() {
+                    return Err(
+                        "FFI disabled: run again with `--ffi` to compare against external programs"
+                            .to_string(),
+                    )
+                }
+                Ok(Some(DifferentialTarget::Ffi { args, root: self.root.map(Path::to_path_buf) }))
+            }
+            Some(name) => {
+                let inputs = func.inputs.iter().map(|input| &input.kind).collect::<Vec<_>>();
+                self.contract
+                    .functions_by_name(&name)
+                    .map_err(|_| format!("unknown function `{name}`"))?
+                    .iter()
+                    .find(|other| {
+                        other.inputs.iter().map(|input| &input.kind).collect::<Vec<_>>() == inputs
+                    })
+                    .map(|other| Some(DifferentialTarget::Function(other.clone())))
+                    .ok_or_else(|| {
+                        format!("`{name}` does not take the same inputs as `{}`", func.name)
+                    })
+            }
+            None => Err("missing function name or ffi command".to_string()),
+        }
+    }
+}
+
+/// Returns the names of the `setUp_<name>()` variants of the test contract.
+///
+/// The tests of a contract with variants are run once for each of them, on a freshly deployed
+/// contract that is set up by `setUp` followed by the variant. Their results are reported as
+/// `<signature> [<name>]`.
+pub(crate) fn setup_variants(contract: &Abi) -> Vec<&str> {
+    contract
+        .functions()
+        .filter(|func| func.inputs.is_empty())
+        .filter_map(|func| func.name.strip_prefix("setUp_"))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Returns the fixture of the given parameter, checking that all of its values have the type of
+/// the parameter
+fn param_fixture<'a>(
+    fixtures: &'a BTreeMap<String, Result<Vec<Token>, String>>,
+    name: &str,
+    kind: &ParamType,
+) -> Result<Option<&'a [Token]>, String> {
+    let values = match fixtures.get(name) {
+        Some(values) => values.as_ref().map_err(Clone::clone)?,
+        None => return Ok(None),
+    };
+    if !values.iter().all(|value| value.type_check(kind)) {
+        return Err(format!("the fixture of `{name}` returns values that are not of type `{kind}`"))
+    }
+    Ok(Some(values.as_slice()))
+}
+
+/// Zips the fixtures of the function's parameters into the arguments of every case
+fn table_cases(
+    func: &Function,
+    fixtures: &BTreeMap<String, Result<Vec<Token>, String>>,
+) -> Result<Vec<Vec<Token>>, String> {
+    let mut columns = Vec::with_capacity(func.inputs.len());
+    for input in &func.inputs {
+        let values = param_fixture(fixtures, &input.name, &input.kind)?
+            .ok_or_else(|| format!("missing fixture for `{}`", input.name))?;
+        columns.push(values);
+    }
+
+    let cases = columns[0].len();
+    if cases == 0 {
+        return Err(format!("the fixture of `{}` is empty", func.inputs[0].name))
+    }
+    if let Some((input, _)) =
+        func.inputs.iter().zip(&columns).find(|(_, values)| values.len() != cases)
+    {
+        return Err(format!(
+            "the fixtures of `{}` and `{}` have different lengths",
+            func.inputs[0].name, input.name
+        ))
+    }
+
+    Ok((0..cases).map(|i| columns.iter().map(|values| values[i].clone()).collect()).collect())
+}
+
+/// Collects the fixtures of the function's integer parameters, which the fuzzer generates more
+/// often
+fn fuzz_fixtures(
+    func: &Function,
+    fixtures: &BTreeMap<String, Result<Vec<Token>, String>>,
+) -> Result<FuzzFixtures, String> {
+    let mut fuzz_fixtures = FuzzFixtures::default();
+    for input in &func.inputs {
+        if !matches!(input.kind, ParamType::Uint(_) | ParamType::Int(_)) {
+            continue
+        }
+        if let Some(values) = param_fixture(fixtures, &input.name, &input.kind)? {
+            let values = values
+                .iter()
+                .filter_map(|value| match value {
+                    Token::Uint(value) | Token::Int(value) => Some(*value),
+                    _ => None,
+                })
+                .collect();
+            fuzz_fixtures.insert(&input.name, values);
+        }
+    }
+    Ok(fuzz_fixtures)
 }
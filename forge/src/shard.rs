@@ -0,0 +1,216 @@
+use crate::SuiteResult;
+use ethers::utils::keccak256;
+use eyre::Result;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
+
+/// One of `count` disjoint shares of the test contracts, so that a test suite can be split across
+/// several machines.
+///
+/// Every shard only deploys and runs its own test contracts. The partition is deterministic, so
+/// that running all shards with the same filters runs every test contract exactly once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shard {
+    /// The 1-based index of this shard
+    pub index: usize,
+    /// The total number of shards
+    pub count: usize,
+    /// The durations of the test contracts in a previous run, used to balance the shards
+    pub weights: Option<BTreeMap<String, Duration>>,
+}
+
+impl Shard {
+    pub fn new(index: usize, count: usize) -> Self {
+        Self { index, count, weights: None }
+    }
+
+    /// Balances the shards by the durations of the test contracts in a previous run instead of
+    /// assigning the contracts by the hash of their identifier
+    #[must_use]
+    pub fn with_weights(mut self, weights: BTreeMap<String, Duration>) -> Self {
+        self.weights = Some(weights);
+        self
+    }
+
+    /// Returns the identifiers of the test contracts that belong to this shard.
+    ///
+    /// Without weights, a contract belongs to the shard its identifier hashes to. With weights,
+    /// the contracts are assigned heaviest first to the shard with the least total weight so far.
+    /// Contracts without a recorded duration weigh as much as the average contract.
+    pub fn select(&self, identifiers: impl IntoIterator<Item = String>) -> BTreeSet<String> {
+        let weights = match &self.weights {
+            Some(weights) => weights,
+            None => {
+                return identifiers
+                    .into_iter()
+                    .filter(|identifier| self.hash_shard(identifier) == self.index - 1)
+                    .collect()
+            }
+        };
+
+        let mut identifiers = identifiers.into_iter().collect::<Vec<_>>();
+        let known = identifiers.iter().filter_map(|identifier| weights.get(identifier));
+        let (total, count) = known
+            .fold((Duration::ZERO, 0u32), |(total, count), weight| (total + *weight, count + 1));
+        let average = if count > 0 { total / count } else { Duration::from_secs(1) };
+        let weight = |identifier: &String| weights.get(identifier).copied().unwrap_or(average);
+
+        // heaviest first, ties are broken by identifier to keep the partition deterministic
+        identifiers.sort_by(|a, b| weight(b).cmp(&weight(a)).then_with(|| a.cmp(b)));
+
+        let mut loads = vec![Duration::ZERO; self.count];
+        let mut selected = BTreeSet::new();
+        for identifier in identifiers {
+            let (shard, load) = loads
+                .iter_mut()
+                .enumerate()
+                .min_by_key(|(_, load)| **load)
+                .expect("there is at least one shard");
+            *load += weight(&identifier);
+            if shard == self.index - 1 {
+                selected.insert(identifier);
+            }
+        }
+        selected
+    }
+
+    /// Returns the 0-based index of the shard the identifier hashes to
+    fn hash_shard(&self, identifier: &str) -> usize {
+        let hash = keccak256(identifier.as_bytes());
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&hash[..8]);
+        (u64::from_be_bytes(word) % self.count as u64) as usize
+    }
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid shard: {s}, expected `<k>/<n>` with 1 <= k <= n");
+        let (index, count) = s.split_once('/').ok_or_else(invalid)?;
+        let index: usize = index.trim().parse().map_err(|_| invalid())?;
+        let count: usize = count.trim().parse().map_err(|_| invalid())?;
+        if index == 0 || index > count {
+            return Err(invalid())
+        }
+        Ok(Shard::new(index, count))
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+/// Reads the durations of the test contracts from a JSON report of `forge test --json`
+pub fn read_durations(path: impl AsRef<Path>) -> Result<BTreeMap<String, Duration>> {
+    #[derive(Deserialize)]
+    struct SuiteDuration {
+        duration: Duration,
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    let suites: BTreeMap<String, SuiteDuration> = serde_json::from_str(&content)?;
+    Ok(suites.into_iter().map(|(identifier, suite)| (identifier, suite.duration)).collect())
+}
+
+/// Merges the JSON reports of several shards into a single report.
+///
+/// Test contracts that are part of several reports have their results combined.
+pub fn merge_reports(
+    reports: impl IntoIterator<Item = BTreeMap<String, SuiteResult>>,
+) -> BTreeMap<String, SuiteResult> {
+    let mut merged = BTreeMap::<String, SuiteResult>::new();
+    for (identifier, suite) in reports.into_iter().flatten() {
+        match merged.get_mut(&identifier) {
+            Some(existing) => {
+                existing.duration += suite.duration;
+                existing.test_results.extend(suite.test_results);
+                existing.warnings.extend(suite.warnings);
+            }
+            None => {
+                merged.insert(identifier, suite);
+            }
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identifiers() -> Vec<String> {
+        (0..20).map(|i| format!("src/T{i}.t.sol:T{i}")).collect()
+    }
+
+    /// Asserts that the shards partition the identifiers
+    fn assert_partition(shards: &[BTreeSet<String>]) {
+        let mut all = shards.iter().flatten().cloned().collect::<Vec<_>>();
+        all.sort();
+        let mut expected = identifiers();
+        expected.sort();
+        assert_eq!(all, expected);
+    }
+
+    #[test]
+    fn can_parse_shard() {
+        assert_eq!("2/3".parse::<Shard>().unwrap(), Shard::new(2, 3));
+        assert_eq!(Shard::new(2, 3).to_string(), "2/3");
+        assert!("0/3".parse::<Shard>().is_err());
+        assert!("4/3".parse::<Shard>().is_err());
+        assert!("3".parse::<Shard>().is_err());
+    }
+
+    #[test]
+    fn can_shard_by_hash() {
+        let shards =
+            (1..=3).map(|index| Shard::new(index, 3).select(identifiers())).collect::<Vec<_>>();
+        assert_partition(&shards);
+
+        // the partition does not depend on the other contracts
+        let single = Shard::new(1, 3).select(identifiers().into_iter().take(5));
+        assert!(single.iter().all(|identifier| shards[0].contains(identifier)));
+    }
+
+    #[test]
+    fn can_shard_by_weight() {
+        // one slow contract and many fast ones, some without a recorded duration
+        let mut weights = identifiers()
+            .into_iter()
+            .skip(5)
+            .map(|identifier| (identifier, Duration::from_secs(1)))
+            .collect::<BTreeMap<_, _>>();
+        weights.insert(identifiers()[0].clone(), Duration::from_secs(10));
+
+        let shards = (1..=2)
+            .map(|index| Shard::new(index, 2).with_weights(weights.clone()).select(identifiers()))
+            .collect::<Vec<_>>();
+        assert_partition(&shards);
+
+        // the slow contract gets a shard mostly to itself
+        assert!(shards[0].contains(&identifiers()[0]));
+        assert!(shards[0].len() < shards[1].len());
+    }
+
+    #[test]
+    fn can_merge_reports() {
+        let suite = |duration: u64| {
+            SuiteResult::new(Duration::from_secs(duration), BTreeMap::new(), vec![])
+        };
+        let merged = merge_reports([
+            BTreeMap::from([("a".to_string(), suite(1)), ("b".to_string(), suite(2))]),
+            BTreeMap::from([("b".to_string(), suite(3)), ("c".to_string(), suite(4))]),
+        ]);
+        assert_eq!(merged.keys().collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(merged["b"].duration, Duration::from_secs(5));
+    }
+}
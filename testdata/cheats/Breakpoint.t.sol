@@ -0,0 +1,16 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract BreakpointTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testBreakpoint() public {
+        cheats.breakpoint("start");
+        uint256 a = 1;
+        cheats.breakpoint("end");
+        assertEq(a, 1);
+    }
+}
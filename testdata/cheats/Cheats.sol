@@ -68,4 +68,6 @@ interface Cheats {
     function getNonce(address) external returns(uint64);
     // Set block.chainid (newChainId)
     function chainId(uint256) external;
+    // Set a named breakpoint for the debugger to jump to
+    function breakpoint(string calldata) external;
 }
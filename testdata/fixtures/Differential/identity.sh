@@ -0,0 +1,5 @@
+#!/bin/sh
+# Reference implementation of a function that returns its arguments: strips the selector off the
+# calldata passed as the last argument
+calldata="$1"
+echo "0x${calldata#0x????????}"
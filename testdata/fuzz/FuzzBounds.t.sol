@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract FuzzBoundsTest is DSTest {
+  /// @custom:fuzz amount range 1..100
+  function testBoundedRange(uint256 amount) public {
+    assertTrue(amount >= 1 && amount <= 100);
+  }
+
+  /// @custom:fuzz addr notzero
+  function testNotZeroAddress(address addr) public {
+    assertTrue(addr != address(0));
+  }
+
+  /// @custom:fuzz amounts range 10..1e18
+  function testBoundedArray(uint64[] memory amounts) public {
+    for (uint256 i = 0; i < amounts.length; i++) {
+      assertTrue(amounts[i] >= 10 && amounts[i] <= 1e18);
+    }
+  }
+
+  /// @custom:fuzz missing notzero
+  function testInvalidConstraint(uint256 x) public {}
+}
@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract FuzzDifferentialTest is DSTest {
+  /// @custom:differential addAssembly
+  function testDifferentialAdd(uint64 a, uint64 b) public pure returns (uint256) {
+    return uint256(a) + b;
+  }
+
+  function addAssembly(uint64 a, uint64 b) public pure returns (uint256 c) {
+    assembly {
+      c := add(a, b)
+    }
+  }
+
+  /// @custom:differential buggyAdd
+  function testDifferentialMismatch(uint64 a, uint64 b) public pure returns (uint256) {
+    return uint256(a) + b;
+  }
+
+  function buggyAdd(uint64 a, uint64 b) public pure returns (uint256) {
+    if (a > 1000) {
+      return a;
+    }
+    return uint256(a) + b;
+  }
+
+  /// @custom:differential ffi sh fixtures/Differential/identity.sh
+  function testDifferentialFfi(uint256 x) public pure returns (uint256) {
+    return x;
+  }
+
+  /// @custom:differential addAssembly
+  function testFailDifferential(uint64 a, uint64 b) public pure returns (uint256) {
+    return uint256(a) + b;
+  }
+}
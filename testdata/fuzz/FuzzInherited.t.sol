@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./FuzzInheritedBase.t.sol";
+
+contract FuzzInheritedTest is FuzzInheritedBase {}
+
+contract FuzzTaggedTest is DSTest {
+  /// @custom:fuzz amount range 1..100
+  function testSameName(uint256 amount) public {
+    assertTrue(amount <= 100);
+  }
+}
+
+contract FuzzUntaggedTest is DSTest {
+  function testSameName(uint256 amount) public {
+    assertTrue(amount <= 100);
+  }
+}
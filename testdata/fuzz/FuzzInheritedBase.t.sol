@@ -0,0 +1,11 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+abstract contract FuzzInheritedBase is DSTest {
+  /// @custom:fuzz amount range 1..100
+  function testInheritedRange(uint256 amount) public {
+    assertTrue(amount >= 1 && amount <= 100);
+  }
+}
@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract LimitsTest is DSTest {
+  function testTimeout() public {
+    uint256 i;
+    while (true) {
+      i++;
+    }
+  }
+
+  /// @custom:gas-limit 50_000
+  function testFailGasLimit() public {
+    for (uint256 i = 0; i < 10_000; i++) {}
+  }
+
+  /// @custom:gas-limit 1_000_000
+  function testGasLimit() public {
+    for (uint256 i = 0; i < 100; i++) {}
+  }
+
+  /// @custom:gas-limit lots
+  function testInvalidGasLimit() public {}
+}
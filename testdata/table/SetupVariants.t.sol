@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract SetupVariantsTest is DSTest {
+  uint256 base;
+  uint256 amount;
+
+  function setUp() public {
+    base = 1;
+  }
+
+  function setUp_small() public {
+    amount = 10;
+  }
+
+  function setUp_large() public {
+    amount = 1e18;
+  }
+
+  function testBase() public {
+    assertEq(base, 1);
+  }
+
+  function testSmall() public {
+    assertLt(amount, 100);
+  }
+}
+
+contract SetupVariantFailTest is DSTest {
+  function setUp_broken() public {
+    require(false, "broken variant");
+  }
+
+  function setUp_working() public {}
+
+  function testVariant() public {}
+}
@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract TableTest is DSTest {
+  uint256[] amounts;
+
+  function setUp() public {
+    amounts.push(1);
+    amounts.push(10);
+    amounts.push(100);
+  }
+
+  function fixture_amount() public view returns (uint256[] memory) {
+    return amounts;
+  }
+
+  function fixture_expected() public pure returns (uint256[3] memory) {
+    return [uint256(2), 20, 201];
+  }
+
+  function fixture_owner() public pure returns (address[] memory) {
+    address[] memory owners = new address[](2);
+    owners[0] = address(1);
+    owners[1] = address(2);
+    return owners;
+  }
+
+  function testDouble(uint256 amount, uint256 expected) public {
+    assertEq(amount * 2, expected);
+  }
+
+  function testMismatchedFixtures(uint256 amount, address owner) public {}
+
+  function testFuzzWithFixture(uint256 amount, uint256 other) public {
+    // `other` has no fixture, so this is fuzzed with the fixture of `amount` generated often
+    require(amount != 10, "found fixture");
+    other;
+  }
+}
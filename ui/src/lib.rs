@@ -16,6 +16,7 @@ use std::{
     cmp::{max, min},
     collections::{BTreeMap, HashMap, VecDeque},
     io,
+    ops::Bound,
     sync::mpsc,
     thread,
     time::{Duration, Instant},
@@ -47,8 +48,12 @@ pub enum TUIExitReason {
 mod op_effects;
 use op_effects::stack_indices_affected;
 
+mod stepping;
+pub use stepping::LineBreakpoint;
+use stepping::SourceSteps;
+
 pub struct Tui {
-    debug_arena: Vec<(Address, Vec<DebugStep>, CallKind)>,
+    debug_arena: Vec<(Address, Vec<DebugStep>, CallKind, usize)>,
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     /// Buffer for keys prior to execution, i.e. '10' + 'k' => move up 10 operations
     key_buffer: String,
@@ -57,13 +62,17 @@ pub struct Tui {
     identified_contracts: HashMap<Address, String>,
     known_contracts: HashMap<String, ContractBytecodeSome>,
     source_code: BTreeMap<u32, String>,
+    /// source-level information about the debug steps, used for stepping line by line
+    source_steps: SourceSteps,
+    /// breakpoints by the position of the step they are set at, i.e. (call index, step index)
+    breakpoints: BTreeMap<(usize, usize), String>,
 }
 
 impl Tui {
     /// Create a tui
     #[allow(unused_must_use)]
     pub fn new(
-        debug_arena: Vec<(Address, Vec<DebugStep>, CallKind)>,
+        debug_arena: Vec<(Address, Vec<DebugStep>, CallKind, usize)>,
         current_step: usize,
         identified_contracts: HashMap<Address, String>,
         known_contracts: HashMap<String, ContractBytecodeSome>,
@@ -75,6 +84,8 @@ impl Tui {
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
         terminal.hide_cursor();
+        let source_steps =
+            SourceSteps::new(&debug_arena, &identified_contracts, &known_contracts, &source_code);
         Ok(Tui {
             debug_arena,
             terminal,
@@ -83,9 +94,46 @@ impl Tui {
             identified_contracts,
             known_contracts,
             source_code,
+            source_steps,
+            breakpoints: BTreeMap::new(),
         })
     }
 
+    /// Sets the breakpoints to jump between.
+    ///
+    /// Named breakpoints are given by their name and position in the debug arena, see
+    /// `DebugArena::breakpoints`. Line breakpoints are matched against the paths of the source
+    /// files, and stop at every step where execution enters that line.
+    #[must_use]
+    pub fn with_breakpoints(
+        mut self,
+        named: Vec<(String, usize, usize)>,
+        lines: Vec<LineBreakpoint>,
+        source_paths: &BTreeMap<u32, String>,
+    ) -> Self {
+        self.breakpoints.extend(named.into_iter().map(|(name, call, step)| ((call, step), name)));
+        for breakpoint in lines {
+            for (id, _) in source_paths.iter().filter(|(_, path)| breakpoint.matches(path)) {
+                for position in self.source_steps.line_positions(*id, breakpoint.line - 1) {
+                    self.breakpoints.entry(position).or_insert_with(|| {
+                        format!("{}:{}", breakpoint.file.display(), breakpoint.line)
+                    });
+                }
+            }
+        }
+        self
+    }
+
+    /// Moves to the given position in the debug arena, i.e. (call index, step index)
+    fn jump_to(&mut self, (call, step): (usize, usize), draw_memory: &mut DrawMemory) {
+        if call != draw_memory.inner_call_index {
+            draw_memory.inner_call_index = call;
+            draw_memory.current_mem_startline = 0;
+            draw_memory.current_stack_startline = 0;
+        }
+        self.current_step = step;
+    }
+
     /// Grab number from buffer. Used for something like '10k' to move up 10 operations
     fn buffer_as_number(buffer: &str, default_value: usize) -> usize {
         if let Ok(num) = buffer.parse() {
@@ -312,7 +360,7 @@ impl Tui {
         let block_controls = Block::default();
 
         let text_output = Text::from(Span::styled(
-            "[q]: quit | [k/j]: prev/next op | [a/s]: prev/next jump | [c/C]: prev/next call | [g/G]: start/end | [t]: toggle stack labels | [m]: toggle memory decoding | [shift + j/k]: scroll stack | [ctrl + j/k]: scroll memory",
+            "[q]: quit | [k/j]: prev/next op | [a/s]: prev/next jump | [c/C]: prev/next call | [n]: next line | [i/o]: step into/out | [b/B]: next/prev breakpoint | [g/G]: start/end | [t]: toggle stack labels | [m]: toggle memory decoding | [shift + j/k]: scroll stack | [ctrl + j/k]: scroll memory",
             Style::default().add_modifier(Modifier::DIM)
         ));
         let paragraph = Paragraph::new(text_output)
@@ -641,7 +689,7 @@ impl Tui {
     ) {
         let block_source_code = Block::default()
             .title(format!(
-                "Address: {} | PC: {} | Gas used in call: {}",
+                "Address: {} | PC: {} | Gas used in call: {}{}",
                 address,
                 if let Some(step) = debug_steps.get(current_step) {
                     step.pc.to_string()
@@ -649,6 +697,11 @@ impl Tui {
                     "END".to_string()
                 },
                 debug_steps[current_step].total_gas_used,
+                if let Some(breakpoint) = &draw_memory.breakpoint {
+                    format!(" | Breakpoint: {breakpoint}")
+                } else {
+                    String::new()
+                },
             ))
             .borders(Borders::ALL);
         let mut text_output: Vec<Spans> = Vec::new();
@@ -953,7 +1006,7 @@ impl Ui for Tui {
         self.terminal.clear()?;
         let mut draw_memory: DrawMemory = DrawMemory::default();
 
-        let debug_call: Vec<(Address, Vec<DebugStep>, CallKind)> = self.debug_arena.clone();
+        let debug_call: Vec<(Address, Vec<DebugStep>, CallKind, usize)> = self.debug_arena.clone();
         let mut opcode_list: Vec<String> =
             debug_call[0].1.iter().map(|step| step.pretty_opcode()).collect();
         let mut last_index = 0;
@@ -962,14 +1015,6 @@ impl Ui for Tui {
         let mut mem_utf = false;
         // UI thread that manages drawing
         loop {
-            if last_index != draw_memory.inner_call_index {
-                opcode_list = debug_call[draw_memory.inner_call_index]
-                    .1
-                    .iter()
-                    .map(|step| step.pretty_opcode())
-                    .collect();
-                last_index = draw_memory.inner_call_index;
-            }
             // Grab interrupt
             match rx.recv()? {
                 // Key press
@@ -1123,6 +1168,66 @@ impl Ui for Tui {
                         }
                         self.key_buffer.clear();
                     }
+                    // Step over to the next source line
+                    KeyCode::Char('n') => {
+                        for _ in 0..Tui::buffer_as_number(&self.key_buffer, 1) {
+                            let position = (draw_memory.inner_call_index, self.current_step);
+                            if let Some(next) = self.source_steps.next_line(position) {
+                                self.jump_to(next, &mut draw_memory);
+                            }
+                        }
+                        self.key_buffer.clear();
+                    }
+                    // Step into the next source line
+                    KeyCode::Char('i') => {
+                        for _ in 0..Tui::buffer_as_number(&self.key_buffer, 1) {
+                            let position = (draw_memory.inner_call_index, self.current_step);
+                            if let Some(next) = self.source_steps.step_into(position) {
+                                self.jump_to(next, &mut draw_memory);
+                            }
+                        }
+                        self.key_buffer.clear();
+                    }
+                    // Step out of the current function
+                    KeyCode::Char('o') => {
+                        for _ in 0..Tui::buffer_as_number(&self.key_buffer, 1) {
+                            let position = (draw_memory.inner_call_index, self.current_step);
+                            if let Some(next) = self.source_steps.step_out(position) {
+                                self.jump_to(next, &mut draw_memory);
+                            }
+                        }
+                        self.key_buffer.clear();
+                    }
+                    // Go to next breakpoint
+                    KeyCode::Char('b') => {
+                        for _ in 0..Tui::buffer_as_number(&self.key_buffer, 1) {
+                            let position = (draw_memory.inner_call_index, self.current_step);
+                            if let Some(next) = self
+                                .breakpoints
+                                .range((Bound::Excluded(position), Bound::Unbounded))
+                                .next()
+                                .map(|(next, _)| *next)
+                            {
+                                self.jump_to(next, &mut draw_memory);
+                            }
+                        }
+                        self.key_buffer.clear();
+                    }
+                    // Go to previous breakpoint
+                    KeyCode::Char('B') => {
+                        for _ in 0..Tui::buffer_as_number(&self.key_buffer, 1) {
+                            let position = (draw_memory.inner_call_index, self.current_step);
+                            if let Some(prev) = self
+                                .breakpoints
+                                .range(..position)
+                                .next_back()
+                                .map(|(prev, _)| *prev)
+                            {
+                                self.jump_to(prev, &mut draw_memory);
+                            }
+                        }
+                        self.key_buffer.clear();
+                    }
                     // toggle stack labels
                     KeyCode::Char('t') => {
                         stack_labels = !stack_labels;
@@ -1170,8 +1275,18 @@ impl Ui for Tui {
                 },
                 Interrupt::IntervalElapsed => {}
             }
+            if last_index != draw_memory.inner_call_index {
+                opcode_list = debug_call[draw_memory.inner_call_index]
+                    .1
+                    .iter()
+                    .map(|step| step.pretty_opcode())
+                    .collect();
+                last_index = draw_memory.inner_call_index;
+            }
             // Draw
             let current_step = self.current_step;
+            draw_memory.breakpoint =
+                self.breakpoints.get(&(draw_memory.inner_call_index, current_step)).cloned();
             self.terminal.draw(|f| {
                 Tui::draw_layout(
                     f,
@@ -1206,6 +1321,7 @@ struct DrawMemory {
     pub inner_call_index: usize,
     pub current_mem_startline: usize,
     pub current_stack_startline: usize,
+    pub breakpoint: Option<String>,
 }
 
 impl DrawMemory {
@@ -1215,6 +1331,7 @@ impl DrawMemory {
             inner_call_index: 0,
             current_mem_startline: 0,
             current_stack_startline: 0,
+            breakpoint: None,
         }
     }
 }
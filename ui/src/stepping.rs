@@ -0,0 +1,349 @@
+use ethers::{
+    solc::{
+        artifacts::ContractBytecodeSome,
+        sourcemap::{Jump, SourceElement},
+    },
+    types::Address,
+};
+use forge::{
+    debug::{DebugStep, Instruction},
+    CallKind,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// A breakpoint on a line of a source file, given as `<FILE>:<LINE>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineBreakpoint {
+    /// The path of the source file, which may be a suffix of the full path
+    pub file: PathBuf,
+    /// The line number, starting at 1
+    pub line: usize,
+}
+
+impl LineBreakpoint {
+    /// Whether the breakpoint is set in the source file at the given path
+    pub fn matches(&self, path: impl AsRef<Path>) -> bool {
+        path.as_ref().ends_with(&self.file)
+    }
+}
+
+impl FromStr for LineBreakpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (file, line) =
+            s.rsplit_once(':').ok_or_else(|| format!("expected <FILE>:<LINE>, got `{s}`"))?;
+        let line = line.parse::<usize>().map_err(|err| format!("invalid line `{line}`: {err}"))?;
+        if file.is_empty() || line == 0 {
+            return Err(format!("expected <FILE>:<LINE>, got `{s}`"))
+        }
+        Ok(Self { file: file.into(), line })
+    }
+}
+
+/// The source location and frame depth of a single debug step
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct StepInfo {
+    /// The index of the source file and the line (starting at 0) being executed, if known
+    location: Option<(u32, usize)>,
+    /// The depth of the external call, and of the internal function calls within it
+    depth: (usize, usize),
+}
+
+/// Source-level information about every step of the flattened debug arena, used to step through
+/// it line by line rather than opcode by opcode.
+///
+/// Positions are given as `(call, step)` indices into the flattened arena, where the steps of a
+/// caller are split into separate calls around each of its sub-calls, see `DebugArena::flatten`.
+#[derive(Debug, Default)]
+pub(crate) struct SourceSteps {
+    steps: Vec<Vec<StepInfo>>,
+    /// Every position, in the order the steps were executed
+    order: Vec<(usize, usize)>,
+}
+
+impl SourceSteps {
+    pub(crate) fn new(
+        debug_arena: &[(Address, Vec<DebugStep>, CallKind, usize)],
+        identified_contracts: &HashMap<Address, String>,
+        known_contracts: &HashMap<String, ContractBytecodeSome>,
+        source_code: &BTreeMap<u32, String>,
+    ) -> Self {
+        let mut sourcemaps: HashMap<(Address, bool), Option<Vec<SourceElement>>> = HashMap::new();
+        // the internal call depth of every external call that is currently executing, by depth
+        let mut internal_depths: Vec<usize> = Vec::new();
+        let mut previous_depth = None;
+
+        let steps: Vec<Vec<StepInfo>> = debug_arena
+            .iter()
+            .map(|(address, steps, kind, depth)| {
+                // a call deeper than the previous one is a new external call, otherwise execution
+                // returned to the caller and resumes at the internal depth it left off at
+                let is_new_call = previous_depth.map_or(true, |previous| previous < *depth);
+                previous_depth = Some(*depth);
+                internal_depths.truncate(if is_new_call { *depth } else { *depth + 1 });
+                internal_depths.resize(*depth + 1, 0);
+                let internal_depth = &mut internal_depths[*depth];
+
+                let is_create = matches!(kind, CallKind::Create);
+                let sourcemap = sourcemaps.entry((*address, is_create)).or_insert_with(|| {
+                    let known = known_contracts.get(identified_contracts.get(address)?)?;
+                    let sourcemap = if is_create {
+                        known.bytecode.source_map()
+                    } else {
+                        known.deployed_bytecode.bytecode.as_ref()?.source_map()
+                    };
+                    sourcemap?.ok()
+                });
+
+                steps
+                    .iter()
+                    .map(|step| {
+                        let element = match step.instruction {
+                            Instruction::OpCode(_) => {
+                                sourcemap.as_ref().and_then(|sourcemap| sourcemap.get(step.ic))
+                            }
+                            Instruction::Cheatcode(_) => None,
+                        };
+                        let info = StepInfo {
+                            location: element.and_then(|element| {
+                                let source = source_code.get(&element.index?)?;
+                                let line = source
+                                    .as_bytes()
+                                    .get(..element.offset)?
+                                    .iter()
+                                    .filter(|byte| **byte == b'\n')
+                                    .count();
+                                Some((element.index?, line))
+                            }),
+                            depth: (*depth, *internal_depth),
+                        };
+                        match element.map(|element| &element.jump) {
+                            Some(Jump::In) => *internal_depth += 1,
+                            Some(Jump::Out) => *internal_depth = internal_depth.saturating_sub(1),
+                            _ => {}
+                        }
+                        info
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let order = steps
+            .iter()
+            .enumerate()
+            .flat_map(|(call, steps)| (0..steps.len()).map(move |step| (call, step)))
+            .collect();
+
+        Self { steps, order }
+    }
+
+    /// Returns the positions of the first step of every run of steps on the given line
+    pub(crate) fn line_positions(&self, source: u32, line: usize) -> Vec<(usize, usize)> {
+        let mut previous = None;
+        self.positions()
+            .filter(|position| {
+                let location = self.info(*position).location;
+                let is_start = location == Some((source, line)) && location != previous;
+                previous = location;
+                is_start
+            })
+            .collect()
+    }
+
+    /// Returns the position of the next line executed in the current function, or in its caller
+    /// if it returns first
+    pub(crate) fn next_line(&self, position: (usize, usize)) -> Option<(usize, usize)> {
+        let current = self.info(position);
+        self.positions_after(position).find(|next| {
+            let info = self.info(*next);
+            info.location.is_some() &&
+                info.location != current.location &&
+                info.depth <= current.depth
+        })
+    }
+
+    /// Returns the position of the next line executed, stepping into any function calls
+    pub(crate) fn step_into(&self, position: (usize, usize)) -> Option<(usize, usize)> {
+        let current = self.info(position);
+        self.positions_after(position).find(|next| {
+            let info = self.info(*next);
+            info.location.is_some() && info.location != current.location
+        })
+    }
+
+    /// Returns the position of the next line executed after the current function returns
+    pub(crate) fn step_out(&self, position: (usize, usize)) -> Option<(usize, usize)> {
+        let current = self.info(position);
+        self.positions_after(position).find(|next| {
+            let info = self.info(*next);
+            info.location.is_some() && info.depth < current.depth
+        })
+    }
+
+    fn info(&self, (call, step): (usize, usize)) -> StepInfo {
+        self.steps.get(call).and_then(|steps| steps.get(step)).copied().unwrap_or_default()
+    }
+
+    /// Iterates over all positions in execution order
+    fn positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.order.iter().copied()
+    }
+
+    /// Iterates over all positions executed after the given one
+    fn positions_after(
+        &self,
+        position: (usize, usize),
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let start = self
+            .order
+            .iter()
+            .position(|other| *other == position)
+            .map_or(self.order.len(), |index| index + 1);
+        self.order[start..].iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: Address = Address::repeat_byte(0xa);
+    const B: Address = Address::repeat_byte(0xb);
+
+    fn step(ic: usize) -> DebugStep {
+        DebugStep {
+            ic,
+            instruction: Instruction::OpCode(revm::opcode::JUMPDEST),
+            ..Default::default()
+        }
+    }
+
+    fn contract(source_map: &str) -> ContractBytecodeSome {
+        serde_json::from_value(serde_json::json!({
+            "abi": [],
+            "bytecode": { "object": "0x" },
+            "deployedBytecode": { "object": "0x", "sourceMap": source_map },
+        }))
+        .unwrap()
+    }
+
+    /// Builds the source steps of calls to `A` and `B`, whose sources are files 0 and 1 made up of
+    /// 3-byte lines
+    fn source_steps(
+        debug_arena: &[(Address, Vec<DebugStep>, CallKind, usize)],
+        source_map_a: &str,
+        source_map_b: &str,
+    ) -> SourceSteps {
+        let identified_contracts = HashMap::from([(A, "A".to_string()), (B, "B".to_string())]);
+        let known_contracts = HashMap::from([
+            ("A".to_string(), contract(source_map_a)),
+            ("B".to_string(), contract(source_map_b)),
+        ]);
+        let source: String = (0..10).map(|line| format!("l{line}\n")).collect();
+        let source_code = BTreeMap::from([(0, source.clone()), (1, source)]);
+        SourceSteps::new(debug_arena, &identified_contracts, &known_contracts, &source_code)
+    }
+
+    /// `A` runs lines 2 and 3, jumping into the internal function on line 7 from line 2 and
+    /// calling `B` (lines 1 and 2) from line 3, before running lines 4 and 5
+    fn call_steps() -> SourceSteps {
+        let debug_arena = [
+            (A, (0..5).map(step).collect(), CallKind::Call, 0),
+            (B, (0..2).map(step).collect(), CallKind::Call, 1),
+            (A, (5..7).map(step).collect(), CallKind::Call, 0),
+        ];
+        source_steps(
+            &debug_arena,
+            "6:1:0:-;6:1:0:i;21:1:0:-;21:1:0:o;9:1:0:-;12:1:0:-;15:1:0:-",
+            "3:1:1:-;6:1:1:-",
+        )
+    }
+
+    #[test]
+    fn can_parse_line_breakpoint() {
+        assert_eq!(
+            "src/Contract.sol:12".parse::<LineBreakpoint>().unwrap(),
+            LineBreakpoint { file: "src/Contract.sol".into(), line: 12 }
+        );
+        assert_eq!(
+            "C:/src/Contract.sol:1".parse::<LineBreakpoint>().unwrap(),
+            LineBreakpoint { file: "C:/src/Contract.sol".into(), line: 1 }
+        );
+        assert!("src/Contract.sol".parse::<LineBreakpoint>().is_err());
+        assert!("src/Contract.sol:".parse::<LineBreakpoint>().is_err());
+        assert!("src/Contract.sol:0".parse::<LineBreakpoint>().is_err());
+        assert!("src/Contract.sol:x".parse::<LineBreakpoint>().is_err());
+        assert!(":12".parse::<LineBreakpoint>().is_err());
+    }
+
+    #[test]
+    fn can_match_line_breakpoint() {
+        let breakpoint: LineBreakpoint = "Contract.sol:12".parse().unwrap();
+        assert!(breakpoint.matches("src/Contract.sol"));
+        assert!(!breakpoint.matches("src/OtherContract.sol"));
+    }
+
+    #[test]
+    fn can_find_line_positions() {
+        let steps = call_steps();
+        assert_eq!(steps.line_positions(0, 2), vec![(0, 0)]);
+        assert_eq!(steps.line_positions(0, 7), vec![(0, 2)]);
+        assert_eq!(steps.line_positions(0, 4), vec![(2, 0)]);
+        assert_eq!(steps.line_positions(1, 1), vec![(1, 0)]);
+        assert!(steps.line_positions(0, 9).is_empty());
+    }
+
+    #[test]
+    fn can_step_over_calls() {
+        let steps = call_steps();
+        // over the internal call
+        assert_eq!(steps.next_line((0, 0)), Some((0, 4)));
+        // over the external call
+        assert_eq!(steps.next_line((0, 4)), Some((2, 0)));
+        // back into the caller after the external call returns
+        assert_eq!(steps.next_line((1, 0)), Some((1, 1)));
+        assert_eq!(steps.next_line((1, 1)), Some((2, 0)));
+        // back into the caller after the internal call returns
+        assert_eq!(steps.next_line((0, 2)), Some((0, 4)));
+        assert_eq!(steps.next_line((2, 1)), None);
+    }
+
+    #[test]
+    fn can_step_into_calls() {
+        let steps = call_steps();
+        assert_eq!(steps.step_into((0, 0)), Some((0, 2)));
+        assert_eq!(steps.step_into((0, 2)), Some((0, 4)));
+        assert_eq!(steps.step_into((0, 4)), Some((1, 0)));
+        assert_eq!(steps.step_into((1, 1)), Some((2, 0)));
+        assert_eq!(steps.step_into((2, 1)), None);
+    }
+
+    #[test]
+    fn can_step_out_of_calls() {
+        let steps = call_steps();
+        assert_eq!(steps.step_out((0, 2)), Some((0, 4)));
+        assert_eq!(steps.step_out((1, 0)), Some((2, 0)));
+        assert_eq!(steps.step_out((0, 4)), None);
+    }
+
+    #[test]
+    fn resets_internal_depth_for_new_calls() {
+        // the first call to `B` returns from within an internal function
+        let debug_arena = [
+            (A, vec![step(0)], CallKind::Call, 0),
+            (B, vec![step(0), step(1)], CallKind::Call, 1),
+            (A, vec![step(1)], CallKind::Call, 0),
+            (B, vec![step(0)], CallKind::Call, 1),
+            (A, vec![step(2)], CallKind::Call, 0),
+        ];
+        let steps = source_steps(&debug_arena, "3:1:0:-;6:1:0:-;9:1:0:-", "3:1:1:i;6:1:1:-");
+
+        assert_eq!(steps.info((1, 1)).depth, (1, 1));
+        assert_eq!(steps.info((3, 0)).depth, (1, 0));
+        assert_eq!(steps.step_out((3, 0)), Some((4, 0)));
+    }
+}
@@ -14,16 +14,21 @@ use ethers_solc::{
     ArtifactId,
 };
 use eyre::{Result, WrapErr};
+use once_cell::sync::Lazy;
 use serde::Deserialize;
 use std::{
     collections::{BTreeMap, HashSet},
     env::VarError,
     fmt,
     str::FromStr,
+    sync::RwLock,
 };
 
 use tokio::runtime::{Handle, Runtime};
 
+pub mod signatures;
+pub use signatures::SignaturesCache;
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum RuntimeOrHandle {
@@ -544,8 +549,20 @@ pub fn encode_args(func: &Function, args: &[impl AsRef<str>]) -> Result<Vec<u8>>
     Ok(func.encode_input(&tokens)?)
 }
 
-/// Fetches a function signature given the selector using 4byte.directory
+/// Fetches a function signature given the selector, from the local signatures cache if it knows the
+/// selector and using 4byte.directory otherwise.
+///
+/// Signatures found on 4byte.directory are added to the cache. Cached signatures have an id of 0.
 pub async fn fourbyte(selector: &str) -> Result<Vec<(String, i32)>> {
+    let cached = cached_signatures(selector, false);
+    if !cached.is_empty() {
+        return Ok(cached)
+    }
+    fourbyte_directory(selector).await
+}
+
+/// Fetches a function signature given the selector using 4byte.directory
+async fn fourbyte_directory(selector: &str) -> Result<Vec<(String, i32)>> {
     #[derive(Deserialize)]
     struct Decoded {
         text_signature: String,
@@ -577,6 +594,12 @@ pub async fn fourbyte(selector: &str) -> Result<Vec<(String, i32)>> {
         eyre::bail!("no signature found for provided function selector")
     }
 
+    let mut cache = SignaturesCache::default();
+    api_response.results.iter().for_each(|d| {
+        cache.add_function(&d.text_signature);
+    });
+    remember_signatures(&cache);
+
     Ok(api_response
         .results
         .into_iter()
@@ -584,8 +607,39 @@ pub async fn fourbyte(selector: &str) -> Result<Vec<(String, i32)>> {
         .collect::<Vec<(String, i32)>>())
 }
 
+/// The local signatures cache, which is only read once per process
+static SIGNATURES: Lazy<RwLock<SignaturesCache>> =
+    Lazy::new(|| RwLock::new(SignaturesCache::load()));
+
+/// Adds signatures fetched from 4byte.directory to the local signatures cache
+fn remember_signatures(cache: &SignaturesCache) {
+    if let Ok(mut signatures) = SIGNATURES.write() {
+        signatures.extend(cache);
+    }
+    let _ = SignaturesCache::save(cache);
+}
+
+/// Returns the signatures the local signatures cache knows for the hex encoded function selector,
+/// or event topic if `event` is set, with an id of 0
+fn cached_signatures(selector: &str, event: bool) -> Vec<(String, i32)> {
+    let selector = selector.strip_prefix("0x").unwrap_or(selector);
+    let len = if event { 64 } else { 8 };
+    let selector = match selector.get(..len).and_then(|selector| hex::decode(selector).ok()) {
+        Some(selector) => selector,
+        None => return Vec::new(),
+    };
+    let cache = match SIGNATURES.read() {
+        Ok(cache) => cache,
+        Err(_) => return Vec::new(),
+    };
+    let sigs = if event { cache.events(&selector) } else { cache.functions(&selector) };
+    sigs.iter().map(|sig| (signatures::strip_indexed(sig), 0)).collect()
+}
+
 pub async fn fourbyte_possible_sigs(calldata: &str, id: Option<String>) -> Result<Vec<String>> {
-    let mut sigs = fourbyte(calldata).await?;
+    // the ids of signatures are only known to 4byte.directory
+    let mut sigs =
+        if id.is_some() { fourbyte_directory(calldata).await? } else { fourbyte(calldata).await? };
 
     match id {
         Some(id) => {
@@ -627,7 +681,10 @@ pub async fn fourbyte_possible_sigs(calldata: &str, id: Option<String>) -> Resul
     }
 }
 
-/// Fetches a event signature given the 32 byte topic using 4byte.directory
+/// Fetches a event signature given the 32 byte topic, from the local signatures cache if it knows
+/// the topic and using 4byte.directory otherwise.
+///
+/// Signatures found on 4byte.directory are added to the cache. Cached signatures have an id of 0.
 pub async fn fourbyte_event(topic: &str) -> Result<Vec<(String, i32)>> {
     #[derive(Deserialize)]
     struct Decoded {
@@ -644,12 +701,22 @@ pub async fn fourbyte_event(topic: &str) -> Result<Vec<(String, i32)>> {
     if topic.len() < 64 {
         return Err(eyre::eyre!("Invalid topic"))
     }
+    let cached = cached_signatures(topic, true);
+    if !cached.is_empty() {
+        return Ok(cached)
+    }
     let topic = &topic[..8];
 
     let url = format!("https://www.4byte.directory/api/v1/event-signatures/?hex_signature={topic}");
     let res = reqwest::get(url).await?;
     let api_response = res.json::<ApiResponse>().await?;
 
+    let mut cache = SignaturesCache::default();
+    api_response.results.iter().for_each(|d| {
+        cache.add_event(&d.text_signature);
+    });
+    remember_signatures(&cache);
+
     Ok(api_response
         .results
         .into_iter()
@@ -659,6 +726,8 @@ pub async fn fourbyte_event(topic: &str) -> Result<Vec<(String, i32)>> {
 
 /// Pretty print calldata and if available, fetch possible function signatures
 ///
+/// Offline, only the local signatures cache is consulted.
+///
 /// ```no_run
 /// 
 /// use foundry_utils::pretty_calldata;
@@ -678,7 +747,7 @@ pub async fn pretty_calldata(calldata: impl AsRef<str>, offline: bool) -> Result
         calldata.get(..8).ok_or_else(|| eyre::eyre!("calldata cannot be less that 4 bytes"))?;
 
     let sigs = if offline {
-        vec![]
+        cached_signatures(selector, false).into_iter().map(|sig| sig.0).collect()
     } else {
         fourbyte(selector).await.unwrap_or_default().into_iter().map(|sig| sig.0).collect()
     };
@@ -0,0 +1,310 @@
+//! A persistent cache of function and event signatures
+
+use ethers_core::{
+    abi::{Abi, Event, Function},
+    utils::keccak256,
+};
+use eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The environment variable that overrides the path of the signatures cache
+pub const SIGNATURES_CACHE_ENV: &str = "FOUNDRY_SIGNATURES_CACHE";
+
+/// A cache of function and event signatures, keyed by their selector and topic.
+///
+/// The cache is stored under the Foundry home directory, see [`SignaturesCache::default_path`], and
+/// is filled with the ABIs of compiled projects and the results of 4byte.directory lookups, so
+/// selectors can be resolved offline.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignaturesCache {
+    /// Function signatures by their `0x` prefixed selector, e.g. `transfer(address,uint256)`
+    #[serde(default)]
+    pub functions: BTreeMap<String, Vec<String>>,
+    /// Event signatures by their `0x` prefixed topic, e.g. `Transfer(address,address,uint256)`
+    ///
+    /// Indexed parameters are marked as such when known, e.g.
+    /// `Transfer(address indexed,address indexed,uint256)`.
+    #[serde(default)]
+    pub events: BTreeMap<String, Vec<String>>,
+}
+
+impl SignaturesCache {
+    /// Returns the path of the signatures cache `~/.foundry/cache/signatures.json`, or the path
+    /// set with the `FOUNDRY_SIGNATURES_CACHE` environment variable
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(SIGNATURES_CACHE_ENV) {
+            return Some(path.into())
+        }
+        dirs_next::home_dir().map(|p| p.join(".foundry").join("cache").join("signatures.json"))
+    }
+
+    /// Loads the cache at the default path, or an empty cache if there is none
+    pub fn load() -> Self {
+        Self::default_path().and_then(|path| Self::read(path).ok()).unwrap_or_default()
+    }
+
+    /// Reads a cache file.
+    ///
+    /// Besides the cache format, this also accepts ABIs and artifacts with an `abi` field.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        if let Ok(cache) = serde_json::from_str::<Self>(&content) {
+            return Ok(cache)
+        }
+
+        #[derive(Deserialize)]
+        struct Artifact {
+            abi: Abi,
+        }
+        let abi = serde_json::from_str::<Abi>(&content)
+            .or_else(|_| serde_json::from_str::<Artifact>(&content).map(|artifact| artifact.abi))
+            .wrap_err_with(|| {
+                format!("{} is neither a signatures file nor an ABI", path.display())
+            })?;
+        let mut cache = Self::default();
+        cache.add_abi(&abi);
+        Ok(cache)
+    }
+
+    /// Writes the cache to a file.
+    ///
+    /// The cache is written to a temporary file first, which is then moved into place, so readers
+    /// never see a partially written file.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // a unique name for every write, so concurrent writes don't clobber each other's file
+        static WRITES: AtomicUsize = AtomicUsize::new(0);
+        let file_name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        let tmp = path.with_file_name(format!(
+            ".{file_name}.{}.{}.tmp",
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)
+            .wrap_err_with(|| format!("failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, path).map_err(|err| {
+            let _ = std::fs::remove_file(&tmp);
+            eyre::Report::new(err).wrap_err(format!("failed to write {}", path.display()))
+        })
+    }
+
+    /// Merges the signatures of `other` into the cache at the default path.
+    ///
+    /// See [`SignaturesCache::merge_into`].
+    pub fn save(other: &Self) -> Result<()> {
+        match Self::default_path() {
+            Some(path) => Self::merge_into(path, other).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Merges the signatures of `other` into the cache file at `path` and returns the number of
+    /// new signatures.
+    ///
+    /// The cache is only written if it did not know all of the signatures already. A cache file
+    /// that can not be parsed is left untouched and an error is returned instead.
+    pub fn merge_into(path: impl AsRef<Path>, other: &Self) -> Result<usize> {
+        let path = path.as_ref();
+        let read = || if path.exists() { Self::read(path) } else { Ok(Self::default()) };
+        let mut cache = read()?;
+        let known = cache.len();
+        if !cache.extend(other) {
+            return Ok(0)
+        }
+        // the file is read again right before it is replaced, so signatures added by concurrent
+        // runs in the meantime are kept
+        let mut latest = read()?;
+        latest.extend(&cache);
+        latest.write(path)?;
+        Ok(cache.len() - known)
+    }
+
+    /// Adds all functions and events of an ABI, returns `true` if any of them were new
+    pub fn add_abi(&mut self, abi: &Abi) -> bool {
+        let mut added = false;
+        for function in abi.functions() {
+            added |= self.add_function(&function_signature(function));
+        }
+        for event in abi.events() {
+            added |= self.add_event(&event_signature(event));
+        }
+        added
+    }
+
+    /// Adds a function signature like `transfer(address,uint256)`, returns `true` if it was new
+    pub fn add_function(&mut self, signature: &str) -> bool {
+        let signature = signature.trim().trim_start_matches("function ");
+        let selector = format!("0x{}", hex::encode(&keccak256(signature)[..4]));
+        insert(&mut self.functions, selector, signature)
+    }
+
+    /// Adds an event signature like `Transfer(address,address,uint256)`, returns `true` if it was
+    /// new
+    pub fn add_event(&mut self, signature: &str) -> bool {
+        let signature = signature.trim().trim_start_matches("event ");
+        let topic = format!("0x{}", hex::encode(keccak256(strip_indexed(signature))));
+        insert(&mut self.events, topic, signature)
+    }
+
+    /// Returns the known signatures of the functions with the selector
+    pub fn functions(&self, selector: &[u8]) -> &[String] {
+        self.functions.get(&format!("0x{}", hex::encode(selector))).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the known signatures of the events with the topic
+    pub fn events(&self, topic: &[u8]) -> &[String] {
+        self.events.get(&format!("0x{}", hex::encode(topic))).map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds all signatures of `other`, returns `true` if any of them were new
+    pub fn extend(&mut self, other: &Self) -> bool {
+        let mut added = false;
+        for signature in other.functions.values().flatten() {
+            added |= self.add_function(signature);
+        }
+        for signature in other.events.values().flatten() {
+            added |= self.add_event(signature);
+        }
+        added
+    }
+
+    /// Returns the number of known signatures
+    pub fn len(&self) -> usize {
+        self.functions.values().chain(self.events.values()).map(Vec::len).sum()
+    }
+
+    /// Returns `true` if the cache does not know any signatures
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Returns the signature of a function without its outputs, e.g. `transfer(address,uint256)`
+pub fn function_signature(function: &Function) -> String {
+    let params =
+        function.inputs.iter().map(|param| param.kind.to_string()).collect::<Vec<_>>().join(",");
+    format!("{}({params})", function.name)
+}
+
+/// Returns the signature of an event with its indexed parameters marked, e.g.
+/// `Transfer(address indexed,address indexed,uint256)`
+pub fn event_signature(event: &Event) -> String {
+    let params =
+        event
+            .inputs
+            .iter()
+            .map(|param| {
+                if param.indexed {
+                    format!("{} indexed", param.kind)
+                } else {
+                    param.kind.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+    format!("{}({params})", event.name)
+}
+
+/// Removes the `indexed` markers of an event signature
+pub fn strip_indexed(signature: &str) -> String {
+    signature.replace(" indexed", "")
+}
+
+/// Inserts the signature under the key, unless it's already known, and returns `true` if it was
+/// new.
+///
+/// An event signature with indexed parameters replaces the same signature without them.
+fn insert(map: &mut BTreeMap<String, Vec<String>>, key: String, signature: &str) -> bool {
+    let signatures = map.entry(key).or_default();
+    if signatures.iter().any(|known| known == signature) {
+        return false
+    }
+    let plain = strip_indexed(signature);
+    if let Some(known) = signatures.iter_mut().find(|known| strip_indexed(known) == plain) {
+        if known.contains(" indexed") {
+            return false
+        }
+        *known = signature.to_string();
+    } else {
+        signatures.push(signature.to_string());
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::abi::parse_abi;
+
+    #[test]
+    fn can_cache_signatures() {
+        let abi = parse_abi(&[
+            "function transfer(address,uint256) returns (bool)",
+            "event Transfer(address indexed from, address indexed to, uint256 amount)",
+        ])
+        .unwrap();
+
+        let mut cache = SignaturesCache::default();
+        assert!(cache.add_event("Transfer(address,address,uint256)"));
+        assert!(cache.add_abi(&abi));
+        assert!(!cache.add_abi(&abi));
+        assert!(!cache.add_event("Transfer(address,address,uint256)"));
+
+        assert_eq!(cache.functions(&[0xa9, 0x05, 0x9c, 0xbb]), ["transfer(address,uint256)"]);
+        let topic = hex::decode("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef")
+            .unwrap();
+        assert_eq!(cache.events(&topic), ["Transfer(address indexed,address indexed,uint256)"]);
+        assert!(cache.functions(&[0, 0, 0, 0]).is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signatures.json");
+        cache.write(&path).unwrap();
+        assert_eq!(SignaturesCache::read(&path).unwrap(), cache);
+
+        // ABIs can be imported as well
+        std::fs::write(
+            &path,
+            r#"{"abi": [
+                {"type": "function", "name": "transfer", "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}], "outputs": [{"name": "", "type": "bool"}], "stateMutability": "nonpayable"},
+                {"type": "event", "name": "Transfer", "inputs": [{"name": "from", "type": "address", "indexed": true}, {"name": "to", "type": "address", "indexed": true}, {"name": "amount", "type": "uint256", "indexed": false}], "anonymous": false}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(SignaturesCache::read(&path).unwrap(), cache);
+    }
+
+    #[test]
+    fn can_merge_into_cache_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache").join("signatures.json");
+
+        let mut cache = SignaturesCache::default();
+        cache.add_function("transfer(address,uint256)");
+        assert_eq!(SignaturesCache::merge_into(&path, &cache).unwrap(), 1);
+        assert_eq!(SignaturesCache::merge_into(&path, &cache).unwrap(), 0);
+
+        let mut other = SignaturesCache::default();
+        other.add_function("approve(address,uint256)");
+        assert_eq!(SignaturesCache::merge_into(&path, &other).unwrap(), 1);
+        assert_eq!(SignaturesCache::read(&path).unwrap().len(), 2);
+
+        // no temporary files are left behind
+        assert_eq!(std::fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+
+        // a corrupt cache file is not overwritten
+        std::fs::write(&path, "{\"functions\": ").unwrap();
+        assert!(SignaturesCache::merge_into(&path, &cache).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{\"functions\": ");
+    }
+}